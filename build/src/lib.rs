@@ -0,0 +1,131 @@
+//! Build script helper that sets a default `DEFMT_LOG` filter per cargo profile
+//!
+//! The defaults are read from the `[package.metadata.defmt.log]` table of the package's
+//! `Cargo.toml`. Keys are the profile kinds cargo reports to build scripts, `debug` (or its alias
+//! `dev`) and `release`; values are `DEFMT_LOG` filters.
+//!
+//! ```toml
+//! [package.metadata.defmt.log]
+//! debug = "trace"
+//! release = "warn"
+//!
+//! [build-dependencies]
+//! defmt-build = "0.1"
+//! ```
+//!
+//! ```no_run
+//! // in `main` of build.rs
+//! defmt_build::configure();
+//! ```
+//!
+//! A `DEFMT_LOG` variable set in the environment always takes precedence over the defaults.
+//!
+//! The filter only applies to the crates of the package whose build script calls [`configure`];
+//! dependencies keep reading `DEFMT_LOG` from the environment.
+
+use std::{env, fs, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context as _};
+
+/// Sets the default `DEFMT_LOG` filter for the profile being built.
+///
+/// Must be called from a build script. Panics if `Cargo.toml` can't be read or the
+/// `[package.metadata.defmt.log]` table is malformed.
+pub fn configure() {
+    if let Err(e) = try_configure() {
+        panic!("defmt-build: {e:#}");
+    }
+}
+
+fn try_configure() -> Result<(), anyhow::Error> {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-env-changed=DEFMT_LOG");
+
+    if env::var_os("DEFMT_LOG").is_some() {
+        return Ok(());
+    }
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").context("not run from a build script")?;
+    let profile = env::var("PROFILE").context("not run from a build script")?;
+    let path = PathBuf::from(manifest_dir).join("Cargo.toml");
+    let manifest =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    if let Some(filter) = default_filter(&manifest, &profile)? {
+        println!("cargo:rustc-env=DEFMT_LOG={filter}");
+    }
+    Ok(())
+}
+
+/// Looks up the filter for `profile` in the `[package.metadata.defmt.log]` table of `manifest`.
+fn default_filter(manifest: &str, profile: &str) -> Result<Option<String>, anyhow::Error> {
+    let manifest = manifest.parse::<toml::Table>()?;
+    let Some(log) = ["package", "metadata", "defmt", "log"]
+        .iter()
+        .try_fold(&manifest, |table, key| table.get(*key)?.as_table())
+    else {
+        return Ok(None);
+    };
+
+    let keys: &[&str] = match profile {
+        "debug" => &["debug", "dev"],
+        _ => &[profile],
+    };
+    let mut values = keys.iter().filter_map(|key| log.get(*key));
+    let Some(value) = values.next() else {
+        return Ok(None);
+    };
+    if values.next().is_some() {
+        bail!("both `debug` and `dev` are set in `[package.metadata.defmt.log]`");
+    }
+
+    let filter = value
+        .as_str()
+        .ok_or_else(|| anyhow!("`{profile}` log filter must be a string"))?;
+    Ok(Some(filter.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[package.metadata.defmt.log]
+dev = "trace"
+release = "warn,app::radio=info"
+"#;
+
+    #[test]
+    fn filter_per_profile() {
+        assert_eq!(
+            default_filter(MANIFEST, "debug").unwrap().as_deref(),
+            Some("trace")
+        );
+        assert_eq!(
+            default_filter(MANIFEST, "release").unwrap().as_deref(),
+            Some("warn,app::radio=info")
+        );
+    }
+
+    #[test]
+    fn missing_table() {
+        let manifest = "[package]\nname = \"app\"\n";
+        assert_eq!(default_filter(manifest, "debug").unwrap(), None);
+
+        let manifest = "[package.metadata.defmt.log]\nrelease = \"warn\"\n";
+        assert_eq!(default_filter(manifest, "debug").unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_table() {
+        let manifest = "[package.metadata.defmt.log]\ndebug = 1\n";
+        assert!(default_filter(manifest, "debug").is_err());
+
+        let manifest = "[package.metadata.defmt.log]\ndebug = \"info\"\ndev = \"trace\"\n";
+        assert!(default_filter(manifest, "debug").is_err());
+    }
+}
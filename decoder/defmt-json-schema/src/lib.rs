@@ -6,11 +6,24 @@ pub struct SchemaVersion {
     pub schema_version: u32,
 }
 
+/// Returns the [JSON Schema](https://json-schema.org) document describing the output of the given
+/// schema version, or `None` if the version is unknown.
+pub fn json_schema(version: &SchemaVersion) -> Option<&'static str> {
+    match *version {
+        v1::SCHEMA_VERSION => Some(v1::JSON_SCHEMA),
+        v2::SCHEMA_VERSION => Some(v2::JSON_SCHEMA),
+        _ => None,
+    }
+}
+
 pub mod v1 {
     use super::*;
 
     pub const SCHEMA_VERSION: SchemaVersion = SchemaVersion { schema_version: 1 };
 
+    /// JSON Schema document matching both the [`SchemaVersion`] line and the [`JsonFrame`] lines
+    pub const JSON_SCHEMA: &str = include_str!("../schema/v1.json");
+
     #[derive(Clone, Debug, Deserialize, Serialize)]
     pub struct JsonFrame {
         pub data: String,
@@ -35,3 +48,110 @@ pub mod v1 {
         pub function: String,
     }
 }
+
+/// Adds the task context of the frame
+pub mod v2 {
+    use super::*;
+
+    pub use super::v1::{Location, ModulePath};
+
+    pub const SCHEMA_VERSION: SchemaVersion = SchemaVersion { schema_version: 2 };
+
+    /// JSON Schema document matching both the [`SchemaVersion`] line and the [`JsonFrame`] lines
+    pub const JSON_SCHEMA: &str = include_str!("../schema/v2.json");
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct JsonFrame {
+        pub data: String,
+        /// Unix timestamp in nanoseconds
+        pub host_timestamp: i64,
+        pub level: Option<Level>,
+        pub location: Location,
+        pub target_timestamp: String,
+        /// The formatted task context, if the firmware defines one with `defmt::task_context!`
+        pub task: Option<String>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn v1_schema_matches_types() {
+        let schema: Value =
+            serde_json::from_str(json_schema(&v1::SCHEMA_VERSION).unwrap()).unwrap();
+        let defs = &schema["$defs"];
+        assert_eq!(
+            defs["SchemaVersion"]["properties"]["schema_version"]["const"],
+            v1::SCHEMA_VERSION.schema_version
+        );
+
+        let frame = v1::JsonFrame {
+            data: "Hello".into(),
+            host_timestamp: 0,
+            level: Some(Level::Info),
+            location: v1::Location {
+                file: None,
+                line: None,
+                module_path: Some(v1::ModulePath {
+                    crate_name: "app".into(),
+                    modules: vec![],
+                    function: "main".into(),
+                }),
+            },
+            target_timestamp: "".into(),
+        };
+        let frame = serde_json::to_value(frame).unwrap();
+        let required = |def: &str| defs[def]["required"].as_array().unwrap().len();
+        assert_eq!(frame.as_object().unwrap().len(), required("JsonFrame"));
+        assert_eq!(
+            frame["location"].as_object().unwrap().len(),
+            required("Location")
+        );
+        assert_eq!(
+            frame["location"]["module_path"].as_object().unwrap().len(),
+            required("ModulePath")
+        );
+        assert!(defs["JsonFrame"]["properties"]["level"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&frame["level"]));
+    }
+
+    #[test]
+    fn v2_schema_matches_types() {
+        let schema: Value =
+            serde_json::from_str(json_schema(&v2::SCHEMA_VERSION).unwrap()).unwrap();
+        let defs = &schema["$defs"];
+        assert_eq!(
+            defs["SchemaVersion"]["properties"]["schema_version"]["const"],
+            v2::SCHEMA_VERSION.schema_version
+        );
+
+        let frame = v2::JsonFrame {
+            data: "Hello".into(),
+            host_timestamp: 0,
+            level: Some(Level::Info),
+            location: v2::Location {
+                file: None,
+                line: None,
+                module_path: None,
+            },
+            target_timestamp: "".into(),
+            task: Some("radio".into()),
+        };
+        let frame = serde_json::to_value(frame).unwrap();
+        let required = defs["JsonFrame"]["required"].as_array().unwrap();
+        assert_eq!(frame.as_object().unwrap().len(), required.len());
+        assert!(required.contains(&"task".into()));
+        assert_eq!(frame["task"], "radio");
+    }
+
+    #[test]
+    fn unknown_version() {
+        assert_eq!(json_schema(&SchemaVersion { schema_version: 0 }), None);
+    }
+}
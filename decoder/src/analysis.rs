@@ -0,0 +1,287 @@
+//! Inter-arrival statistics of the frames of each log statement
+
+use std::{collections::BTreeMap, num::NonZeroU64};
+
+use crate::{Frame, Timestamp};
+
+/// Collects the time between consecutive frames of each log statement, from their timestamps
+///
+/// Frames without a timestamp, or whose timestamp is not a single integer, are ignored. A
+/// timestamp lower than the previous one of the same statement, e.g. after the device was reset,
+/// starts over without recording a period.
+///
+/// The periods themselves are not kept, so the memory used only grows with the number of log
+/// statements, not with the number of frames.
+#[derive(Debug, Default)]
+pub struct TimingAnalysis {
+    timings: BTreeMap<u64, Timing>,
+}
+
+impl TimingAnalysis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the timestamp of `frame`.
+    pub fn frame(&mut self, frame: &Frame) {
+        let Some(timestamp) = frame.timestamp() else {
+            return;
+        };
+
+        let timing = self.timings.entry(frame.index()).or_insert_with(|| {
+            Timing::new(frame.index(), frame.format().to_owned(), timestamp.rate)
+        });
+        timing.frames += 1;
+        if let Some(last) = timing.last {
+            if last.rate == timestamp.rate && last.count <= timestamp.count {
+                timing.record(timestamp.count - last.count);
+            }
+        }
+        timing.rate = timestamp.rate;
+        timing.last = Some(timestamp);
+    }
+
+    /// Returns the timing of each log statement received, ordered by index.
+    pub fn timings(&self) -> impl Iterator<Item = &Timing> {
+        self.timings.values()
+    }
+}
+
+/// Number of buckets the periods of a log statement are counted in
+const BUCKETS: usize = 256;
+
+/// Timing of the frames of one log statement
+#[derive(Clone, Debug)]
+pub struct Timing {
+    /// Index of the log statement
+    pub index: u64,
+    pub format: String,
+    /// Counts per second of the timestamps, if their unit is known
+    pub rate: Option<NonZeroU64>,
+    /// Number of timestamped frames received
+    pub frames: usize,
+    last: Option<Timestamp>,
+    /// Number of periods recorded
+    periods: usize,
+    min: u128,
+    max: u128,
+    /// Running mean and sum of squared deviations from it (Welford's algorithm)
+    mean: f64,
+    m2: f64,
+    /// Periods counted in `BUCKETS` buckets of `width` counts each, the first one starting at
+    /// `origin`; the buckets are merged pairwise when a period falls outside of them
+    origin: u128,
+    width: u128,
+    buckets: Box<[usize; BUCKETS]>,
+}
+
+/// A bar of the histogram of the periods of a log statement
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bin {
+    /// Lowest period counted, in timestamp counts
+    pub start: u128,
+    /// Highest period counted, in timestamp counts
+    pub end: u128,
+    pub count: usize,
+}
+
+impl Timing {
+    fn new(index: u64, format: String, rate: Option<NonZeroU64>) -> Self {
+        Self {
+            index,
+            format,
+            rate,
+            frames: 0,
+            last: None,
+            periods: 0,
+            min: u128::MAX,
+            max: 0,
+            mean: 0.0,
+            m2: 0.0,
+            origin: 0,
+            width: 1,
+            buckets: Box::new([0; BUCKETS]),
+        }
+    }
+
+    /// Records the time between two consecutive frames, in timestamp counts.
+    fn record(&mut self, period: u128) {
+        if self.periods == 0 {
+            // center the buckets on the first period, which is likely typical
+            self.origin = period.saturating_sub(BUCKETS as u128 / 2);
+        }
+        self.periods += 1;
+        self.min = self.min.min(period);
+        self.max = self.max.max(period);
+
+        let delta = period as f64 - self.mean;
+        self.mean += delta / self.periods as f64;
+        self.m2 += delta * (period as f64 - self.mean);
+
+        while !(self.origin..self.origin + BUCKETS as u128 * self.width).contains(&period) {
+            self.widen(period < self.origin);
+        }
+        self.buckets[((period - self.origin) / self.width) as usize] += 1;
+    }
+
+    /// Doubles the width of the buckets, extending them downwards if `down`, else upwards.
+    fn widen(&mut self, down: bool) {
+        let origin = match down {
+            true => self.origin.saturating_sub(BUCKETS as u128 * self.width),
+            false => self.origin,
+        };
+        let width = self.width * 2;
+
+        let mut buckets = Box::new([0; BUCKETS]);
+        for (i, count) in self.buckets.iter().enumerate() {
+            let start = self.origin + i as u128 * self.width;
+            buckets[((start - origin) / width) as usize] += count;
+        }
+        (self.origin, self.width, self.buckets) = (origin, width, buckets);
+    }
+
+    /// Returns the number of periods recorded, one less than the frames received unless the
+    /// device was reset.
+    pub fn periods(&self) -> usize {
+        self.periods
+    }
+
+    pub fn min(&self) -> Option<u128> {
+        (self.periods != 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<u128> {
+        (self.periods != 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.periods != 0).then_some(self.mean)
+    }
+
+    /// Returns the standard deviation of the periods.
+    pub fn jitter(&self) -> Option<f64> {
+        (self.periods != 0).then(|| (self.m2 / self.periods as f64).sqrt())
+    }
+
+    /// Counts the periods in up to `bins` bars of equal width, from the lowest period to the
+    /// highest one. The bounds of the bars are rounded to the buckets the periods were counted
+    /// in, which are narrow compared to the range of the periods.
+    pub fn histogram(&self, bins: usize) -> Vec<Bin> {
+        let (Some(min), Some(max)) = (self.min(), self.max()) else {
+            return Vec::new();
+        };
+        let first = ((min - self.origin) / self.width) as usize;
+        let last = ((max - self.origin) / self.width) as usize;
+        let per_bin = (last - first) / bins.max(1) + 1;
+
+        self.buckets[first..=last]
+            .chunks(per_bin)
+            .enumerate()
+            .map(|(i, buckets)| {
+                let start = self.origin + (first + i * per_bin) as u128 * self.width;
+                Bin {
+                    start,
+                    end: start + per_bin as u128 * self.width - 1,
+                    count: buckets.iter().sum(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_periods(periods: &[u128]) -> Timing {
+        let mut timing = Timing::new(0, "tick".to_owned(), NonZeroU64::new(1_000));
+        timing.frames = periods.len() + 1;
+        for &period in periods {
+            timing.record(period);
+        }
+        timing
+    }
+
+    #[test]
+    fn statistics() {
+        let timing = with_periods(&[10, 12, 8, 10]);
+
+        assert_eq!(timing.min(), Some(8));
+        assert_eq!(timing.max(), Some(12));
+        assert_eq!(timing.mean(), Some(10.0));
+        assert_eq!(timing.jitter(), Some(2f64.sqrt()));
+    }
+
+    #[test]
+    fn no_periods() {
+        let timing = with_periods(&[]);
+
+        assert_eq!(timing.min(), None);
+        assert_eq!(timing.mean(), None);
+        assert_eq!(timing.jitter(), None);
+        assert!(timing.histogram(4).is_empty());
+    }
+
+    #[test]
+    fn histogram() {
+        let timing = with_periods(&[10, 12, 8, 10, 17]);
+
+        assert_eq!(
+            timing.histogram(4),
+            [
+                Bin {
+                    start: 8,
+                    end: 10,
+                    count: 3
+                },
+                Bin {
+                    start: 11,
+                    end: 13,
+                    count: 1
+                },
+                Bin {
+                    start: 14,
+                    end: 16,
+                    count: 0
+                },
+                Bin {
+                    start: 17,
+                    end: 19,
+                    count: 1
+                },
+            ]
+        );
+
+        let timing = with_periods(&[5, 5]);
+        assert_eq!(
+            timing.histogram(4),
+            [Bin {
+                start: 5,
+                end: 5,
+                count: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn wide_periods() {
+        let periods = (0..10_000).map(|i| 1_000 + i * 97).collect::<Vec<_>>();
+        let timing = with_periods(&periods);
+
+        assert_eq!(timing.periods(), 10_000);
+        assert_eq!(timing.min(), Some(1_000));
+        assert_eq!(timing.max(), Some(1_000 + 9_999 * 97));
+        assert_eq!(timing.mean(), Some(1_000.0 + 9_999.0 * 97.0 / 2.0));
+
+        let histogram = timing.histogram(4);
+        assert_eq!(histogram.len(), 4);
+        assert!(histogram[0].start <= 1_000);
+        assert!(histogram[3].end >= 1_000 + 9_999 * 97);
+        assert_eq!(histogram.iter().map(|bin| bin.count).sum::<usize>(), 10_000);
+        assert!(histogram.iter().all(|bin| bin.count.abs_diff(2_500) <= 100));
+
+        // a period below the buckets extends them downwards
+        let timing = with_periods(&[1_000, 10]);
+        assert_eq!(timing.histogram(1)[0].count, 2);
+    }
+}
@@ -0,0 +1,216 @@
+//! Comparison of the log statements of two firmware versions
+
+use std::collections::BTreeMap;
+
+use defmt_parser::Level;
+
+use crate::Location;
+
+/// A log statement, or `println!`, in the table of a firmware
+#[derive(Clone, Debug)]
+pub struct Statement {
+    /// Index of the format string, as sent in the log frames
+    pub index: usize,
+    /// Log level, or `None` for `println!`
+    pub level: Option<Level>,
+    pub format: String,
+    /// Name of the crate containing the statement
+    pub crate_name: String,
+    pub location: Option<Location>,
+}
+
+impl Statement {
+    /// Key identifying the statement across firmware versions, if its location is unchanged.
+    fn location_key(&self) -> Option<(&str, &std::path::Path, u64)> {
+        let location = self.location.as_ref()?;
+        Some((&self.crate_name, &location.file, location.line))
+    }
+}
+
+/// Differences between the log statements of two firmware versions
+///
+/// Statements are matched by crate, level and format string. Statements that don't match any
+/// statement of the other version, but are at the same location, are reported as changed; the
+/// remaining ones as added or removed.
+#[derive(Debug, Default)]
+pub struct CatalogDiff {
+    pub added: Vec<Statement>,
+    pub removed: Vec<Statement>,
+    /// Statements whose level or format string changed, as `(old, new)` pairs
+    pub changed: Vec<(Statement, Statement)>,
+    /// Unchanged statements whose index shifted, as `(old, new)` pairs
+    pub moved: Vec<(Statement, Statement)>,
+}
+
+impl CatalogDiff {
+    /// Compares the statements of an `old` and a `new` firmware, as returned by
+    /// [`Table::statements`](crate::Table::statements).
+    pub fn new(old: &[Statement], new: &[Statement]) -> Self {
+        let mut diff = Self::default();
+
+        // match identical statements, preferring those at the same location
+        let mut groups = BTreeMap::<_, (Vec<&Statement>, Vec<&Statement>)>::new();
+        for statement in old {
+            groups
+                .entry(content_key(statement))
+                .or_default()
+                .0
+                .push(statement);
+        }
+        for statement in new {
+            groups
+                .entry(content_key(statement))
+                .or_default()
+                .1
+                .push(statement);
+        }
+
+        let mut unmatched_old = vec![];
+        let mut unmatched_new = vec![];
+        for (_, (old, mut new)) in groups {
+            let mut pairs = vec![];
+            let mut rest = vec![];
+            for old in old {
+                let same_location = new.iter().position(|new| {
+                    old.location_key().is_some() && new.location_key() == old.location_key()
+                });
+                match same_location {
+                    Some(i) => pairs.push((old, new.remove(i))),
+                    None => rest.push(old),
+                }
+            }
+            let matched = rest.len().min(new.len());
+            pairs.extend(rest.drain(..matched).zip(new.drain(..matched)));
+            unmatched_old.extend(rest);
+            unmatched_new.extend(new);
+
+            for (old, new) in pairs {
+                if old.index != new.index {
+                    diff.moved.push((old.clone(), new.clone()));
+                }
+            }
+        }
+
+        // what is left at the same location was edited
+        let mut new_at = BTreeMap::new();
+        for statement in unmatched_new {
+            match statement.location_key() {
+                Some(key) => new_at.entry(key).or_insert_with(Vec::new).push(statement),
+                None => diff.added.push(statement.clone()),
+            }
+        }
+        for old in unmatched_old {
+            let new = old
+                .location_key()
+                .and_then(|key| new_at.get_mut(&key))
+                .and_then(Vec::pop);
+            match new {
+                Some(new) => diff.changed.push((old.clone(), new.clone())),
+                None => diff.removed.push(old.clone()),
+            }
+        }
+        diff.added
+            .extend(new_at.into_values().flatten().map(Statement::clone));
+
+        diff.added.sort_by_key(|statement| statement.index);
+        diff.removed.sort_by_key(|statement| statement.index);
+        diff.changed.sort_by_key(|(_, new)| new.index);
+        diff.moved.sort_by_key(|(_, new)| new.index);
+        diff
+    }
+
+    /// Returns `true` if the firmware versions have the same statements at the same indices.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.moved.is_empty()
+    }
+}
+
+fn content_key(statement: &Statement) -> (&str, Option<&'static str>, &str) {
+    let level = statement.level.map(Level::as_str);
+    (&statement.crate_name, level, &statement.format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(index: usize, level: Level, format: &str, line: u64) -> Statement {
+        Statement {
+            index,
+            level: Some(level),
+            format: format.to_owned(),
+            crate_name: "app".to_owned(),
+            location: Some(Location {
+                file: "src/main.rs".into(),
+                line,
+                module: "app".to_owned(),
+            }),
+        }
+    }
+
+    fn indices(statements: &[Statement]) -> Vec<usize> {
+        statements.iter().map(|statement| statement.index).collect()
+    }
+
+    fn index_pairs(pairs: &[(Statement, Statement)]) -> Vec<(usize, usize)> {
+        pairs
+            .iter()
+            .map(|(old, new)| (old.index, new.index))
+            .collect()
+    }
+
+    #[test]
+    fn unchanged() {
+        let old = [
+            statement(0, Level::Info, "boot", 1),
+            statement(1, Level::Warn, "low battery: {=u8}%", 2),
+        ];
+
+        let diff = CatalogDiff::new(&old, &old);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn diff() {
+        let old = [
+            statement(0, Level::Info, "boot", 1),
+            statement(1, Level::Warn, "low battery: {=u8}%", 2),
+            statement(2, Level::Error, "sensor failed", 3),
+            statement(3, Level::Debug, "tick", 4),
+            statement(4, Level::Info, "shutdown", 5),
+        ];
+        let new = [
+            statement(0, Level::Info, "boot", 1),
+            statement(1, Level::Info, "radio up", 10),
+            statement(2, Level::Warn, "low battery: {=u8}%", 2),
+            // level changed
+            statement(3, Level::Warn, "sensor failed", 3),
+            // format string changed
+            statement(4, Level::Debug, "tick {=u32}", 4),
+        ];
+
+        let diff = CatalogDiff::new(&old, &new);
+        assert_eq!(indices(&diff.added), [1]);
+        assert_eq!(indices(&diff.removed), [4]);
+        assert_eq!(index_pairs(&diff.changed), [(2, 3), (3, 4)]);
+        assert_eq!(index_pairs(&diff.moved), [(1, 2)]);
+    }
+
+    #[test]
+    fn duplicates() {
+        let old = [
+            statement(0, Level::Info, "retry", 5),
+            statement(1, Level::Info, "retry", 9),
+        ];
+        let new = [statement(0, Level::Info, "retry", 9)];
+
+        let diff = CatalogDiff::new(&old, &new);
+        assert!(diff.added.is_empty());
+        assert_eq!(indices(&diff.removed), [0]);
+        assert!(diff.changed.is_empty());
+        assert_eq!(index_pairs(&diff.moved), [(1, 0)]);
+    }
+}
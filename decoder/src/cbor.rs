@@ -0,0 +1,67 @@
+//! Pretty-printing of the CBOR values sent by `defmt::Serde2Format`
+
+use std::{fmt::Write as _, io};
+
+use ciborium::value::Value;
+
+use crate::DecodeError;
+
+/// Decodes one CBOR item from the front of `bytes`, advancing it past the item.
+pub(crate) fn decode(bytes: &mut &[u8]) -> Result<String, DecodeError> {
+    let value: Value = ciborium::de::from_reader(&mut *bytes).map_err(|e| match e {
+        ciborium::de::Error::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            DecodeError::UnexpectedEof
+        }
+        _ => DecodeError::Malformed,
+    })?;
+
+    let mut buf = String::new();
+    format_value(&value, &mut buf);
+    Ok(buf)
+}
+
+fn format_value(value: &Value, buf: &mut String) {
+    match value {
+        Value::Integer(x) => write!(buf, "{}", i128::from(*x)).unwrap(),
+        Value::Float(x) => buf.push_str(ryu::Buffer::new().format(*x)),
+        Value::Bool(x) => write!(buf, "{x}").unwrap(),
+        Value::Null => buf.push_str("null"),
+        Value::Text(x) => write!(buf, "{x:?}").unwrap(),
+        Value::Bytes(x) => {
+            buf.push_str("b\"");
+            for byte in x {
+                write!(buf, "{}", std::ascii::escape_default(*byte)).unwrap();
+            }
+            buf.push('"');
+        }
+        Value::Tag(tag, value) => {
+            write!(buf, "{tag}(").unwrap();
+            format_value(value, buf);
+            buf.push(')');
+        }
+        Value::Array(values) => {
+            buf.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i != 0 {
+                    buf.push_str(", ");
+                }
+                format_value(value, buf);
+            }
+            buf.push(']');
+        }
+        Value::Map(entries) => {
+            buf.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i != 0 {
+                    buf.push_str(", ");
+                }
+                format_value(key, buf);
+                buf.push_str(": ");
+                format_value(value, buf);
+            }
+            buf.push('}');
+        }
+        // `Value` is non-exhaustive
+        _ => write!(buf, "{value:?}").unwrap(),
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     ops::Range,
 };
 
-use crate::{Arg, DecodeError, FormatSliceElement, Table};
+use crate::{cbor, Arg, DecodeError, FormatSliceElement, RecordFlag, Table};
 use byteorder::{ReadBytesExt, LE};
 use defmt_parser::{get_max_bitfield_range, Fragment, Parameter, Type};
 
@@ -23,7 +23,7 @@ impl<'t, 'b> Decoder<'t, 'b> {
         merge_bitfields(params);
 
         // sort & dedup to ensure that format string args can be addressed by index too
-        params.sort_by(|a, b| a.index.cmp(&b.index));
+        params.sort_by_key(|a| a.index);
         params.dedup_by(|a, b| a.index == b.index);
     }
 
@@ -124,7 +124,7 @@ impl<'t, 'b> Decoder<'t, 'b> {
                     1 => true,
                     _ => return Err(DecodeError::Malformed),
                 })),
-                Type::FormatSlice => {
+                Type::FormatSlice | Type::FormatIter => {
                     let num_elements = self.bytes.read_u32::<LE>()? as usize;
                     let elements = self.decode_format_slice(num_elements)?;
                     args.push(Arg::FormatSlice { elements });
@@ -202,6 +202,96 @@ impl<'t, 'b> Decoder<'t, 'b> {
                     }
                     args.push(Arg::Slice(arg_slice.to_vec()));
                 }
+                Type::U16Slice | Type::U32Slice | Type::U64Slice => {
+                    let (format, width) = match param.ty {
+                        Type::U16Slice => ("{=u16}", 2),
+                        Type::U32Slice => ("{=u32}", 4),
+                        _ => ("{=u64}", 8),
+                    };
+                    let num_elements = self.bytes.read_u32::<LE>()? as usize;
+                    let mut elements = Vec::with_capacity(num_elements);
+                    for _ in 0..num_elements {
+                        let element = self.bytes.read_uint128::<LE>(width)?;
+                        elements.push(FormatSliceElement {
+                            format,
+                            args: vec![Arg::Uxx(element)],
+                        });
+                    }
+                    // the elements are printed like a `{=[?]}`, applying the hint to each of them
+                    args.push(Arg::FormatSlice { elements });
+                }
+                Type::Chunked => {
+                    let id = self.bytes.read_u8()?;
+                    let len = self.bytes.read_u32::<LE>()? as usize;
+                    // the data follows in continuation frames, unless there is none. `len` comes
+                    // straight off the wire, so nothing is allocated up front; the buffer grows as
+                    // the chunks arrive
+                    args.push(match len {
+                        0 => Arg::Slice(vec![]),
+                        _ => Arg::Chunked {
+                            id,
+                            len,
+                            data: Vec::new(),
+                        },
+                    });
+                }
+                Type::Chunk => {
+                    let id = self.bytes.read_u8()?;
+                    let len = self.bytes.read_u32::<LE>()? as usize;
+                    if self.bytes.len() < len {
+                        return Err(DecodeError::UnexpectedEof);
+                    }
+                    let (data, rest) = self.bytes.split_at(len);
+                    self.bytes = rest;
+                    args.push(Arg::Chunk {
+                        id,
+                        data: data.to_vec(),
+                    });
+                }
+                Type::Record => {
+                    let id = self.bytes.read_u8()?;
+                    let flag = match self.bytes.read_u8()? {
+                        0 => RecordFlag::Begin,
+                        1 => RecordFlag::Continue,
+                        2 => RecordFlag::End,
+                        _ => return Err(DecodeError::Malformed),
+                    };
+                    args.push(Arg::Record { id, flag });
+                }
+                Type::Snapshot => {
+                    let seq = self.bytes.read_u8()?;
+                    match self.bytes.read_u8()? {
+                        // full
+                        0 => {
+                            let start = self.bytes;
+                            let mut value = self.decode_format("{=?}")?;
+                            let len = start.len() - self.bytes.len();
+                            args.push(Arg::Snapshot {
+                                seq,
+                                bytes: start[..len].to_vec(),
+                                value: Box::new(value.remove(0)),
+                            });
+                        }
+                        // delta
+                        1 => {
+                            let mut runs = vec![];
+                            let mut offset = 0;
+                            for _ in 0..self.bytes.read_u8()? {
+                                offset += self.bytes.read_u8()? as usize;
+                                let len = self.bytes.read_u8()? as usize;
+                                if self.bytes.len() < len {
+                                    return Err(DecodeError::UnexpectedEof);
+                                }
+                                let (data, rest) = self.bytes.split_at(len);
+                                self.bytes = rest;
+                                runs.push((offset, data.to_vec()));
+                                offset += len;
+                            }
+                            args.push(Arg::SnapshotDelta { seq, runs });
+                        }
+                        _ => return Err(DecodeError::Malformed),
+                    }
+                }
                 Type::U8Array(len) => {
                     let mut arg_slice = vec![];
                     // note: went for the suboptimal but simple solution; optimize if necessary
@@ -219,6 +309,10 @@ impl<'t, 'b> Decoder<'t, 'b> {
                     let c = std::char::from_u32(data).ok_or(DecodeError::Malformed)?;
                     args.push(Arg::Char(c));
                 }
+                Type::Cbor => {
+                    let value = cbor::decode(&mut self.bytes)?;
+                    args.push(Arg::Preformatted(value));
+                }
                 Type::Debug | Type::Display => {
                     // UTF-8 stream without a prefix length, terminated with `0xFF`.
 
@@ -298,12 +392,8 @@ fn merge_bitfields(params: &mut Vec<Parameter>) {
             let mut i = 0;
             while i != params.len() {
                 match &params[i].ty {
-                    Type::BitField(_) => {
-                        if params[i].index == index {
-                            params.remove(i);
-                        } else {
-                            i += 1; // we haven't removed a bitfield -> move i forward
-                        }
+                    Type::BitField(_) if params[i].index == index => {
+                        params.remove(i);
                     }
                     _ => {
                         i += 1; // we haven't removed a bitfield -> move i forward
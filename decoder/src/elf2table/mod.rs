@@ -3,13 +3,14 @@
 //! This is an implementation detail of [`probe-run`](https://github.com/knurling-rs/probe-run) and
 //! not meant to be consumed by other tools at the moment so all the API is unstable.
 
-mod symbol;
+pub(crate) mod symbol;
 
 use std::{
     borrow::Cow,
     collections::{BTreeMap, HashMap},
     convert::TryInto,
     fmt,
+    ops::Range,
     path::{Path, PathBuf},
 };
 
@@ -17,7 +18,11 @@ use crate::{BitflagsKey, StringEntry, Table, TableEntry, Tag, DEFMT_VERSION};
 use anyhow::{anyhow, bail, ensure};
 use object::{Object, ObjectSection, ObjectSymbol};
 
+/// Name of the symbol holding the table hash sent by `defmt::handshake!`
+const TABLE_HASH_SYMBOL: &str = "_defmt_table_hash";
+
 pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyhow::Error> {
+    let bytes = elf;
     let elf = object::File::parse(elf)?;
     // first pass to extract the `_defmt_version`
     let mut version = None;
@@ -107,6 +112,8 @@ pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyh
     let mut map = BTreeMap::new();
     let mut bitflags_map = HashMap::new();
     let mut timestamp = None;
+    let mut timestamp_sources = BTreeMap::new();
+    let mut task_context = None;
     for entry in elf.symbols() {
         // Skipping symbols with empty string names, as they may be added by
         // `objcopy`, and breaks JSON demangling
@@ -135,6 +142,31 @@ pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyh
                         name.to_string(),
                     ));
                 }
+                symbol::SymbolTag::Defmt(Tag::TimestampSource) => {
+                    // `{id}:{format}`
+                    let (id, format) = match sym.data().split_once(':') {
+                        Some((id, format)) => (id.parse::<u8>()?, format),
+                        None => bail!("malformed timestamp source string '{}'", sym.data()),
+                    };
+
+                    let entry = TableEntry::new(
+                        StringEntry::new(Tag::TimestampSource, format.to_string()),
+                        name.to_string(),
+                    );
+                    if timestamp_sources.insert(id, entry).is_some() {
+                        bail!("multiple formats found for timestamp source {}", id);
+                    }
+                }
+                symbol::SymbolTag::Defmt(Tag::TaskContext) => {
+                    if task_context.is_some() {
+                        bail!("multiple task context format specifications found");
+                    }
+
+                    task_context = Some(TableEntry::new(
+                        StringEntry::new(Tag::TaskContext, sym.data().to_string()),
+                        name.to_string(),
+                    ));
+                }
                 symbol::SymbolTag::Defmt(Tag::BitflagsValue) => {
                     // Bitflags values always occupy 128 bits / 16 bytes.
                     const BITFLAGS_VALUE_SIZE: u64 = 16;
@@ -208,14 +240,53 @@ pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyh
         })
         .collect();
 
+    let table_hash = table_hash_range(&elf)
+        .and_then(|range| bytes.get(range))
+        .map(|hash| u64::from_le_bytes(hash.try_into().unwrap()));
+
     Ok(Some(Table {
+        table_hash,
         entries: map,
         timestamp,
+        timestamp_sources,
+        task_context,
         bitflags,
         encoding,
+        svd: None,
+        redaction: Default::default(),
+        show_sensitive: false,
+        tick_rate: None,
+        boot_epoch: None,
+        strict: false,
     }))
 }
 
+/// Returns the range of the ELF file holding the table hash, if the firmware sends a handshake
+/// frame.
+fn table_hash_range(elf: &object::File) -> Option<Range<usize>> {
+    let symbol = elf
+        .symbols()
+        .find(|symbol| symbol.name() == Ok(TABLE_HASH_SYMBOL))?;
+    let section = elf.section_by_index(symbol.section_index()?).ok()?;
+    let (offset, _) = section.file_range()?;
+    let start = offset + symbol.address().checked_sub(section.address())?;
+    let start = usize::try_from(start).ok()?;
+    Some(start..start + 8)
+}
+
+/// Writes the hash of the table into `elf`, where `defmt::handshake!` reads it from.
+pub fn stamp_impl(elf: &mut [u8]) -> Result<u64, anyhow::Error> {
+    let table = parse_impl(elf, true)?.ok_or_else(|| anyhow!(".defmt data not found"))?;
+    let hash = table.hash();
+    let range = table_hash_range(&object::File::parse(&*elf)?).ok_or_else(|| {
+        anyhow!("`{TABLE_HASH_SYMBOL}` not found; the firmware does not call `defmt::handshake!`")
+    })?;
+    elf.get_mut(range)
+        .ok_or_else(|| anyhow!("`{TABLE_HASH_SYMBOL}` is not stored in the ELF file"))?
+        .copy_from_slice(&hash.to_le_bytes());
+    Ok(hash)
+}
+
 /// Checks if the version encoded in the symbol table is compatible with this version of the `decoder` crate
 fn check_version(version: &str) -> Result<(), String> {
     if version != DEFMT_VERSION {
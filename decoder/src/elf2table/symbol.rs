@@ -32,15 +32,41 @@ pub enum SymbolTag<'a> {
     Defmt(Tag),
 
     /// Non-`defmt_*` tag for custom tooling.
+    #[allow(dead_code)]
     Custom(&'a str),
 }
 
+/// Prefix of the symbols of strings deduplicated across crates by the `intern-dedup` feature of
+/// `defmt`; they are named `defmt.dedup.TAG.HEX`, where `HEX` are the hex-encoded bytes of the
+/// string, and aren't tied to a package or crate.
+const DEDUP_PREFIX: &str = "defmt.dedup.";
+
 impl Symbol {
     pub fn demangle(raw: &str) -> anyhow::Result<Self> {
+        if let Some(deduplicated) = raw.strip_prefix(DEDUP_PREFIX) {
+            return Self::demangle_deduplicated(deduplicated)
+                .ok_or_else(|| anyhow::anyhow!("failed to demangle defmt symbol `{}`", raw));
+        }
+
         serde_json::from_str(raw)
             .map_err(|j| anyhow::anyhow!("failed to demangle defmt symbol `{}`: {}", raw, j))
     }
 
+    fn demangle_deduplicated(raw: &str) -> Option<Self> {
+        let (tag, hex) = raw.split_once('.')?;
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            package: String::new(),
+            disambiguator: String::new(),
+            tag: format!("defmt_{tag}"),
+            data: String::from_utf8(bytes).ok()?,
+            crate_name: String::new(),
+        })
+    }
+
     pub fn tag(&self) -> SymbolTag<'_> {
         match &*self.tag {
             "defmt_prim" => SymbolTag::Defmt(Tag::Prim),
@@ -48,6 +74,9 @@ impl Symbol {
             "defmt_bitflags" => SymbolTag::Defmt(Tag::Bitflags),
             "defmt_write" => SymbolTag::Defmt(Tag::Write),
             "defmt_timestamp" => SymbolTag::Defmt(Tag::Timestamp),
+            "defmt_timestamp_source" => SymbolTag::Defmt(Tag::TimestampSource),
+            "defmt_task_context" => SymbolTag::Defmt(Tag::TaskContext),
+            "defmt_handshake" => SymbolTag::Defmt(Tag::Handshake),
             "defmt_bitflags_value" => SymbolTag::Defmt(Tag::BitflagsValue),
             "defmt_str" => SymbolTag::Defmt(Tag::Str),
             "defmt_println" => SymbolTag::Defmt(Tag::Println),
@@ -76,3 +105,17 @@ impl Symbol {
         &self.crate_name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangle_deduplicated() {
+        let symbol = Symbol::demangle("defmt.dedup.derived.4e6f6e65").unwrap();
+        assert!(matches!(symbol.tag(), SymbolTag::Defmt(Tag::Derived)));
+        assert_eq!(symbol.data(), "None");
+
+        assert!(Symbol::demangle("defmt.dedup.derived.4e6f6e6").is_err());
+    }
+}
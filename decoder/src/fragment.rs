@@ -0,0 +1,146 @@
+//! Reassembly of the frames split into fragments by `defmt::Fragmenter`
+
+const FIRST: u8 = 0x80;
+const LAST: u8 = 0x40;
+const SEQUENCE_MASK: u8 = 0x3f;
+const HEADER_LEN: usize = 2;
+
+/// Reassembles the frames sent in fragments by `defmt::Fragmenter`, for transports with tiny
+/// packets.
+///
+/// Only the data of frames whose fragments were all received is passed on; frames that lost a
+/// fragment, detected by a gap in the sequence numbers, are dropped.
+///
+/// The sequence numbers are 6 bits wide and wrap around, so a loss of a multiple of 64
+/// consecutive packets within a frame goes unnoticed; frames spanning that many packets are
+/// unusual, and the frame then still has to decode.
+#[derive(Default)]
+pub struct Reassembler {
+    /// Received data that doesn't make up a complete fragment yet
+    pending: Vec<u8>,
+    /// Data of the frame being reassembled, unless waiting for the first fragment of a frame
+    frame: Option<Vec<u8>>,
+    /// Sequence number of the next fragment
+    sequence: u8,
+    dropped_frames: usize,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes a received packet, appending the data of the frame it completes, if any, to `out`.
+    ///
+    /// A packet that isn't a complete fragment, e.g. because it was truncated, is dropped along
+    /// with the frame it belongs to; reassembly starts over with the next packet.
+    pub fn packet(&mut self, packet: &[u8], out: &mut Vec<u8>) {
+        match packet {
+            [header, len, payload @ ..] if payload.len() == usize::from(*len) => {
+                self.fragment(*header, payload, out)
+            }
+            _ => {
+                if self.frame.take().is_some() {
+                    self.dropped_frames += 1;
+                }
+            }
+        }
+    }
+
+    /// Pushes received data made up of packets whose boundaries were lost, e.g. when they were
+    /// forwarded through a pipe, appending the data of completely received frames to `out`.
+    ///
+    /// The packets are told apart by the lengths in their headers, so unlike with
+    /// [`Reassembler::packet`], bytes lost within a packet make the following packets unreadable.
+    pub fn received(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        self.pending.extend_from_slice(data);
+
+        let mut start = 0;
+        while let [_, len, ..] = self.pending[start..] {
+            let end = start + HEADER_LEN + usize::from(len);
+            let Some(packet) = self.pending.get(start..end) else {
+                break;
+            };
+            let packet = packet.to_vec();
+            self.packet(&packet, out);
+            start = end;
+        }
+        self.pending.drain(..start);
+    }
+
+    /// Returns the number of frames dropped because some of their fragments were lost.
+    pub fn dropped_frames(&self) -> usize {
+        self.dropped_frames
+    }
+
+    fn fragment(&mut self, header: u8, payload: &[u8], out: &mut Vec<u8>) {
+        let sequence = header & SEQUENCE_MASK;
+        if header & FIRST != 0 {
+            // the last fragment of the previous frame was lost
+            if self.frame.replace(vec![]).is_some() {
+                self.dropped_frames += 1;
+            }
+        } else if sequence != self.sequence && self.frame.take().is_some() {
+            self.dropped_frames += 1;
+        }
+        self.sequence = (sequence + 1) & SEQUENCE_MASK;
+
+        if let Some(frame) = &mut self.frame {
+            frame.extend_from_slice(payload);
+            if header & LAST != 0 {
+                out.append(frame);
+                self.frame = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reassemble() {
+        let mut reassembler = Reassembler::new();
+        let mut out = vec![];
+
+        // fragments split across reads
+        reassembler.received(&[0x80, 3, 1, 2], &mut out);
+        reassembler.received(&[3, 0x41, 3, 4, 5, 6, 0xc2], &mut out);
+        assert_eq!(out, [1, 2, 3, 4, 5, 6]);
+        reassembler.received(&[1, 7], &mut out);
+        assert_eq!(out, [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(reassembler.dropped_frames(), 0);
+    }
+
+    #[test]
+    fn packets() {
+        let mut reassembler = Reassembler::new();
+        let mut out = vec![];
+
+        reassembler.packet(&[0x80, 2, 1, 2], &mut out);
+        reassembler.packet(&[0x41, 2, 3], &mut out); // truncated
+        reassembler.packet(&[0xc2, 1, 4], &mut out);
+        assert_eq!(out, [4]);
+        assert_eq!(reassembler.dropped_frames(), 1);
+    }
+
+    #[test]
+    fn lost_fragments() {
+        let mut reassembler = Reassembler::new();
+        let mut out = vec![];
+
+        reassembler.received(
+            &[
+                0x41, 1, 9, // last fragment of a frame sent before the host started listening
+                0x82, 1, 1, // sequence number 3 lost
+                0x44, 1, 3, //
+                0x85, 1, 4, // last fragment lost
+                0xc7, 1, 5, //
+            ],
+            &mut out,
+        );
+        assert_eq!(out, [5]);
+        assert_eq!(reassembler.dropped_frames(), 2);
+    }
+}
@@ -2,9 +2,10 @@ use std::{
     convert::TryFrom,
     fmt::{self, Write as _},
     mem,
+    num::NonZeroU64,
 };
 
-use crate::{Arg, BitflagsKey, Table};
+use crate::{Arg, BitflagsKey, RecordFlag, Table};
 use colored::Colorize;
 use defmt_parser::{DisplayHint, Fragment, Level, ParserMode, TimePrecision, Type};
 use time::{macros::format_description, OffsetDateTime};
@@ -38,6 +39,14 @@ impl std::fmt::UpperHex for I128Hex {
     }
 }
 
+/// The timestamp of a frame, as a count of some unit of time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timestamp {
+    pub count: u128,
+    /// Counts per second, if the unit is known from the display hint of the timestamp
+    pub rate: Option<NonZeroU64>,
+}
+
 /// A log frame
 #[derive(Debug, PartialEq)]
 pub struct Frame<'t> {
@@ -46,6 +55,8 @@ pub struct Frame<'t> {
     index: u64,
     timestamp_format: Option<&'t str>,
     timestamp_args: Vec<Arg<'t>>,
+    task_context_format: Option<&'t str>,
+    task_context_args: Vec<Arg<'t>>,
     // Format string
     format: &'t str,
     args: Vec<Arg<'t>>,
@@ -67,11 +78,18 @@ impl<'t> Frame<'t> {
             index,
             timestamp_format,
             timestamp_args,
+            task_context_format: None,
+            task_context_args: Vec::new(),
             format,
             args,
         }
     }
 
+    pub(crate) fn set_task_context(&mut self, format: &'t str, args: Vec<Arg<'t>>) {
+        self.task_context_format = Some(format);
+        self.task_context_args = args;
+    }
+
     /// Returns a struct that will format this log frame (including message, timestamp, level,
     /// etc.).
     pub fn display(&'t self, colored: bool) -> DisplayFrame<'t> {
@@ -86,6 +104,13 @@ impl<'t> Frame<'t> {
             .map(|_| DisplayTimestamp { frame: self })
     }
 
+    /// Returns a struct that will format the context (e.g. the task) the frame was logged from, if
+    /// the firmware defines one with `defmt::task_context!`.
+    pub fn display_task_context(&'t self) -> Option<DisplayTaskContext<'t>> {
+        self.task_context_format
+            .map(|_| DisplayTaskContext { frame: self })
+    }
+
     /// Returns a struct that will format the message contained in this log frame.
     pub fn display_message(&'t self) -> DisplayMessage<'t> {
         DisplayMessage { frame: self }
@@ -99,8 +124,119 @@ impl<'t> Frame<'t> {
         self.index
     }
 
-    fn format_args(&self, format: &str, args: &[Arg], parent_hint: Option<&DisplayHint>) -> String {
-        self.format_args_real(format, args, parent_hint).unwrap() // cannot fail, we only write to a `String`
+    /// Returns the format string of the message.
+    pub fn format(&self) -> &'t str {
+        self.format
+    }
+
+    /// Returns the value of the timestamp, if it is a single non-negative integer.
+    ///
+    /// Its rate is known for the `us`, `iso8601ms`, `iso8601s`, `tsms` and `ts` display hints, and
+    /// for `tick` if the tick rate of the table is set.
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        let params =
+            defmt_parser::parse(self.timestamp_format?, ParserMode::ForwardsCompatible).ok()?;
+        let mut params = params.into_iter().filter_map(|fragment| match fragment {
+            Fragment::Parameter(param) => Some(param),
+            Fragment::Literal(_) => None,
+        });
+        let (Some(param), None) = (params.next(), params.next()) else {
+            return None;
+        };
+
+        let count = match self.timestamp_args.get(param.index)? {
+            Arg::Uxx(x) => *x,
+            Arg::Ixx(x) => u128::try_from(*x).ok()?,
+            _ => return None,
+        };
+        let rate = match param.hint {
+            Some(DisplayHint::Microseconds) => NonZeroU64::new(1_000_000),
+            Some(DisplayHint::ISO8601(TimePrecision::Millis)) => NonZeroU64::new(1_000),
+            Some(DisplayHint::ISO8601(TimePrecision::Seconds)) => NonZeroU64::new(1),
+            Some(DisplayHint::Ticks) => self.table.tick_rate,
+            _ => None,
+        };
+        Some(Timestamp { count, rate })
+    }
+
+    /// Takes the id and data out of a continuation frame of a `{=chunked}` argument.
+    pub(crate) fn take_chunk(&mut self) -> Option<(u8, Vec<u8>)> {
+        match &mut self.args[..] {
+            [Arg::Chunk { id, data }] => Some((*id, mem::take(data))),
+            _ => None,
+        }
+    }
+
+    /// Returns the id and flag of a marker frame of a logical record.
+    pub(crate) fn record_marker(&self) -> Option<(u8, RecordFlag)> {
+        match self.args[..] {
+            [Arg::Record { id, flag }] => Some((id, flag)),
+            _ => None,
+        }
+    }
+
+    /// Returns the `defmt::snapshot!` value of the frame, if it carries one.
+    pub(crate) fn snapshot_mut(&mut self) -> Option<&mut Arg<'t>> {
+        self.args
+            .iter_mut()
+            .find(|arg| matches!(arg, Arg::Snapshot { .. } | Arg::SnapshotDelta { .. }))
+    }
+
+    /// Returns the task context as shown, used to tell the frames of different tasks apart.
+    pub(crate) fn task_context_key(&self) -> Option<String> {
+        self.task_context_format
+            .map(|format| self.format_args(format, &self.task_context_args, None, false))
+    }
+
+    /// Returns the ids of the `{=chunked}` arguments still waiting for data.
+    pub(crate) fn chunked_ids(&self) -> impl Iterator<Item = u8> + '_ {
+        self.args.iter().filter_map(|arg| match arg {
+            Arg::Chunked { id, .. } => Some(*id),
+            _ => None,
+        })
+    }
+
+    /// Returns `true` if the frame has `{=chunked}` arguments still waiting for data.
+    pub(crate) fn is_incomplete(&self) -> bool {
+        self.args
+            .iter()
+            .any(|arg| matches!(arg, Arg::Chunked { .. }))
+    }
+
+    /// Appends `chunk` to the `{=chunked}` argument with the given id, if there is one.
+    ///
+    /// Returns `false` if there is no such argument.
+    pub(crate) fn append_chunk(&mut self, chunk_id: u8, chunk: &[u8]) -> bool {
+        let arg = self
+            .args
+            .iter_mut()
+            .find(|arg| matches!(arg, Arg::Chunked { id, .. } if *id == chunk_id));
+        let Some(arg) = arg else {
+            return false;
+        };
+
+        if let Arg::Chunked { len, data, .. } = arg {
+            // never take more than the announced length
+            let missing = *len - data.len();
+            data.extend_from_slice(&chunk[..chunk.len().min(missing)]);
+            if data.len() >= *len {
+                *arg = Arg::Slice(mem::take(data));
+            }
+        }
+        true
+    }
+
+    /// Formats `args` according to `format`. With `redact`, the table's redaction rules are
+    /// applied to the arguments.
+    fn format_args(
+        &self,
+        format: &str,
+        args: &[Arg],
+        parent_hint: Option<&DisplayHint>,
+        redact: bool,
+    ) -> String {
+        self.format_args_real(format, args, parent_hint, redact)
+            .unwrap() // cannot fail, we only write to a `String`
     }
 
     fn format_args_real(
@@ -108,16 +244,30 @@ impl<'t> Frame<'t> {
         format: &str,
         args: &[Arg],
         parent_hint: Option<&DisplayHint>,
+        redact: bool,
     ) -> Result<String, fmt::Error> {
         let params = defmt_parser::parse(format, ParserMode::ForwardsCompatible).unwrap();
         let mut buf = String::new();
+        // the text right before the current parameter, which may name it
+        let mut literal = String::new();
+        let debug = DisplayHint::Debug;
         for param in params {
             match param {
                 Fragment::Literal(lit) => {
                     buf.push_str(&lit);
+                    literal = lit.into_owned();
                 }
                 Fragment::Parameter(param) => {
-                    let hint = param.hint.as_ref().or(parent_hint);
+                    let mut hint = param.hint.as_ref().or(parent_hint);
+                    if let Some(DisplayHint::Sensitive) = param.hint {
+                        if redact && !self.table.show_sensitive {
+                            buf.push_str("<redacted>");
+                            literal.clear();
+                            continue;
+                        }
+                        hint = Some(&debug);
+                    }
+                    let start = buf.len();
 
                     match &args[param.index] {
                         Arg::Bool(x) => write!(buf, "{x}")?,
@@ -160,13 +310,18 @@ impl<'t> Frame<'t> {
                         Arg::IStr(x) => self.format_str(x, hint, &mut buf)?,
                         Arg::Format { format, args } => match parent_hint {
                             Some(DisplayHint::Ascii) => {
-                                buf.push_str(&self.format_args(format, args, parent_hint));
+                                buf.push_str(&self.format_args(format, args, parent_hint, redact));
                             }
-                            _ => buf.push_str(&self.format_args(format, args, hint)),
+                            _ => buf.push_str(&self.format_args(format, args, hint, redact)),
                         },
                         Arg::FormatSequence { args } => {
                             for arg in args {
-                                buf.push_str(&self.format_args("{=?}", &[arg.clone()], hint))
+                                buf.push_str(&self.format_args(
+                                    "{=?}",
+                                    std::slice::from_ref(arg),
+                                    hint,
+                                    redact,
+                                ))
                             }
                         }
                         Arg::FormatSlice { elements } => {
@@ -200,6 +355,7 @@ impl<'t> Frame<'t> {
                                             element.format,
                                             &element.args,
                                             hint,
+                                            redact,
                                         ))?;
                                     }
                                     buf.write_str("]")?;
@@ -207,8 +363,35 @@ impl<'t> Frame<'t> {
                             }
                         }
                         Arg::Slice(x) => self.format_bytes(x, hint, &mut buf)?,
+                        Arg::Chunked { len, data, .. } => {
+                            self.format_bytes(data, hint, &mut buf)?;
+                            write!(buf, " <truncated: {} of {len} bytes received>", data.len())?
+                        }
+                        Arg::Chunk { id, data } => {
+                            write!(buf, "<chunk of {} bytes for #{id}>", data.len())?
+                        }
+                        Arg::Record { id, flag } => write!(buf, "<{flag:?} of record #{id}>")?,
+                        Arg::Snapshot { value, .. } => buf.push_str(&self.format_args(
+                            "{=?}",
+                            std::slice::from_ref(&**value),
+                            hint,
+                            redact,
+                        )),
+                        Arg::SnapshotDelta { .. } => {
+                            buf.push_str("<delta of snapshot without its previous value>")
+                        }
                         Arg::Char(c) => write!(buf, "{c}")?,
                     }
+
+                    let redaction = &self.table.redaction;
+                    if let Some(action) = redact
+                        .then(|| redaction.action(self.format, &literal))
+                        .flatten()
+                    {
+                        let value = buf.split_off(start);
+                        redaction.apply(action, &value, &mut buf);
+                    }
+                    literal.clear();
                 }
             }
         }
@@ -245,6 +428,61 @@ impl<'t> Frame<'t> {
                 let micros = x % 1_000_000;
                 write!(buf, "{seconds}.{micros:06}")?;
             }
+            Some(DisplayHint::Ticks) => self.format_ticks(x, buf)?,
+            Some(DisplayHint::Duration) => {
+                // `ticks * NOM / DENOM` is in seconds; go through nanoseconds to stay exact.
+                let units = [
+                    (1_000_000_000, "s"),
+                    (1_000_000, "ms"),
+                    (1_000, "us"),
+                    (1, "ns"),
+                ];
+                format_ratio(x, 1_000_000_000, &units, buf)?;
+            }
+            Some(DisplayHint::Rate) => {
+                // `ticks * NOM / DENOM` is in hertz; go through millihertz to stay exact.
+                let units = [
+                    (1_000_000_000_000, "GHz"),
+                    (1_000_000_000, "MHz"),
+                    (1_000_000, "kHz"),
+                    (1_000, "Hz"),
+                    (1, "mHz"),
+                ];
+                format_ratio(x, 1_000, &units, buf)?;
+            }
+            Some(DisplayHint::Register {
+                peripheral,
+                register,
+            }) => {
+                let fields = self
+                    .table
+                    .svd
+                    .as_ref()
+                    .and_then(|svd| svd.fields(peripheral, register));
+                match fields {
+                    Some(fields) => {
+                        let set_fields = fields
+                            .iter()
+                            .filter_map(|field| {
+                                let mask = u128::MAX >> (128 - field.width);
+                                let value = (x >> field.offset) & mask;
+                                match (value, field.width) {
+                                    (0, _) => None,
+                                    (_, 1) => Some(field.name.clone()),
+                                    (_, _) => Some(format!("{}={value:#x}", field.name)),
+                                }
+                            })
+                            .collect::<Vec<_>>();
+                        if set_fields.is_empty() {
+                            write!(buf, "(empty)")?;
+                        } else {
+                            write!(buf, "{}", set_fields.join(" | "))?;
+                        }
+                    }
+                    // no SVD file loaded, or the register isn't in it
+                    None => write!(buf, "{x:#x}")?,
+                }
+            }
             Some(DisplayHint::Bitflags {
                 name,
                 package,
@@ -409,6 +647,68 @@ impl<'t> Frame<'t> {
         .unwrap();
         write!(buf, "{}", date_time.format(format).unwrap())
     }
+
+    /// Formats a tick count as seconds since boot, or as ISO8601 date time if the boot epoch is
+    /// known. Without a configured tick rate the raw count is printed.
+    fn format_ticks(&self, ticks: u128, buf: &mut String) -> Result<(), fmt::Error> {
+        let Some(hz) = self.table.tick_rate else {
+            return write!(buf, "{ticks}");
+        };
+        // split before scaling, so that large tick counts don't overflow
+        let hz = u128::from(hz.get());
+        let secs = ticks / hz;
+        let subsec_nanos = ticks % hz * 1_000_000_000 / hz;
+
+        let date_time = self.table.boot_epoch.and_then(|epoch| {
+            let secs = i128::from(epoch).checked_add(i128::try_from(secs).ok()?)?;
+            let nanos = secs.checked_mul(1_000_000_000)? + subsec_nanos as i128;
+            OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()
+        });
+        match date_time {
+            Some(date_time) => {
+                let format = format_description!(
+                    "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6]Z"
+                );
+                write!(buf, "{}", date_time.format(format).unwrap())
+            }
+            None => write!(buf, "{}.{:06}", secs, subsec_nanos / 1_000),
+        }
+    }
+}
+
+/// Formats a value packed by the `Format` impls of `embedded-time` types.
+///
+/// `packed` holds the tick count in its lower 64 bits, followed by the 32-bit numerator and
+/// denominator of the ratio converting ticks into the base unit (seconds or hertz). The value is
+/// scaled by `base` first, and then printed in the largest of `units` that it doesn't fall below.
+fn format_ratio(
+    packed: u128,
+    base: u128,
+    units: &[(u128, &str)],
+    buf: &mut String,
+) -> Result<(), fmt::Error> {
+    let ticks = packed as u64 as u128;
+    let nom = (packed >> 64) as u32 as u128;
+    let denom = (packed >> 96) as u32 as u128;
+    if denom == 0 {
+        return write!(buf, "{ticks} ticks @ ({nom}/{denom})");
+    }
+
+    // cannot overflow: 64 + 32 bits, times a `base` of less than 32 bits
+    let value = ticks * nom * base / denom;
+    let (scale, unit) = units
+        .iter()
+        .find(|(scale, _)| value >= *scale)
+        .unwrap_or(&units[units.len() - 1]);
+
+    write!(buf, "{}", value / scale)?;
+    let fraction = value % scale;
+    if fraction != 0 {
+        let digits = scale.ilog10() as usize;
+        let fraction = format!("{fraction:0digits$}");
+        write!(buf, ".{}", fraction.trim_end_matches('0'))?;
+    }
+    write!(buf, " {unit}")
 }
 
 pub struct DisplayTimestamp<'t> {
@@ -421,6 +721,23 @@ impl fmt::Display for DisplayTimestamp<'_> {
             self.frame.timestamp_format.unwrap(),
             &self.frame.timestamp_args,
             None,
+            false,
+        );
+        f.write_str(&args)
+    }
+}
+
+pub struct DisplayTaskContext<'t> {
+    frame: &'t Frame<'t>,
+}
+
+impl fmt::Display for DisplayTaskContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = self.frame.format_args(
+            self.frame.task_context_format.unwrap(),
+            &self.frame.task_context_args,
+            None,
+            false,
         );
         f.write_str(&args)
     }
@@ -434,7 +751,7 @@ impl fmt::Display for DisplayMessage<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let args = self
             .frame
-            .format_args(self.frame.format, &self.frame.args, None);
+            .format_args(self.frame.format, &self.frame.args, None, true);
         f.write_str(&args)
     }
 }
@@ -478,15 +795,21 @@ impl fmt::Display for DisplayFrame<'_> {
                 format!(
                     "{} ",
                     self.frame
-                        .format_args(fmt, &self.frame.timestamp_args, None,),
+                        .format_args(fmt, &self.frame.timestamp_args, None, false),
                 )
             })
             .unwrap_or_default();
 
+        let task_context = self
+            .frame
+            .display_task_context()
+            .map(|task_context| format!("[{task_context}] "))
+            .unwrap_or_default();
+
         let args = self
             .frame
-            .format_args(self.frame.format, &self.frame.args, None);
+            .format_args(self.frame.format, &self.frame.args, None, true);
 
-        write!(f, "{timestamp}{level}{args}")
+        write!(f, "{timestamp}{level}{task_context}{args}")
     }
 }
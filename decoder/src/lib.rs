@@ -12,27 +12,41 @@
 
 pub const DEFMT_VERSION: &str = "4";
 
+mod analysis;
+mod catalog;
+mod cbor;
 mod decoder;
 mod elf2table;
+mod fragment;
 mod frame;
 pub mod log;
+pub mod redact;
+mod select;
 mod stream;
+mod svd;
 
 use std::{
     collections::{BTreeMap, HashMap},
     error::Error,
     fmt, io,
+    num::NonZeroU64,
     str::FromStr,
 };
 
 use byteorder::{ReadBytesExt, LE};
 use decoder::Decoder;
 use defmt_parser::Level;
-use elf2table::parse_impl;
+use elf2table::{parse_impl, stamp_impl};
+use redact::Redaction;
 
+pub use analysis::{Bin, Timing, TimingAnalysis};
+pub use catalog::{CatalogDiff, Statement};
 pub use elf2table::{Location, Locations};
-pub use frame::Frame;
+pub use fragment::Reassembler;
+pub use frame::{Frame, Timestamp};
+pub use select::{Handshake, TableSelector};
 pub use stream::StreamDecoder;
+pub use svd::Svd;
 
 /// Specifies the origin of a format string
 #[derive(PartialEq, Eq, Debug)]
@@ -49,6 +63,12 @@ pub enum Tag {
     Str,
     /// Defines the global timestamp format.
     Timestamp,
+    /// Defines the format of one of several timestamp sources.
+    TimestampSource,
+    /// Defines the format of the task context.
+    TaskContext,
+    /// Hash identifying the table, sent by `defmt::handshake!`.
+    Handshake,
 
     /// `static` containing a possible value of a bitflags type.
     BitflagsValue,
@@ -150,10 +170,25 @@ impl Encoding {
 /// Internal table that holds log levels and maps format strings to indices
 #[derive(Debug, Eq, PartialEq)]
 pub struct Table {
+    /// Table hash stored in the firmware, sent by `defmt::handshake!`
+    table_hash: Option<u64>,
     timestamp: Option<TableEntry>,
+    /// Timestamp formats keyed by source id, if more than one source is defined
+    timestamp_sources: BTreeMap<u8, TableEntry>,
+    task_context: Option<TableEntry>,
     entries: BTreeMap<usize, TableEntry>,
     bitflags: HashMap<BitflagsKey, Vec<(String, u128)>>,
     encoding: Encoding,
+    svd: Option<Svd>,
+    redaction: Redaction,
+    /// Show the values of `#[defmt(sensitive)]` fields instead of redacting them
+    show_sensitive: bool,
+    /// Rate of the counter logged with the `tick` display hint
+    tick_rate: Option<NonZeroU64>,
+    /// Unix time (in seconds) at which the tick counter was zero
+    boot_epoch: Option<i64>,
+    /// Report anomalies in the data as errors; see [`Table::set_strict`]
+    strict: bool,
 }
 
 impl Table {
@@ -175,6 +210,55 @@ impl Table {
         self.timestamp = Some(timestamp);
     }
 
+    /// Sets the format of timestamp source `id`.
+    ///
+    /// Once any source is set, every frame is expected to start its timestamp with the `u8` id of
+    /// the source that produced it.
+    pub fn set_timestamp_source_entry(&mut self, id: u8, timestamp: TableEntry) {
+        self.timestamp_sources.insert(id, timestamp);
+    }
+
+    /// Sets the format of the task context, which every frame then carries after its timestamp.
+    pub fn set_task_context_entry(&mut self, task_context: TableEntry) {
+        self.task_context = Some(task_context);
+    }
+
+    /// Sets the register descriptions used by the `reg:PERIPHERAL.REGISTER` display hint.
+    pub fn set_svd(&mut self, svd: Svd) {
+        self.svd = Some(svd);
+    }
+
+    /// Sets the rules selecting the arguments whose values are redacted or hashed when frames are
+    /// displayed.
+    pub fn set_redaction(&mut self, redaction: Redaction) {
+        self.redaction = redaction;
+    }
+
+    /// Shows the values of `#[defmt(sensitive)]` fields, which are redacted by default.
+    pub fn set_show_sensitive(&mut self, show: bool) {
+        self.show_sensitive = show;
+    }
+
+    /// Sets the rate, in hertz, of the counter logged with the `tick` display hint.
+    ///
+    /// Ticks are then printed as seconds since boot, or as ISO8601 date time if the boot epoch was
+    /// set with [`Table::set_boot_epoch`].
+    pub fn set_tick_rate(&mut self, hz: NonZeroU64) {
+        self.tick_rate = Some(hz);
+    }
+
+    /// Sets the Unix time, in seconds, at which the tick counter was zero.
+    pub fn set_boot_epoch(&mut self, unix_seconds: i64) {
+        self.boot_epoch = Some(unix_seconds);
+    }
+
+    /// Reports anomalies that hint at a mismatch between the firmware and the ELF file as
+    /// [`DecodeError::Anomaly`], instead of skipping the frame or ignoring them: unknown indices,
+    /// and frames shorter or longer than their arguments (detected with the rzCOBS encoding only).
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
     fn _get(&self, index: usize) -> Result<(Option<Level>, &str), ()> {
         let entry = self.entries.get(&index).ok_or(())?;
         Ok((entry.string.tag.to_level(), &entry.string.string))
@@ -207,11 +291,97 @@ impl Table {
         self.entries.is_empty()
     }
 
+    /// Writes the hash of the table into the ELF file `elf`, for `defmt::handshake!` to send, and
+    /// returns it.
+    ///
+    /// This is meant to run after linking and before flashing; see [`Table::hash`].
+    pub fn stamp(elf: &mut [u8]) -> Result<u64, anyhow::Error> {
+        stamp_impl(elf)
+    }
+
+    /// Returns a hash of the format strings in the table and their indices.
+    ///
+    /// It changes whenever the table does, and only then, so it identifies the table in the
+    /// handshake frame once [`Table::stamp`] wrote it into the ELF file.
+    pub fn hash(&self) -> u64 {
+        // FNV-1a, which unlike the hashers of `std` is stable across Rust versions
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut write = |bytes: &[u8]| {
+            for &byte in bytes.iter().chain([&0xff]) {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        let entries = self.timestamp.iter().map(|entry| (None, entry));
+        let entries = entries.chain(
+            self.timestamp_sources
+                .iter()
+                .map(|(&id, entry)| (Some(id as usize), entry)),
+        );
+        let entries = entries.chain(self.task_context.iter().map(|entry| (None, entry)));
+        let entries = entries.chain(
+            self.entries
+                .iter()
+                .map(|(&index, entry)| (Some(index), entry)),
+        );
+        for (index, entry) in entries {
+            write(&index.map_or(u64::MAX, |index| index as u64).to_le_bytes());
+            write(format!("{:?}", entry.string.tag).as_bytes());
+            write(entry.string.string.as_bytes());
+        }
+        write(format!("{:?}", self.encoding).as_bytes());
+        hash
+    }
+
+    /// Returns the table hash stored in the ELF file, which the firmware sends with its handshake
+    /// frame; it differs from [`Table::hash`] if the ELF file wasn't stamped with
+    /// [`Table::stamp`].
+    pub fn stamped_hash(&self) -> Option<u64> {
+        self.table_hash
+    }
+
+    /// Returns the table hash sent along with the handshake frame at `index`, if it is one.
+    fn handshake_hash(&self, index: usize) -> Option<u64> {
+        match self.entries.get(&index)?.string.tag {
+            Tag::Handshake => self.table_hash,
+            _ => None,
+        }
+    }
+
+    /// Iterates over the unencoded handshake frames the firmware can send.
+    pub fn handshake_frames(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        self.entries.keys().filter_map(|&index| {
+            let hash = self.handshake_hash(index)?;
+            let mut frame = (index as u16).to_le_bytes().to_vec();
+            frame.extend(hash.to_le_bytes());
+            Some(frame)
+        })
+    }
+
     /// Iterates over the raw symbols of the table entries
     pub fn raw_symbols(&self) -> impl Iterator<Item = &str> + '_ {
         self.entries.values().map(|s| &*s.raw_symbol)
     }
 
+    /// Returns the log statements and `println!`s of the firmware, e.g. to compare them with
+    /// those of another version using [`CatalogDiff`].
+    pub fn statements(&self, locations: Option<&Locations>) -> Vec<Statement> {
+        self.indices()
+            .map(|index| {
+                let entry = &self.entries[&index];
+                let crate_name = elf2table::symbol::Symbol::demangle(&entry.raw_symbol)
+                    .map(|symbol| symbol.crate_name().to_owned())
+                    .unwrap_or_default();
+                Statement {
+                    index,
+                    level: entry.string.tag.to_level(),
+                    format: entry.string.string.clone(),
+                    crate_name,
+                    location: locations.and_then(|locs| locs.get(&(index as u64)).cloned()),
+                }
+            })
+            .collect()
+    }
+
     pub fn get_locations(&self, elf: &[u8]) -> Result<Locations, anyhow::Error> {
         elf2table::get_locations(elf, self)
     }
@@ -228,23 +398,59 @@ impl Table {
         let len = bytes.len();
         let index = bytes.read_u16::<LE>()? as u64;
 
+        // handshake frames have no timestamp, and only carry the table hash
+        if let Some(hash) = self.handshake_hash(index as usize) {
+            if bytes.read_u64::<LE>()? != hash {
+                return Err(DecodeError::Malformed);
+            }
+            let frame = Frame::new(
+                self,
+                None,
+                index,
+                None,
+                vec![],
+                "handshake, table hash {=u64:#x}",
+                vec![Arg::Uxx(hash.into())],
+            );
+            return Ok((frame, len - bytes.len()));
+        }
+
         let mut decoder = Decoder::new(self, bytes);
 
         let mut timestamp_format = None;
         let mut timestamp_args = Vec::new();
-        if let Some(entry) = self.timestamp.as_ref() {
+        let entry = if self.timestamp_sources.is_empty() {
+            self.timestamp.as_ref()
+        } else {
+            let id = decoder.bytes.read_u8()?;
+            Some(
+                self.timestamp_sources
+                    .get(&id)
+                    .ok_or(DecodeError::Malformed)?,
+            )
+        };
+        if let Some(entry) = entry {
             let format = &entry.string.string;
             timestamp_format = Some(&**format);
             timestamp_args = decoder.decode_format(format)?;
         }
 
-        let (level, format) = self
-            .get_with_level(index as usize)
-            .map_err(|_| DecodeError::Malformed)?;
+        let mut task_context = None;
+        if let Some(entry) = &self.task_context {
+            let format = &*entry.string.string;
+            task_context = Some((format, decoder.decode_format(format)?));
+        }
+
+        let (level, format) =
+            self.get_with_level(index as usize)
+                .map_err(|_| match self.strict {
+                    true => DecodeError::Anomaly(Anomaly::UnknownIndex { index }),
+                    false => DecodeError::Malformed,
+                })?;
 
         let args = decoder.decode_format(format)?;
 
-        let frame = Frame::new(
+        let mut frame = Frame::new(
             self,
             level,
             index,
@@ -253,11 +459,43 @@ impl Table {
             format,
             args,
         );
+        if let Some((format, args)) = task_context {
+            frame.set_task_context(format, args);
+        }
 
         let consumed = len - decoder.bytes.len();
         Ok((frame, consumed))
     }
 
+    /// Decodes a complete frame, whose size is known from the framing of the encoding. In strict
+    /// mode, frames shorter or longer than their arguments are reported as anomalies.
+    fn decode_complete<'t>(&'t self, bytes: &[u8]) -> Result<Frame<'t>, DecodeError> {
+        let format = |index: u64| {
+            self.get_with_level(index as usize)
+                .map(|(_, format)| format.to_string())
+                .unwrap_or_default()
+        };
+        match self.decode(bytes) {
+            Ok((frame, consumed)) if self.strict && consumed < bytes.len() => {
+                Err(DecodeError::Anomaly(Anomaly::TrailingBytes {
+                    index: frame.index(),
+                    format: format(frame.index()),
+                    count: bytes.len() - consumed,
+                }))
+            }
+            Ok((frame, _)) => Ok(frame),
+            Err(DecodeError::UnexpectedEof) if self.strict && bytes.len() >= 2 => {
+                let index = u16::from_le_bytes([bytes[0], bytes[1]]).into();
+                Err(DecodeError::Anomaly(Anomaly::MissingBytes {
+                    index,
+                    format: format(index),
+                }))
+            }
+            Err(DecodeError::UnexpectedEof) => Err(DecodeError::Malformed),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn new_stream_decoder(&self) -> Box<dyn StreamDecoder + '_> {
         match self.encoding {
             Encoding::Raw => Box::new(stream::Raw::new(self)),
@@ -298,6 +536,35 @@ enum Arg<'t> {
     },
     /// Slice or Array of bytes.
     Slice(Vec<u8>),
+    /// `{=chunked}` byte slice whose continuation frames have not all been received yet; shown
+    /// with the data received so far, marked as truncated
+    Chunked {
+        id: u8,
+        len: usize,
+        data: Vec<u8>,
+    },
+    /// Part of a `{=chunked}` byte slice, from a continuation frame
+    Chunk {
+        id: u8,
+        data: Vec<u8>,
+    },
+    /// Flag of a logical record (`defmt::Record`), from a marker frame
+    Record {
+        id: u8,
+        flag: RecordFlag,
+    },
+    /// `defmt::snapshot!` value sent in full, and its encoding
+    Snapshot {
+        seq: u8,
+        bytes: Vec<u8>,
+        value: Box<Arg<'t>>,
+    },
+    /// `defmt::snapshot!` value sent as the bytes of its encoding that changed since the previous
+    /// snapshot, as `(offset, bytes)` runs
+    SnapshotDelta {
+        seq: u8,
+        runs: Vec<(usize, Vec<u8>)>,
+    },
     /// Char
     Char(char),
 
@@ -313,12 +580,61 @@ struct FormatSliceElement<'t> {
     args: Vec<Arg<'t>>,
 }
 
+/// Flag of a marker frame sent by `defmt::Record`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RecordFlag {
+    Begin,
+    Continue,
+    End,
+}
+
 #[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum DecodeError {
     /// More data is needed to decode the next frame.
     UnexpectedEof,
 
     Malformed,
+
+    /// An anomaly reported in strict mode; see [`Table::set_strict`].
+    Anomaly(Anomaly),
+}
+
+/// Anomaly in the decoded data, hinting at a mismatch between the firmware and the ELF file
+#[derive(Debug, Eq, PartialEq)]
+pub enum Anomaly {
+    /// The index of the frame is not in the table.
+    UnknownIndex { index: u64 },
+    /// The frame is shorter than the arguments of its format string.
+    MissingBytes { index: u64, format: String },
+    /// The frame has bytes left over after the arguments of its format string.
+    TrailingBytes {
+        index: u64,
+        format: String,
+        count: usize,
+    },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::UnknownIndex { index } => {
+                write!(f, "frame index {index:#06x} is not in the table")
+            }
+            Anomaly::MissingBytes { index, format } => write!(
+                f,
+                "frame {index:#06x} ({format:?}) is shorter than the arguments of its format string"
+            ),
+            Anomaly::TrailingBytes {
+                index,
+                format,
+                count,
+            } => write!(
+                f,
+                "frame {index:#06x} ({format:?}) has {count} byte(s) left over after its arguments"
+            ),
+        }
+    }
 }
 
 impl From<io::Error> for DecodeError {
@@ -336,6 +652,9 @@ impl fmt::Display for DecodeError {
         match self {
             DecodeError::UnexpectedEof => f.write_str("unexpected end of stream"),
             DecodeError::Malformed => f.write_str("malformed data"),
+            DecodeError::Anomaly(anomaly) => {
+                write!(f, "{anomaly}; does the ELF file match the firmware?")
+            }
         }
     }
 }
@@ -348,10 +667,19 @@ mod tests {
 
     fn test_table(entries: impl IntoIterator<Item = TableEntry>) -> Table {
         Table {
+            table_hash: None,
             timestamp: None,
             entries: entries.into_iter().enumerate().collect(),
             bitflags: Default::default(),
             encoding: Encoding::Raw,
+            svd: None,
+            redaction: Redaction::default(),
+            show_sensitive: false,
+            tick_rate: None,
+            boot_epoch: None,
+            strict: false,
+            timestamp_sources: BTreeMap::new(),
+            task_context: None,
         }
     }
 
@@ -364,9 +692,7 @@ mod tests {
                 Tag::Timestamp,
                 timestamp.into(),
             )),
-            entries: entries.into_iter().enumerate().collect(),
-            bitflags: Default::default(),
-            encoding: Encoding::Raw,
+            ..test_table(entries)
         }
     }
 
@@ -388,8 +714,7 @@ mod tests {
                 Tag::Timestamp,
                 "{=u8:us}".to_owned(),
             )),
-            bitflags: Default::default(),
-            encoding: Encoding::Raw,
+            ..test_table([])
         };
 
         let frame = table.decode(bytes).unwrap().0;
@@ -484,16 +809,16 @@ mod tests {
                     vec![],
                     FMT,
                     vec![
-                        Arg::Uxx(42),                      // u8
-                        Arg::Uxx(u16::max_value().into()), // u16
-                        Arg::Uxx(u32::max_value().into()), // u32
-                        Arg::Uxx(u64::max_value().into()), // u64
-                        Arg::Uxx(u128::max_value()),       // u128
-                        Arg::Ixx(-1),                      // i8
-                        Arg::Ixx(-1),                      // i16
-                        Arg::Ixx(-1),                      // i32
-                        Arg::Ixx(-1),                      // i64
-                        Arg::Ixx(-1),                      // i128
+                        Arg::Uxx(42),              // u8
+                        Arg::Uxx(u16::MAX.into()), // u16
+                        Arg::Uxx(u32::MAX.into()), // u32
+                        Arg::Uxx(u64::MAX.into()), // u64
+                        Arg::Uxx(u128::MAX),       // u128
+                        Arg::Ixx(-1),              // i8
+                        Arg::Ixx(-1),              // i16
+                        Arg::Ixx(-1),              // i32
+                        Arg::Ixx(-1),              // i64
+                        Arg::Ixx(-1),              // i128
                     ],
                 ),
                 bytes.len(),
@@ -667,6 +992,547 @@ mod tests {
         );
     }
 
+    #[test]
+    fn timestamp_sources() {
+        let entries = vec![TableEntry::new_without_symbol(
+            Tag::Info,
+            "Hello".to_owned(),
+        )];
+        let mut table = test_table(entries);
+        table.set_timestamp_source_entry(
+            0,
+            TableEntry::new_without_symbol(Tag::TimestampSource, "{=u8:us}".to_owned()),
+        );
+        table.set_timestamp_source_entry(
+            1,
+            TableEntry::new_without_symbol(Tag::TimestampSource, "rtc {=u16}".to_owned()),
+        );
+
+        let bytes = [
+            0, 0, // index
+            0, // timestamp source
+            2, // timestamp
+        ];
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.display(false).to_string(), "0.000002 INFO Hello");
+
+        let bytes = [
+            0, 0, // index
+            1, // timestamp source
+            0x39, 0x05, // timestamp
+        ];
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.display(false).to_string(), "rtc 1337 INFO Hello");
+
+        let bytes = [
+            0, 0, // index
+            2, // unknown timestamp source
+        ];
+        assert_eq!(table.decode(&bytes), Err(DecodeError::Malformed));
+    }
+
+    #[test]
+    fn task_context() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "Hello {=u8}".to_owned()),
+            TableEntry::new_without_symbol(Tag::Str, "usb".to_owned()),
+        ];
+        let mut table = test_table_with_timestamp(entries, "{=u8:us}");
+        table.set_task_context_entry(TableEntry::new_without_symbol(
+            Tag::TaskContext,
+            "{=istr}".to_owned(),
+        ));
+
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            1, 0,  // task context
+            42, // argument
+        ];
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            "0.000002 INFO [usb] Hello 42"
+        );
+        assert_eq!(frame.display_message().to_string(), "Hello 42");
+    }
+
+    #[test]
+    fn handshake() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "Hello".to_owned()),
+            TableEntry::new_without_symbol(Tag::Handshake, "handshake".to_owned()),
+        ];
+        let table = Table {
+            table_hash: Some(0xdeadbeef),
+            ..test_table_with_timestamp(entries, "{=u8:us}")
+        };
+
+        let bytes = [
+            1, 0, // index
+            0xef, 0xbe, 0xad, 0xde, 0, 0, 0, 0, // table hash
+        ];
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            "handshake, table hash 0xdeadbeef"
+        );
+
+        let bytes = [1, 0, 0xee, 0xbe, 0xad, 0xde, 0, 0, 0, 0];
+        assert_eq!(table.decode(&bytes), Err(DecodeError::Malformed));
+    }
+
+    #[test]
+    fn table_hash() {
+        let table = |format: &str| {
+            test_table([
+                TableEntry::new_without_symbol(Tag::Info, format.to_owned()),
+                TableEntry::new_without_symbol(Tag::Handshake, "handshake".to_owned()),
+            ])
+        };
+        assert_eq!(table("Hello").hash(), table("Hello").hash());
+        assert_ne!(table("Hello").hash(), table("Hallo").hash());
+        assert_ne!(
+            table("Hello").hash(),
+            test_table_with_timestamp(table("Hello").entries.into_values(), "{=u8}").hash()
+        );
+    }
+
+    #[test]
+    fn table_selector() {
+        let handshake = |hash| Table {
+            table_hash: Some(hash),
+            ..test_table([
+                TableEntry::new_without_symbol(Tag::Info, "Hello".to_owned()),
+                TableEntry::new_without_symbol(Tag::Handshake, "handshake".to_owned()),
+            ])
+        };
+        let mut tables = [handshake(0x12345678), handshake(0xdeadbeef)];
+
+        let mut selector = TableSelector::new(&tables);
+        assert_eq!(selector.received(&[0, 0, 1, 0, 0xef, 0xbe]), None);
+        assert_eq!(
+            selector.received(&[0xad, 0xde, 0, 0, 0, 0, 0, 0]),
+            Some(Handshake {
+                table: 1,
+                preceding: vec![],
+                data: vec![1, 0, 0xef, 0xbe, 0xad, 0xde, 0, 0, 0, 0, 0, 0],
+            })
+        );
+
+        // data preceding the handshake frame in the same call is passed on
+        let mut selector = TableSelector::new(&tables);
+        assert_eq!(selector.received(&[0, 0]), None);
+        assert_eq!(
+            selector.received(&[0, 0, 1, 0, 0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0]),
+            Some(Handshake {
+                table: 0,
+                preceding: vec![0, 0],
+                data: vec![1, 0, 0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0],
+            })
+        );
+
+        for table in &mut tables {
+            table.encoding = Encoding::Rzcobs;
+        }
+        let mut selector = TableSelector::new(&tables);
+        // data before the first separator may be the tail of an earlier frame; it's not accepted
+        let data = [0x01, 0xef, 0xbe, 0xad, 0xde, 0x42, 0x7f, 0x00];
+        assert_eq!(selector.received(&data), None);
+        assert_eq!(selector.received(&[0x01, 0x7e, 0x00]), None);
+        let mut data = vec![0x00];
+        data.extend([0x01, 0xef, 0xbe, 0xad, 0xde, 0x42, 0x7f, 0x00]);
+        assert_eq!(selector.received(&data[..4]), None);
+        assert_eq!(
+            selector.received(&data[4..]),
+            Some(Handshake {
+                table: 1,
+                preceding: vec![],
+                data: data[1..].to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let entries = vec![TableEntry::new_without_symbol(
+            Tag::Info,
+            "x={=u8}".to_owned(),
+        )];
+        let mut table = test_table(entries);
+
+        // without strict mode, extra bytes are ignored
+        assert!(table.decode_complete(&[0, 0, 1, 2]).is_ok());
+        assert_eq!(table.decode_complete(&[0, 0]), Err(DecodeError::Malformed));
+
+        table.set_strict(true);
+        assert!(table.decode_complete(&[0, 0, 1]).is_ok());
+        assert_eq!(
+            table.decode_complete(&[5, 0, 1]),
+            Err(DecodeError::Anomaly(Anomaly::UnknownIndex { index: 5 }))
+        );
+        assert_eq!(
+            table.decode_complete(&[0, 0]),
+            Err(DecodeError::Anomaly(Anomaly::MissingBytes {
+                index: 0,
+                format: "x={=u8}".to_owned()
+            }))
+        );
+        assert_eq!(
+            table
+                .decode_complete(&[0, 0, 1, 2])
+                .unwrap_err()
+                .to_string(),
+            "frame 0x0000 (\"x={=u8}\") has 1 byte(s) left over after its arguments; does the \
+             ELF file match the firmware?"
+        );
+    }
+
+    #[test]
+    fn redaction() {
+        use redact::{Action, Rule};
+
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "device {=?} user={=u8}".to_owned()),
+            TableEntry::new_without_symbol(
+                Tag::Derived,
+                "Device {{ serial: {=u16}, rev: {=u8} }}".to_owned(),
+            ),
+            TableEntry::new_without_symbol(Tag::Info, "pin {=u8}".to_owned()),
+        ];
+        let mut table = test_table_with_timestamp(entries, "{=u8:us}");
+        table.set_redaction(
+            Redaction::new()
+                .rule(Rule::new(Action::Redact).field("serial"))
+                .rule(Rule::new(Action::Redact).statement("pin"))
+                .rule(Rule::new(Action::Hash).field("user"))
+                .salt("pepper"),
+        );
+
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            1, 0, // index of `Device`
+            0x39, 0x30, // serial
+            3,    // rev
+            7,    // user
+        ];
+        let frame = table.decode(&bytes).unwrap().0;
+        let hash = {
+            let mut buf = String::new();
+            table.redaction.apply(Action::Hash, "7", &mut buf);
+            buf
+        };
+        assert_eq!(
+            frame.display(false).to_string(),
+            format!("0.000002 INFO device Device {{ serial: <redacted>, rev: 3 }} user={hash}")
+        );
+        assert!(hash.starts_with("<hash:"));
+
+        let bytes = [2, 0, 3, 42];
+        let frame = table.decode(&bytes).unwrap().0;
+        // the timestamp is never redacted
+        assert_eq!(
+            frame.display(false).to_string(),
+            "0.000003 INFO pin <redacted>"
+        );
+    }
+
+    #[test]
+    fn sensitive() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "{=?}".to_owned()),
+            TableEntry::new_without_symbol(
+                Tag::Derived,
+                "Login {{ user: {=str:?}, pin: {=u16:__internal_sensitive} }}".to_owned(),
+            ),
+        ];
+        let mut table = test_table(entries);
+
+        let bytes = [
+            0, 0, // index
+            1, 0, // index of `Login`
+            1, 0, 0, 0, b'a', // user
+            0xd2, 0x04, // pin
+        ];
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            "INFO Login { user: \"a\", pin: <redacted> }"
+        );
+
+        table.set_show_sensitive(true);
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            "INFO Login { user: \"a\", pin: 1234 }"
+        );
+    }
+
+    #[test]
+    fn chunked() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "dump {=chunked} done".to_owned()),
+            TableEntry::new_without_symbol(Tag::Prim, "{=__internal_Chunk}".to_owned()),
+            TableEntry::new_without_symbol(Tag::Info, "Hello".to_owned()),
+        ];
+        let table = test_table(entries);
+
+        let bytes = [
+            1, 0, // continuation frame of an unknown argument; dropped
+            9, // id
+            1, 0, 0, 0, // length
+            0, // data
+            0, 0, // index
+            7, // id
+            5, 0, 0, 0, // length
+            2, 0, // another frame sent before the continuation frames
+            1, 0, // continuation frame
+            7, // id
+            3, 0, 0, 0, // length
+            1, 2, 3, // data
+            1, 0, // continuation frame
+            7, // id
+            2, 0, 0, 0, // length
+            4, 5, // data
+        ];
+
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&bytes);
+        let frame = decoder.decode().unwrap();
+        assert_eq!(frame.display(false).to_string(), "INFO Hello");
+        let frame = decoder.decode().unwrap();
+        assert_eq!(
+            frame.display(false).to_string(),
+            "INFO dump [1, 2, 3, 4, 5] done"
+        );
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+    }
+
+    #[test]
+    fn chunked_truncated() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "dump {=chunked}".to_owned()),
+            TableEntry::new_without_symbol(Tag::Prim, "{=__internal_Chunk}".to_owned()),
+        ];
+        let table = test_table(entries);
+
+        let bytes = [
+            0, 0, // index
+            7, // id
+            255, 255, 255, 255, // length; nothing is allocated for it up front
+            1, 0, // continuation frame
+            7, // id
+            2, 0, 0, 0, // length
+            1, 2, // data
+            0, 0, // index
+            7, // id, reused once the ids wrapped around; the previous frame is given up on
+            1, 0, 0, 0, // length
+            1, 0, // continuation frame
+            7, // id
+            1, 0, 0, 0, // length
+            3, // data
+        ];
+
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&bytes);
+        let frame = decoder.decode().unwrap();
+        assert_eq!(
+            frame.display(false).to_string(),
+            "INFO dump [1, 2] <truncated: 2 of 4294967295 bytes received>"
+        );
+        let frame = decoder.decode().unwrap();
+        assert_eq!(frame.display(false).to_string(), "INFO dump [3]");
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+
+        // the stream ends before all data arrived
+        decoder.received(&[0, 0, 8, 2, 0, 0, 0, 1, 0, 8, 1, 0, 0, 0, 4]);
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+        decoder.finish();
+        let frame = decoder.decode().unwrap();
+        assert_eq!(
+            frame.display(false).to_string(),
+            "INFO dump [4] <truncated: 1 of 2 bytes received>"
+        );
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+    }
+
+    #[test]
+    fn chunked_pending_limit() {
+        let entries = vec![TableEntry::new_without_symbol(
+            Tag::Info,
+            "dump {=chunked}".to_owned(),
+        )];
+        let table = test_table(entries);
+
+        let mut decoder = table.new_stream_decoder();
+        for id in 0..=16 {
+            decoder.received(&[0, 0, id, 1, 0, 0, 0]);
+        }
+        let frame = decoder.decode().unwrap();
+        assert_eq!(
+            frame.display(false).to_string(),
+            "INFO dump [] <truncated: 0 of 1 bytes received>"
+        );
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+    }
+
+    #[test]
+    fn records() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "part {=u8}".to_owned()),
+            TableEntry::new_without_symbol(Tag::Prim, "{=__internal_Record}".to_owned()),
+            TableEntry::new_without_symbol(Tag::Error, "failed".to_owned()),
+        ];
+        let mut table = test_table(entries);
+        table.set_task_context_entry(TableEntry::new_without_symbol(
+            Tag::TaskContext,
+            "{=u8}".to_owned(),
+        ));
+
+        let bytes = [
+            1, 0, // record marker
+            1, // task context
+            3, // id
+            0, // begin
+            0, 0, // index
+            1, // task context
+            1, // argument
+            0, 0, // frame of another task, interleaved with the record
+            2, // task context
+            9, // argument
+            0, 0, // index
+            1, // task context
+            2, // argument
+            1, 0, // record marker
+            1, // task context
+            3, // id
+            2, // end
+        ];
+
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&bytes);
+        for expected in ["INFO [2] part 9", "INFO [1] part 1", "INFO [1] part 2"] {
+            let frame = decoder.decode().unwrap();
+            assert_eq!(frame.display(false).to_string(), expected);
+        }
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+
+        // a record that doesn't end is shown once it holds too many frames
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&[1, 0, 1, 4, 0]);
+        for i in 0..stream::MAX_RECORD_FRAMES as u8 {
+            assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+            decoder.received(&[0, 0, 1, i]);
+        }
+        for i in 0..stream::MAX_RECORD_FRAMES as u8 {
+            let frame = decoder.decode().unwrap();
+            assert_eq!(
+                frame.display(false).to_string(),
+                format!("INFO [1] part {i}")
+            );
+        }
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+
+        // an ERROR frame is shown right away, after the frames of its record held back so far
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&[1, 0, 1, 5, 0, 0, 0, 1, 1]);
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+        decoder.received(&[2, 0, 1]);
+        for expected in ["INFO [1] part 1", "ERROR [1] failed"] {
+            let frame = decoder.decode().unwrap();
+            assert_eq!(frame.display(false).to_string(), expected);
+        }
+        // the record is still open
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+        decoder.received(&[0, 0, 1, 3]);
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+
+        // at the end of the stream, the frames of records that haven't ended are shown
+        decoder.finish();
+        let frame = decoder.decode().unwrap();
+        assert_eq!(frame.display(false).to_string(), "INFO [1] part 3");
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+    }
+
+    #[test]
+    fn records_without_task_context() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "part {=u8}".to_owned()),
+            TableEntry::new_without_symbol(Tag::Prim, "{=__internal_Record}".to_owned()),
+        ];
+        let table = test_table(entries);
+
+        let bytes = [
+            1, 0, // record marker
+            3, // id
+            0, // begin
+            0, 0, // index
+            1, // argument
+        ];
+
+        // the frames of other tasks can't be told apart, so nothing is held back
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&bytes);
+        let frame = decoder.decode().unwrap();
+        assert_eq!(frame.display(false).to_string(), "INFO part 1");
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+    }
+
+    #[test]
+    fn snapshots() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "status = {=__internal_Snapshot}".to_owned()),
+            TableEntry::new_without_symbol(
+                Tag::Derived,
+                "Status {{ uptime: {=u32}, battery: {=u8} }}".to_owned(),
+            ),
+        ];
+        let table = test_table(entries);
+
+        let bytes = [
+            0, 0, // index
+            0, // sequence number
+            0, // full
+            1, 0, // index of `Status`
+            0xe8, 0x03, 0, 0, // uptime
+            5, // battery
+            0, 0, // index
+            1, // sequence number
+            1, // delta
+            1, // runs
+            6, // offset
+            1, // length
+            6, // battery
+            0, 0,    // index
+            3,    // sequence number; the previous snapshot was lost
+            1,    // delta
+            1,    // runs
+            2,    // offset
+            1,    // length
+            0xe9, // uptime
+            0, 0, // index
+            4, // sequence number
+            0, // full
+            1, 0, // index of `Status`
+            0xea, 0x03, 0, 0, // uptime
+            6, // battery
+        ];
+
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&bytes);
+        for expected in [
+            "INFO status = Status { uptime: 1000, battery: 5 }",
+            "INFO status = Status { uptime: 1000, battery: 6 }",
+            "INFO status = <delta of snapshot without its previous value>",
+            "INFO status = Status { uptime: 1002, battery: 6 }",
+        ] {
+            let frame = decoder.decode().unwrap();
+            assert_eq!(frame.display(false).to_string(), expected);
+        }
+        assert_eq!(decoder.decode(), Err(DecodeError::UnexpectedEof));
+    }
+
     #[test]
     fn display_i16_with_hex_hint() {
         // defmt::info!("x: {=i16:#x},y: {=i16:#x},z: {=i16:#x}", -1_i16, -100_i16, -1000_i16);
@@ -798,6 +1664,254 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_duration() {
+        let mut bytes = vec![
+            0, 0, // index
+            2, // timestamp
+        ];
+        // 1500 ticks of 1/1_000_000 s
+        let packed = 1_500 | 1 << 64 | 1_000_000 << 96;
+        bytes.extend_from_slice(&u128::to_le_bytes(packed));
+
+        decode_and_expect(
+            "{=u128:__internal_duration}",
+            &bytes,
+            "0.000002 INFO 1.5 ms",
+        );
+    }
+
+    #[test]
+    fn display_duration_odd_ratio() {
+        let mut bytes = vec![
+            0, 0, // index
+            2, // timestamp
+        ];
+        // 3 ticks of 1/32_768 s
+        let packed = 3 | 1 << 64 | 32_768 << 96;
+        bytes.extend_from_slice(&u128::to_le_bytes(packed));
+
+        decode_and_expect(
+            "{=u128:__internal_duration}",
+            &bytes,
+            "0.000002 INFO 91.552 us",
+        );
+    }
+
+    #[test]
+    fn display_rate() {
+        let mut bytes = vec![
+            0, 0, // index
+            2, // timestamp
+        ];
+        // 8 ticks of 1_000_000 Hz
+        let packed = 8 | 1_000_000 << 64 | 1 << 96;
+        bytes.extend_from_slice(&u128::to_le_bytes(packed));
+
+        decode_and_expect("{=u128:__internal_rate}", &bytes, "0.000002 INFO 8 MHz");
+    }
+
+    #[test]
+    fn display_epoch_seconds() {
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            160, 158, 126, 96, // unix timestamp in bytes: 1618910880
+        ];
+
+        decode_and_expect("{=u32:ts}", &bytes, "0.000002 INFO 2021-04-20T09:28:00Z");
+    }
+
+    #[test]
+    fn display_epoch_millis() {
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            36, 188, 151, 238, 120, 1, 0, 0, // unix timestamp in bytes: 1618910624804
+        ];
+
+        decode_and_expect(
+            "{=u64:tsms}",
+            &bytes,
+            "0.000002 INFO 2021-04-20T09:23:44.804Z",
+        );
+    }
+
+    #[test]
+    fn display_ticks() {
+        let entries = vec![TableEntry::new_without_symbol(
+            Tag::Info,
+            "Hello".to_owned(),
+        )];
+        let mut table = test_table_with_timestamp(entries, "{=u32:tick}");
+        let bytes = [
+            0, 0, // index
+            0x00, 0x80, 0x01, 0x00, // timestamp: 98_304 ticks
+        ];
+
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.display(false).to_string(), "98304 INFO Hello");
+
+        table.set_tick_rate(NonZeroU64::new(65_536).unwrap());
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.display(false).to_string(), "1.500000 INFO Hello");
+
+        table.set_boot_epoch(1618910624);
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            "2021-04-20T09:23:45.500000Z INFO Hello"
+        );
+    }
+
+    #[test]
+    fn display_ticks_large() {
+        let entries = vec![TableEntry::new_without_symbol(
+            Tag::Info,
+            "Hello".to_owned(),
+        )];
+        let mut table = test_table_with_timestamp(entries, "{=u128:tick}");
+        let mut bytes = vec![0, 0]; // index
+        bytes.extend(u128::MAX.to_le_bytes()); // timestamp
+
+        table.set_tick_rate(NonZeroU64::new(2).unwrap());
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            format!("{}.500000 INFO Hello", u128::MAX / 2)
+        );
+
+        // out of the range of dates
+        table.set_boot_epoch(1618910624);
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            format!("{}.500000 INFO Hello", u128::MAX / 2)
+        );
+    }
+
+    #[test]
+    fn timestamp() {
+        let entries = || {
+            vec![TableEntry::new_without_symbol(
+                Tag::Info,
+                "Hello".to_owned(),
+            )]
+        };
+        let bytes = [
+            0, 0, // index
+            0x00, 0x80, 0x01, 0x00, // timestamp
+        ];
+
+        let mut table = test_table_with_timestamp(entries(), "{=u32:tick}");
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.timestamp().unwrap().count, 98_304);
+        assert_eq!(frame.timestamp().unwrap().rate, None);
+
+        table.set_tick_rate(NonZeroU64::new(65_536).unwrap());
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.timestamp().unwrap().rate, NonZeroU64::new(65_536));
+
+        let table = test_table_with_timestamp(entries(), "{=u32:us}");
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.timestamp().unwrap().rate, NonZeroU64::new(1_000_000));
+
+        let table = test_table_with_timestamp(entries(), "{=u16}.{=u16}");
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.timestamp(), None);
+
+        let table = test_table(entries());
+        let frame = table.decode(&bytes[..2]).unwrap().0;
+        assert_eq!(frame.timestamp(), None);
+    }
+
+    #[test]
+    fn timing_analysis() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "sample".to_owned()),
+            TableEntry::new_without_symbol(Tag::Debug, "idle".to_owned()),
+        ];
+        let table = test_table_with_timestamp(entries, "{=u8:us}");
+
+        let mut analysis = TimingAnalysis::new();
+        // index, timestamp
+        for bytes in [[0, 0, 10], [1, 0, 15], [0, 0, 20], [0, 0, 32], [0, 0, 2]] {
+            analysis.frame(&table.decode(&bytes).unwrap().0);
+        }
+        analysis.frame(&table.decode(&[0, 0, 12]).unwrap().0);
+
+        let timings = analysis.timings().collect::<Vec<_>>();
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].format, "sample");
+        assert_eq!(timings[0].frames, 5);
+        // the device was reset before timestamp 2
+        assert_eq!(timings[0].periods(), 3);
+        assert_eq!((timings[0].min(), timings[0].max()), (Some(10), Some(12)));
+        assert_eq!(timings[0].rate, NonZeroU64::new(1_000_000));
+        assert_eq!(timings[1].index, 1);
+        assert_eq!(timings[1].periods(), 0);
+    }
+
+    #[test]
+    fn display_register() {
+        let entries = vec![TableEntry::new_without_symbol(
+            Tag::Info,
+            "SR={=u32:reg:USART1.SR} BRR={=u32:reg:USART1.BRR} CR1={=u32:reg:USART1.CR1}"
+                .to_owned(),
+        )];
+        let mut table = test_table_with_timestamp(entries, "{=u8:us}");
+        table.set_svd(
+            Svd::parse(
+                r#"<device><peripherals><peripheral><name>USART1</name><registers>
+                <register><name>SR</name><fields>
+                    <field><name>TC</name><bitOffset>6</bitOffset><bitWidth>1</bitWidth></field>
+                    <field><name>TXE</name><bitOffset>7</bitOffset><bitWidth>1</bitWidth></field>
+                    <field><name>RXNE</name><bitOffset>5</bitOffset><bitWidth>1</bitWidth></field>
+                </fields></register>
+                <register><name>BRR</name><fields>
+                    <field><name>DIV_Fraction</name><bitOffset>0</bitOffset><bitWidth>4</bitWidth></field>
+                    <field><name>DIV_Mantissa</name><bitOffset>4</bitOffset><bitWidth>12</bitWidth></field>
+                </fields></register>
+                </registers></peripheral></peripherals></device>"#,
+            )
+            .unwrap(),
+        );
+
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            0xc0, 0, 0, 0, // SR
+            0x50, 0x04, 0, 0, // BRR
+            0xc0, 0, 0, 0, // CR1, not described by the SVD file
+        ];
+
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            "0.000002 INFO SR=TC | TXE BRR=DIV_Mantissa=0x45 CR1=0xc0",
+        );
+    }
+
+    #[test]
+    fn cbor() {
+        let bytes = [
+            0, 0,    // index
+            2,    // timestamp
+            0xa2, // map(2)
+            0x64, b'n', b'a', b'm', b'e', // text(4) "name"
+            0x66, b'u', b'p', b'l', b'i', b'n', b'k', // text(6) "uplink"
+            0x67, b'r', b'e', b't', b'r', b'i', b'e', b's', // text(7) "retries"
+            0x82, 0x03, 0x38, 0x63, // array(2) [3, -100]
+            1,    // trailing arg
+        ];
+
+        decode_and_expect(
+            "{=__internal_Cbor} {=u8}",
+            &bytes,
+            r#"0.000002 INFO {"name": "uplink", "retries": [3, -100]} 1"#,
+        );
+    }
+
     #[test]
     fn bools_simple() {
         let bytes = [
@@ -1005,6 +2119,51 @@ mod tests {
         decode_and_expect("x={=[u8]}", &bytes, "0.000002 INFO x=[23, 42]");
     }
 
+    #[test]
+    fn word_slice_with_hint() {
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            2, 0, 0, 0, // length of the slice
+            0xef, 0xbe, // slice content
+            0x0d, 0xf0,
+        ];
+        decode_and_expect("x={=[u16]:#x}", &bytes, "0.000002 INFO x=[0xbeef, 0xf00d]");
+    }
+
+    #[test]
+    fn word_slice_u32_binary() {
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            1, 0, 0, 0, // length of the slice
+            5, 0, 0, 0, // slice content
+            1, // trailing arg
+        ];
+        decode_and_expect("x={=[u32]:b} y={=u8}", &bytes, "0.000002 INFO x=[101] y=1");
+    }
+
+    #[test]
+    fn iter() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "x={=iter}".to_owned()),
+            TableEntry::new_without_symbol(Tag::Prim, "{=u8:x}".to_owned()),
+        ];
+
+        let table = test_table_with_timestamp(entries, "{=u8:us}");
+
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            2, 0, 0, 0, // number of elements
+            1, 0, // index of the element format
+            23, 42, // elements
+        ];
+
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(frame.display(false).to_string(), "0.000002 INFO x=[17, 2a]");
+    }
+
     #[test]
     fn slice_with_trailing_args() {
         let bytes = [
@@ -1088,8 +2247,7 @@ mod tests {
                 Tag::Timestamp,
                 "{=u8:us}".to_owned(),
             )),
-            bitflags: Default::default(),
-            encoding: Encoding::Raw,
+            ..test_table([])
         };
 
         let bytes = [
@@ -1,4 +1,4 @@
-use defmt_json_schema::v1::{JsonFrame, Location, ModulePath, SCHEMA_VERSION};
+use defmt_json_schema::v2::{JsonFrame, Location, ModulePath, SCHEMA_VERSION};
 use log::{Log, Metadata, Record};
 use time::OffsetDateTime;
 
@@ -67,6 +67,7 @@ fn create_json_frame(record: DefmtRecord, host_timestamp: i64) -> JsonFrame {
             module_path: create_module_path(record.module_path()),
         },
         target_timestamp: record.timestamp().to_string(),
+        task: record.task().map(str::to_string),
     }
 }
 
@@ -0,0 +1,195 @@
+//! Forwarding of defmt frames to a host application's own `log` setup
+
+use std::cmp::Reverse;
+
+use log::{Level, Record};
+
+use crate::Frame;
+
+/// Maps defmt frames to plain `log` records.
+///
+/// Unlike [`log_defmt`](super::log_defmt), whose records are only understood by the loggers in
+/// this module, [`Mapping::log`] produces records with a regular target and level, so that they
+/// can be filtered and printed by any `log` implementation.
+///
+/// ```
+/// use defmt_decoder::log::Mapping;
+///
+/// let mapping = Mapping::new()
+///     .rename("my_firmware::drivers", "radio")
+///     .prefix("device::")
+///     .level(defmt_parser::Level::Trace, log::Level::Debug);
+///
+/// assert_eq!(mapping.target(Some("my_firmware::drivers::spi")), "device::radio::spi");
+/// assert_eq!(mapping.target(Some("my_firmware::main")), "device::my_firmware::main");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    renames: Vec<(String, String)>,
+    prefix: String,
+    levels: Vec<(crate::Level, Level)>,
+    println_level: Level,
+    default_target: String,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Self {
+            renames: Vec::new(),
+            prefix: String::new(),
+            levels: Vec::new(),
+            println_level: Level::Info,
+            default_target: "defmt".to_string(),
+        }
+    }
+}
+
+impl Mapping {
+    /// Creates a mapping which uses the module paths as targets and keeps the levels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the module path prefix `module` with `target`.
+    ///
+    /// `module` only matches whole path segments. If several renames match, the longest one is
+    /// used.
+    pub fn rename(mut self, module: &str, target: &str) -> Self {
+        self.renames.push((module.to_string(), target.to_string()));
+        // longest prefix first, so that the first match is the most specific one
+        self.renames
+            .sort_by_key(|(module, _)| Reverse(module.len()));
+        self
+    }
+
+    /// Prepends `prefix` to every target, after renaming.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Logs frames of the defmt level `from` with the `log` level `to`.
+    pub fn level(mut self, from: crate::Level, to: Level) -> Self {
+        self.levels.retain(|(level, _)| *level != from);
+        self.levels.push((from, to));
+        self
+    }
+
+    /// Sets the `log` level of frames without a level, i.e. those of `defmt::println!`.
+    ///
+    /// Defaults to `Info`.
+    pub fn println_level(mut self, level: Level) -> Self {
+        self.println_level = level;
+        self
+    }
+
+    /// Sets the target of frames without location info. Defaults to `defmt`.
+    pub fn default_target(mut self, target: &str) -> Self {
+        self.default_target = target.to_string();
+        self
+    }
+
+    /// Returns the `log` target of a frame logged from `module_path`.
+    pub fn target(&self, module_path: Option<&str>) -> String {
+        let Some(module_path) = module_path else {
+            return format!("{}{}", self.prefix, self.default_target);
+        };
+
+        let renamed = self.renames.iter().find_map(|(module, target)| {
+            let rest = module_path.strip_prefix(module.as_str())?;
+            match rest.is_empty() || rest.starts_with("::") {
+                true => Some(format!("{target}{rest}")),
+                false => None,
+            }
+        });
+        format!(
+            "{}{}",
+            self.prefix,
+            renamed.as_deref().unwrap_or(module_path)
+        )
+    }
+
+    /// Returns the `log` level of a frame with the defmt level `level`.
+    pub fn map_level(&self, level: Option<crate::Level>) -> Level {
+        let Some(level) = level else {
+            return self.println_level;
+        };
+
+        self.levels
+            .iter()
+            .find(|(from, _)| *from == level)
+            .map(|(_, to)| *to)
+            .unwrap_or(match level {
+                crate::Level::Trace => Level::Trace,
+                crate::Level::Debug => Level::Debug,
+                crate::Level::Info => Level::Info,
+                crate::Level::Warn => Level::Warn,
+                crate::Level::Error => Level::Error,
+            })
+    }
+
+    /// Logs a defmt frame as a plain `log` record; its timestamp and task context, if any, precede
+    /// the message.
+    pub fn log(
+        &self,
+        frame: &Frame<'_>,
+        file: Option<&str>,
+        line: Option<u32>,
+        module_path: Option<&str>,
+    ) {
+        let target = self.target(module_path);
+        let level = self.map_level(frame.level());
+        let timestamp = frame
+            .display_timestamp()
+            .map(|ts| format!("{ts} "))
+            .unwrap_or_default();
+        let task_context = frame
+            .display_task_context()
+            .map(|task_context| format!("[{task_context}] "))
+            .unwrap_or_default();
+
+        log::logger().log(
+            &Record::builder()
+                .args(format_args!(
+                    "{timestamp}{task_context}{}",
+                    frame.display_message()
+                ))
+                .level(level)
+                .target(&target)
+                .module_path(module_path)
+                .file(file)
+                .line(line)
+                .build(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target() {
+        let mapping = Mapping::new()
+            .rename("app", "firmware")
+            .rename("app::net::tcp", "tcp");
+
+        assert_eq!(mapping.target(Some("app::net::tcp::rx")), "tcp::rx");
+        assert_eq!(mapping.target(Some("app::net::udp")), "firmware::net::udp");
+        assert_eq!(mapping.target(Some("app")), "firmware");
+        // only whole path segments match
+        assert_eq!(mapping.target(Some("application")), "application");
+        assert_eq!(mapping.target(None), "defmt");
+    }
+
+    #[test]
+    fn level() {
+        let mapping = Mapping::new()
+            .level(crate::Level::Trace, Level::Debug)
+            .println_level(Level::Warn);
+
+        assert_eq!(mapping.map_level(Some(crate::Level::Trace)), Level::Debug);
+        assert_eq!(mapping.map_level(Some(crate::Level::Error)), Level::Error);
+        assert_eq!(mapping.map_level(None), Level::Warn);
+    }
+}
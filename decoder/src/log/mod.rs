@@ -7,6 +7,7 @@
 //! [`defmt`]: https://crates.io/crates/defmt
 
 mod json_logger;
+mod mapping;
 mod pretty_logger;
 
 use log::{Level, LevelFilter, Metadata, Record};
@@ -14,6 +15,7 @@ use serde::{Deserialize, Serialize};
 
 use std::fmt;
 
+pub use self::mapping::Mapping;
 use self::{json_logger::JsonLogger, pretty_logger::PrettyLogger};
 use crate::Frame;
 
@@ -39,10 +41,19 @@ pub fn log_defmt(
         crate::Level::Error => Level::Error,
     });
 
+    let task = frame
+        .display_task_context()
+        .map(|task_context| task_context.to_string());
+
     let target = format!(
         "{}{}",
         DEFMT_TARGET_MARKER,
-        serde_json::to_value(Payload { timestamp, level }).unwrap()
+        serde_json::to_value(Payload {
+            timestamp,
+            level,
+            task
+        })
+        .unwrap()
     );
 
     log::logger().log(
@@ -57,6 +68,13 @@ pub fn log_defmt(
     );
 }
 
+/// Returns the JSON Schema document describing the output of the JSON logger.
+///
+/// The schema version of the output is declared in its first line, `{"schema_version":N}`.
+pub fn json_schema() -> &'static str {
+    defmt_json_schema::v2::JSON_SCHEMA
+}
+
 /// Determines whether `metadata` belongs to a log record produced by [`log_defmt`].
 pub fn is_defmt_frame(metadata: &Metadata) -> bool {
     metadata.target().starts_with(DEFMT_TARGET_MARKER)
@@ -72,6 +90,8 @@ pub struct DefmtRecord<'a> {
 struct Payload {
     level: Option<Level>,
     timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    task: Option<String>,
 }
 
 impl<'a> DefmtRecord<'a> {
@@ -95,6 +115,11 @@ impl<'a> DefmtRecord<'a> {
         self.payload.level
     }
 
+    /// Returns the formatted task context, if the firmware defines one with `task_context!`.
+    pub fn task(&self) -> Option<&str> {
+        self.payload.task.as_deref()
+    }
+
     pub fn args(&self) -> &fmt::Arguments<'a> {
         self.log_record.args()
     }
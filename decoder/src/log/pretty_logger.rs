@@ -113,7 +113,7 @@ impl PrettyLogger {
             false => format!("{} ", record.timestamp()),
         };
 
-        writeln!(&mut sink, "{timestamp}{}", record.args()).ok();
+        writeln!(&mut sink, "{timestamp}{}{}", task(&record), record.args()).ok();
         print_location(
             &mut sink,
             record.file(),
@@ -163,13 +163,13 @@ impl<'a> Printer<'a> {
     /// The format is as follows (this is not part of the stable API and may change):
     ///
     /// ```text
-    /// <timestamp> <level> <args>
+    /// <timestamp> <level> [<task>] <args>
     /// └─ <module> @ <file>:<line>
     /// ```
     pub fn print_colored<W: io::Write>(&self, sink: &mut W) -> io::Result<()> {
         writeln!(
             sink,
-            "{timestamp:>0$}{spacing}{level:5} {args}",
+            "{timestamp:>0$}{spacing}{level:5} {task}{args}",
             self.min_timestamp_width,
             timestamp = self.record.timestamp(),
             spacing = if self.record.timestamp().is_empty() {
@@ -181,6 +181,7 @@ impl<'a> Printer<'a> {
                 .level
                 .to_string()
                 .color(color_for_log_level(self.level)),
+            task = task(self.record),
             args = color_diff(self.record.args().to_string()),
         )?;
 
@@ -295,3 +296,11 @@ fn print_location<W: io::Write>(
 
     Ok(())
 }
+
+/// The task context of `record` in brackets, followed by a space; empty if it has none.
+fn task(record: &DefmtRecord) -> String {
+    record
+        .task()
+        .map(|task| format!("[{task}] "))
+        .unwrap_or_default()
+}
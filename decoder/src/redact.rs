@@ -0,0 +1,176 @@
+//! Redaction of argument values, e.g. serial numbers or credentials, before they are displayed or
+//! exported
+
+use anyhow::{anyhow, bail};
+
+/// What is shown instead of a redacted value
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    /// Replaces the value with `<redacted>`.
+    Redact,
+    /// Replaces the value with a salted hash of it, so that equal values can still be correlated.
+    ///
+    /// NOTE: this is not a cryptographic hash; values from a small domain can be recovered by
+    /// anyone who knows the salt.
+    Hash,
+}
+
+/// Selects the arguments to redact
+///
+/// A rule without a statement and field applies to every argument.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rule {
+    statement: Option<String>,
+    field: Option<String>,
+    action: Action,
+}
+
+impl Rule {
+    pub fn new(action: Action) -> Self {
+        Self {
+            statement: None,
+            field: None,
+            action,
+        }
+    }
+
+    /// Parses a comma separated list of `statement=TEXT` and `field=NAME` selectors.
+    pub fn parse(selectors: &str, action: Action) -> Result<Self, anyhow::Error> {
+        let mut rule = Self::new(action);
+        for selector in selectors.split(',') {
+            let (key, value) = selector
+                .split_once('=')
+                .ok_or_else(|| anyhow!("malformed redaction selector `{selector}`"))?;
+            match key.trim() {
+                "statement" => rule = rule.statement(value),
+                "field" => rule = rule.field(value.trim()),
+                _ => bail!("unknown redaction selector `{key}`; expected `statement` or `field`"),
+            }
+        }
+        Ok(rule)
+    }
+
+    /// Only applies the rule to log statements whose format string contains `text`.
+    pub fn statement(mut self, text: impl Into<String>) -> Self {
+        self.statement = Some(text.into());
+        self
+    }
+
+    /// Only applies the rule to arguments named `name`, i.e. those written as `name: {}` or
+    /// `name={}`, like the fields of `#[derive(Format)]` structs.
+    pub fn field(mut self, name: impl Into<String>) -> Self {
+        self.field = Some(name.into());
+        self
+    }
+
+    fn matches(&self, statement: &str, field: Option<&str>) -> bool {
+        let statement_matches = match &self.statement {
+            Some(text) => statement.contains(&**text),
+            None => true,
+        };
+        let field_matches = match &self.field {
+            Some(name) => field == Some(&**name),
+            None => true,
+        };
+        statement_matches && field_matches
+    }
+}
+
+/// Rules selecting the arguments whose values are not shown
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Redaction {
+    rules: Vec<Rule>,
+    salt: String,
+}
+
+impl Redaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule. If several rules match an argument, the first one is used.
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Sets the salt mixed into hashed values.
+    pub fn salt(mut self, salt: impl Into<String>) -> Self {
+        self.salt = salt.into();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns the action for an argument of the log statement `statement`, written after the
+    /// `literal` text.
+    pub(crate) fn action(&self, statement: &str, literal: &str) -> Option<Action> {
+        let field = field_name(literal);
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(statement, field))
+            .map(|rule| rule.action)
+    }
+
+    /// Writes what is shown instead of `value`.
+    pub(crate) fn apply(&self, action: Action, value: &str, buf: &mut String) {
+        match action {
+            Action::Redact => buf.push_str("<redacted>"),
+            Action::Hash => buf.push_str(&format!("<hash:{:016x}>", self.hash(value))),
+        }
+    }
+
+    /// 64-bit FNV-1a hash of the salt and `value`, stable across platforms and releases
+    fn hash(&self, value: &str) -> u64 {
+        let salt = self.salt.bytes().chain([0xff]);
+        salt.chain(value.bytes())
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+/// Returns the name written right before a parameter, e.g. `serial` in `Device { serial: ` or
+/// `serial=`.
+fn field_name(literal: &str) -> Option<&str> {
+    let literal = literal.trim_end();
+    let literal = literal
+        .strip_suffix(':')
+        .or_else(|| literal.strip_suffix('='))?
+        .trim_end();
+    let start = literal
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()?
+        .0;
+    let name = &literal[start..];
+    (!name.starts_with(|c: char| c.is_ascii_digit())).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_names() {
+        assert_eq!(field_name("Device { serial: "), Some("serial"));
+        assert_eq!(field_name("login as user="), Some("user"));
+        assert_eq!(field_name("key = "), Some("key"));
+        assert_eq!(field_name("value "), None);
+        assert_eq!(field_name("ratio: 1:"), None);
+        assert_eq!(field_name(""), None);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Rule::parse("statement=login,field=password", Action::Hash).unwrap(),
+            Rule::new(Action::Hash).statement("login").field("password")
+        );
+        assert!(Rule::parse("field", Action::Redact).is_err());
+        assert!(Rule::parse("name=password", Action::Redact).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Selection of the table a stream was encoded with, based on its handshake frame
+
+use crate::{stream::rzcobs_decode, Encoding, Table};
+
+/// Number of bytes kept between calls to [`TableSelector::received`]; enough to hold an encoded
+/// handshake frame and its separators.
+const MAX_HANDSHAKE_LEN: usize = 16;
+
+/// A handshake frame found by [`TableSelector::received`]
+#[derive(Debug, Eq, PartialEq)]
+pub struct Handshake {
+    /// Position of the table whose handshake frame was received
+    pub table: usize,
+    /// The received data preceding the handshake frame, which is meant for the stream decoder of
+    /// the table selected before
+    pub preceding: Vec<u8>,
+    /// The data starting at the handshake frame, which is meant for the stream decoder of `table`
+    pub data: Vec<u8>,
+}
+
+/// Finds the handshake frame sent by `defmt::handshake!` in a stream of data, and with it the
+/// table that the stream was encoded with.
+pub struct TableSelector {
+    /// The unencoded handshake frames, along with the encoding and position of their table
+    handshakes: Vec<(Encoding, Vec<u8>, usize)>,
+    buf: Vec<u8>,
+}
+
+impl TableSelector {
+    /// Creates a selector choosing between `tables`. Tables without a handshake frame are never
+    /// selected.
+    pub fn new<'t>(tables: impl IntoIterator<Item = &'t Table>) -> Self {
+        let handshakes = tables
+            .into_iter()
+            .enumerate()
+            .flat_map(|(position, table)| {
+                table
+                    .handshake_frames()
+                    .map(move |frame| (table.encoding(), frame, position))
+            })
+            .collect();
+        Self {
+            handshakes,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Pushes received data to the selector, and returns the handshake frame found in it, if any.
+    pub fn received(&mut self, data: &[u8]) -> Option<Handshake> {
+        // the data kept from earlier calls has already been passed on
+        let kept = self.buf.len();
+        self.buf.extend_from_slice(data);
+
+        let found = self
+            .handshakes
+            .iter()
+            .filter_map(|(encoding, frame, position)| {
+                let start = match encoding {
+                    Encoding::Raw => self
+                        .buf
+                        .windows(frame.len())
+                        .position(|window| window == &frame[..]),
+                    Encoding::Rzcobs => find_rzcobs(&self.buf, frame),
+                }?;
+                Some((start, *position))
+            })
+            .min();
+
+        match found {
+            Some((start, table)) => {
+                let data = self.buf.split_off(start);
+                let preceding = self.buf.split_off(kept.min(start));
+                self.buf.clear();
+                Some(Handshake {
+                    table,
+                    preceding,
+                    data,
+                })
+            }
+            None => {
+                let excess = self.buf.len().saturating_sub(MAX_HANDSHAKE_LEN);
+                self.buf.drain(..excess);
+                None
+            }
+        }
+    }
+}
+
+/// Returns the start of the first complete rzCOBS frame in `buf` that decodes to `frame`.
+fn find_rzcobs(buf: &[u8], frame: &[u8]) -> Option<usize> {
+    let separators = buf
+        .iter()
+        .enumerate()
+        .filter(|(_, &byte)| byte == 0)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    separators.windows(2).find_map(|pair| {
+        let (start, end) = (pair[0] + 1, pair[1]);
+        let decoded = rzcobs_decode(buf.get(start..end).filter(|data| !data.is_empty())?).ok()?;
+        // trailing zeros are not encoded, so the decoded frame may be padded with zeros
+        let matches = decoded.len() >= frame.len()
+            && decoded[..frame.len()] == *frame
+            && decoded[frame.len()..].iter().all(|&byte| byte == 0);
+        matches.then_some(start)
+    })
+}
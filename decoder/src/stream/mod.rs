@@ -4,7 +4,16 @@ mod rzcobs;
 pub use raw::Raw;
 pub use rzcobs::Rzcobs;
 
-use crate::{DecodeError, Frame};
+pub(crate) use rzcobs::rzcobs_decode;
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    mem,
+};
+
+use defmt_parser::Level;
+
+use crate::{decoder::Decoder, Arg, DecodeError, Frame, RecordFlag, Table};
 
 pub trait StreamDecoder {
     /// Push received data to the decoder. The decoder stores it
@@ -12,4 +21,217 @@ pub trait StreamDecoder {
     fn received(&mut self, data: &[u8]);
 
     fn decode(&mut self) -> Result<Frame<'_>, DecodeError>;
+
+    /// Gives up on the frames held back waiting for more data, e.g. for the continuation frames
+    /// of a `{=chunked}` argument, and makes them available through
+    /// [`decode`](StreamDecoder::decode). Call this at the end of the stream, or when the device
+    /// was reset, so they are not lost.
+    fn finish(&mut self) {}
+}
+
+/// Maximum number of frames held back by [`Chunks`] while waiting for continuation frames.
+const MAX_PENDING_CHUNKED: usize = 16;
+
+/// Reassembles `{=chunked}` arguments from their continuation frames.
+#[derive(Default)]
+struct Chunks<'t> {
+    /// Frames waiting for continuation frames, oldest first
+    pending: VecDeque<Frame<'t>>,
+    /// Frames ready to be shown, oldest first
+    ready: VecDeque<Frame<'t>>,
+}
+
+impl<'t> Chunks<'t> {
+    /// Takes a decoded frame; the frames that are ready to be shown are returned by [`pop`].
+    ///
+    /// Frames with `{=chunked}` arguments are held back until all their data has arrived; they are
+    /// ready when their last continuation frame is passed in. Continuation frames themselves are
+    /// never shown.
+    ///
+    /// A held-back frame is given up on, and shown truncated, once a newer frame reuses the id of
+    /// one of its arguments (the ids wrap around) or once more than [`MAX_PENDING_CHUNKED`] frames
+    /// are held back, so lost continuation frames don't pile up.
+    ///
+    /// [`pop`]: Chunks::pop
+    fn push(&mut self, mut frame: Frame<'t>) {
+        let Some((id, chunk)) = frame.take_chunk() else {
+            if !frame.is_incomplete() {
+                self.ready.push_back(frame);
+                return;
+            }
+
+            let ids = frame.chunked_ids().collect::<Vec<_>>();
+            while let Some(index) = self
+                .pending
+                .iter()
+                .position(|pending| pending.chunked_ids().any(|id| ids.contains(&id)))
+            {
+                self.evict(index);
+            }
+            if self.pending.len() == MAX_PENDING_CHUNKED {
+                self.evict(0);
+            }
+            self.pending.push_back(frame);
+            return;
+        };
+
+        // a chunk without a pending frame was sent before the host started listening; drop it
+        let Some(index) = self
+            .pending
+            .iter_mut()
+            .position(|pending| pending.append_chunk(id, &chunk))
+        else {
+            return;
+        };
+        if !self.pending[index].is_incomplete() {
+            self.evict(index);
+        }
+    }
+
+    /// Returns the oldest frame that is ready to be shown, if any.
+    fn pop(&mut self) -> Option<Frame<'t>> {
+        self.ready.pop_front()
+    }
+
+    fn evict(&mut self, index: usize) {
+        self.ready.extend(self.pending.remove(index));
+    }
+
+    /// Makes the held-back frames ready, shown truncated.
+    fn finish(&mut self) {
+        self.ready.extend(self.pending.drain(..));
+    }
+}
+
+/// Reconstructs the values of `defmt::snapshot!` sent as deltas.
+#[derive(Default)]
+struct Snapshots {
+    /// Sequence number and encoding of the last snapshot of each call site, by the index of its
+    /// log statement
+    last: HashMap<u64, (u8, Vec<u8>)>,
+}
+
+impl Snapshots {
+    /// Replaces the snapshot carried by `frame`, if any, with its full value.
+    ///
+    /// A delta is applied to the encoding of the previous snapshot of the call site; if that one
+    /// was missed, e.g. because the host started listening late, the delta is left as is until
+    /// the next full snapshot arrives.
+    fn resolve<'t>(&mut self, table: &'t Table, frame: &mut Frame<'t>) {
+        let index = frame.index();
+        let Some(arg) = frame.snapshot_mut() else {
+            return;
+        };
+
+        match arg {
+            Arg::Snapshot { seq, bytes, value } => {
+                self.last.insert(index, (*seq, mem::take(bytes)));
+                *arg = mem::replace(&mut **value, Arg::Bool(false));
+            }
+            Arg::SnapshotDelta { seq, runs } => {
+                let Some((last_seq, bytes)) = self.last.remove(&index) else {
+                    return;
+                };
+                if last_seq.wrapping_add(1) != *seq {
+                    return;
+                }
+                let Some(bytes) = apply_delta(bytes, runs) else {
+                    return;
+                };
+                let Ok(mut value) = Decoder::new(table, &bytes).decode_format("{=?}") else {
+                    return;
+                };
+                self.last.insert(index, (*seq, bytes));
+                *arg = value.remove(0);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Writes the `(offset, bytes)` runs of a snapshot delta over `bytes`.
+fn apply_delta(mut bytes: Vec<u8>, runs: &[(usize, Vec<u8>)]) -> Option<Vec<u8>> {
+    for (offset, run) in runs {
+        bytes
+            .get_mut(*offset..offset + run.len())?
+            .copy_from_slice(run);
+    }
+    Some(bytes)
+}
+
+/// Maximum number of frames held back by a record; beyond it, they are shown before the record ends.
+pub(crate) const MAX_RECORD_FRAMES: usize = 64;
+
+/// Keeps the frames of logical records (`defmt::Record`) together.
+#[derive(Default)]
+struct Records<'t> {
+    /// The record each task is logging to, by task context
+    open: HashMap<String, u8>,
+    /// Frames of the records that haven't ended yet, by record id
+    frames: BTreeMap<u8, Vec<Frame<'t>>>,
+    /// Frames that are ready to be shown, oldest first
+    ready: VecDeque<Frame<'t>>,
+}
+
+impl<'t> Records<'t> {
+    /// Takes a decoded frame.
+    ///
+    /// Frames logged by a task that is logging to a record are held back until the record ends,
+    /// and then made ready together; or, once [`MAX_RECORD_FRAMES`] are held back, e.g. because
+    /// the marker frame ending the record was lost, before it ends. ERROR frames are never held
+    /// back: they are made ready right away, after the frames of their record held back so far.
+    /// Marker frames themselves are never made ready.
+    ///
+    /// Without a task context, the frames of different tasks can't be told apart, so no frames
+    /// are held back.
+    fn push(&mut self, frame: Frame<'t>) {
+        let Some(task) = frame.task_context_key() else {
+            if frame.record_marker().is_none() {
+                self.ready.push_back(frame);
+            }
+            return;
+        };
+        match frame.record_marker() {
+            Some((id, RecordFlag::Begin)) => {
+                // the previous record with the same id never ended, e.g. because the device reset
+                self.end(id);
+                self.frames.insert(id, vec![]);
+                self.open.insert(task, id);
+            }
+            // a record that began before the host started listening isn't held back
+            Some((id, RecordFlag::Continue)) if self.frames.contains_key(&id) => {
+                self.open.insert(task, id);
+            }
+            Some((_, RecordFlag::Continue)) => {}
+            Some((id, RecordFlag::End)) => self.end(id),
+            None => match self.open.get(&task).and_then(|id| self.frames.get_mut(id)) {
+                Some(frames) => {
+                    let error = frame.level() == Some(Level::Error);
+                    frames.push(frame);
+                    if error || frames.len() == MAX_RECORD_FRAMES {
+                        self.ready.extend(frames.drain(..));
+                    }
+                }
+                None => self.ready.push_back(frame),
+            },
+        }
+    }
+
+    /// Returns the oldest frame that is ready to be shown, if any.
+    fn pop(&mut self) -> Option<Frame<'t>> {
+        self.ready.pop_front()
+    }
+
+    /// Makes the frames of all records that haven't ended yet ready, oldest record first.
+    fn finish(&mut self) {
+        self.open.clear();
+        let frames = mem::take(&mut self.frames);
+        self.ready.extend(frames.into_values().flatten());
+    }
+
+    fn end(&mut self, id: u8) {
+        self.open.retain(|_, open| *open != id);
+        self.ready
+            .extend(self.frames.remove(&id).into_iter().flatten());
+    }
 }
@@ -1,9 +1,12 @@
-use super::StreamDecoder;
+use super::{Chunks, Records, Snapshots, StreamDecoder};
 use crate::{DecodeError, Frame, Table};
 
 pub struct Raw<'a> {
     table: &'a Table,
     data: Vec<u8>,
+    chunks: Chunks<'a>,
+    records: Records<'a>,
+    snapshots: Snapshots,
 }
 
 impl<'a> Raw<'a> {
@@ -11,6 +14,9 @@ impl<'a> Raw<'a> {
         Self {
             table,
             data: Vec::new(),
+            chunks: Chunks::default(),
+            records: Records::default(),
+            snapshots: Snapshots::default(),
         }
     }
 }
@@ -21,12 +27,25 @@ impl<'a> StreamDecoder for Raw<'a> {
     }
 
     fn decode(&mut self) -> Result<Frame<'_>, DecodeError> {
-        match self.table.decode(&self.data) {
-            Ok((frame, consumed)) => {
-                self.data.drain(0..consumed);
-                Ok(frame)
+        loop {
+            if let Some(frame) = self.records.pop() {
+                return Ok(frame);
             }
-            Err(e) => Err(e),
+            let (mut frame, consumed) = self.table.decode(&self.data)?;
+            self.data.drain(0..consumed);
+            self.snapshots.resolve(self.table, &mut frame);
+            self.chunks.push(frame);
+            while let Some(frame) = self.chunks.pop() {
+                self.records.push(frame);
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.chunks.finish();
+        while let Some(frame) = self.chunks.pop() {
+            self.records.push(frame);
         }
+        self.records.finish();
     }
 }
@@ -1,11 +1,11 @@
-use super::StreamDecoder;
+use super::{Chunks, Records, Snapshots, StreamDecoder};
 use crate::{DecodeError, Frame, Table};
 
 /// Decode a full message.
 ///
 /// `data` must be a full rzCOBS encoded message. Decoding partial
 /// messages is not possible. `data` must NOT include any `0x00` separator byte.
-fn rzcobs_decode(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+pub(crate) fn rzcobs_decode(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
     let mut res = vec![];
     let mut data = data.iter().rev().cloned();
     while let Some(x) = data.next() {
@@ -42,6 +42,9 @@ fn rzcobs_decode(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
 pub struct Rzcobs<'a> {
     table: &'a Table,
     raw: Vec<u8>,
+    chunks: Chunks<'a>,
+    records: Records<'a>,
+    snapshots: Snapshots,
 }
 
 impl<'a> Rzcobs<'a> {
@@ -49,6 +52,9 @@ impl<'a> Rzcobs<'a> {
         Self {
             table,
             raw: Vec::new(),
+            chunks: Chunks::default(),
+            records: Records::default(),
+            snapshots: Snapshots::default(),
         }
     }
 }
@@ -66,30 +72,46 @@ impl<'a> StreamDecoder for Rzcobs<'a> {
     }
 
     fn decode(&mut self) -> Result<Frame<'_>, DecodeError> {
-        // Find frame separator. If not found, we don't have enough data yet.
-        let zero = self
-            .raw
-            .iter()
-            .position(|&x| x == 0)
-            .ok_or(DecodeError::UnexpectedEof)?;
+        // continuation frames of `{=chunked}` arguments and record markers don't produce a frame
+        // of their own
+        loop {
+            if let Some(frame) = self.records.pop() {
+                return Ok(frame);
+            }
+            // Find frame separator. If not found, we don't have enough data yet.
+            let zero = self
+                .raw
+                .iter()
+                .position(|&x| x == 0)
+                .ok_or(DecodeError::UnexpectedEof)?;
 
-        let frame = rzcobs_decode(&self.raw[..zero]);
+            let frame = rzcobs_decode(&self.raw[..zero]);
 
-        // Even if it failed, pop the data off so we don't get stuck.
-        // Pop off the frame + 1 or more separator zero-bytes
-        if let Some(nonzero) = self.raw[zero..].iter().position(|&x| x != 0) {
-            self.raw.drain(0..zero + nonzero);
-        } else {
-            self.raw.clear();
-        }
+            // Even if it failed, pop the data off so we don't get stuck.
+            // Pop off the frame + 1 or more separator zero-bytes
+            if let Some(nonzero) = self.raw[zero..].iter().position(|&x| x != 0) {
+                self.raw.drain(0..zero + nonzero);
+            } else {
+                self.raw.clear();
+            }
+
+            assert!(self.raw.is_empty() || self.raw[0] != 0);
 
-        assert!(self.raw.is_empty() || self.raw[0] != 0);
+            let frame: Vec<u8> = frame?;
+            let mut frame = self.table.decode_complete(&frame)?;
+            self.snapshots.resolve(self.table, &mut frame);
+            self.chunks.push(frame);
+            while let Some(frame) = self.chunks.pop() {
+                self.records.push(frame);
+            }
+        }
+    }
 
-        let frame: Vec<u8> = frame?;
-        match self.table.decode(&frame) {
-            Ok((frame, _consumed)) => Ok(frame),
-            Err(DecodeError::UnexpectedEof) => Err(DecodeError::Malformed),
-            Err(DecodeError::Malformed) => Err(DecodeError::Malformed),
+    fn finish(&mut self) {
+        self.chunks.finish();
+        while let Some(frame) = self.chunks.pop() {
+            self.records.push(frame);
         }
+        self.records.finish();
     }
 }
@@ -0,0 +1,191 @@
+//! Register descriptions loaded from a CMSIS-SVD file
+//!
+//! Used to print values logged with the `{=u32:reg:PERIPHERAL.REGISTER}` display hint as the names
+//! of their bit-fields.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail};
+use roxmltree::{Document, Node};
+
+/// The registers of a device, keyed by `PERIPHERAL.REGISTER`
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Svd {
+    registers: BTreeMap<String, Vec<Field>>,
+}
+
+/// A bit-field of a register
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Field {
+    pub(crate) name: String,
+    pub(crate) offset: u32,
+    pub(crate) width: u32,
+}
+
+impl Svd {
+    /// Parses the contents of a CMSIS-SVD file.
+    ///
+    /// Only peripherals, registers and their fields are read. Peripherals which are `derivedFrom`
+    /// another one inherit its registers; register clusters and `dim` arrays are not supported.
+    pub fn parse(xml: &str) -> Result<Self, anyhow::Error> {
+        let doc = Document::parse(xml)?;
+        let peripherals = doc
+            .descendants()
+            .filter(|node| node.has_tag_name("peripheral"))
+            .collect::<Vec<_>>();
+
+        let mut registers = BTreeMap::new();
+        for peripheral in &peripherals {
+            let name = child_text(peripheral, "name")
+                .ok_or_else(|| anyhow!("peripheral without a name"))?;
+
+            // `derivedFrom` peripherals only list what differs, which usually excludes registers
+            let source = match peripheral.attribute("derivedFrom") {
+                Some(base) if child(peripheral, "registers").is_none() => peripherals
+                    .iter()
+                    .find(|p| child_text(p, "name") == Some(base))
+                    .ok_or_else(|| {
+                        anyhow!("`{name}` is derived from unknown peripheral `{base}`")
+                    })?,
+                _ => peripheral,
+            };
+
+            let Some(regs) = child(source, "registers") else {
+                continue;
+            };
+            for register in regs.children().filter(|n| n.has_tag_name("register")) {
+                let reg_name = child_text(&register, "name")
+                    .ok_or_else(|| anyhow!("register without a name in `{name}`"))?;
+                let fields = match child(&register, "fields") {
+                    Some(fields) => fields
+                        .children()
+                        .filter(|n| n.has_tag_name("field"))
+                        .map(|field| parse_field(&field))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => vec![],
+                };
+                registers.insert(format!("{name}.{reg_name}"), fields);
+            }
+        }
+
+        Ok(Self { registers })
+    }
+
+    pub(crate) fn fields(&self, peripheral: &str, register: &str) -> Option<&[Field]> {
+        self.registers
+            .get(&format!("{peripheral}.{register}"))
+            .map(|fields| &fields[..])
+    }
+}
+
+fn parse_field(field: &Node) -> Result<Field, anyhow::Error> {
+    let name = child_text(field, "name").ok_or_else(|| anyhow!("field without a name"))?;
+
+    // the bit range can be given in one of three ways
+    let (offset, width) = if let Some(offset) = child_text(field, "bitOffset") {
+        let width = child_text(field, "bitWidth").unwrap_or("1");
+        (parse_u32(offset)?, parse_u32(width)?)
+    } else if let (Some(lsb), Some(msb)) = (child_text(field, "lsb"), child_text(field, "msb")) {
+        let (lsb, msb) = (parse_u32(lsb)?, parse_u32(msb)?);
+        (lsb, msb.saturating_sub(lsb) + 1)
+    } else if let Some(range) = child_text(field, "bitRange") {
+        // `[msb:lsb]`
+        let (msb, lsb) = range
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split_once(':')
+            .ok_or_else(|| anyhow!("malformed bit range `{range}` of field `{name}`"))?;
+        let (lsb, msb) = (parse_u32(lsb)?, parse_u32(msb)?);
+        (lsb, msb.saturating_sub(lsb) + 1)
+    } else {
+        bail!("field `{name}` has no bit range")
+    };
+
+    if width == 0 || offset + width > 128 {
+        bail!("field `{name}` is out of range");
+    }
+
+    Ok(Field {
+        name: name.to_owned(),
+        offset,
+        width,
+    })
+}
+
+fn child<'a, 'input>(node: &Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+fn parse_u32(s: &str) -> Result<u32, anyhow::Error> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| anyhow!("invalid number `{s}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device>
+  <name>TEST</name>
+  <peripherals>
+    <peripheral>
+      <name>USART1</name>
+      <registers>
+        <register>
+          <name>SR</name>
+          <fields>
+            <field><name>TC</name><bitOffset>6</bitOffset><bitWidth>1</bitWidth></field>
+            <field><name>TXE</name><bitRange>[7:7]</bitRange></field>
+            <field><name>CTS</name><lsb>9</lsb><msb>9</msb></field>
+          </fields>
+        </register>
+        <register>
+          <name>BRR</name>
+          <fields>
+            <field><name>DIV_Fraction</name><bitOffset>0</bitOffset><bitWidth>4</bitWidth></field>
+            <field><name>DIV_Mantissa</name><bitOffset>0x4</bitOffset><bitWidth>12</bitWidth></field>
+          </fields>
+        </register>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="USART1">
+      <name>USART2</name>
+    </peripheral>
+  </peripherals>
+</device>"#;
+
+    #[test]
+    fn fields() {
+        let svd = Svd::parse(SVD).unwrap();
+        let field = |name: &str, offset, width| Field {
+            name: name.to_owned(),
+            offset,
+            width,
+        };
+
+        assert_eq!(
+            svd.fields("USART1", "SR").unwrap(),
+            [field("TC", 6, 1), field("TXE", 7, 1), field("CTS", 9, 1)]
+        );
+        assert_eq!(
+            svd.fields("USART1", "BRR").unwrap(),
+            [field("DIV_Fraction", 0, 4), field("DIV_Mantissa", 4, 12)]
+        );
+    }
+
+    #[test]
+    fn derived_peripheral() {
+        let svd = Svd::parse(SVD).unwrap();
+        assert_eq!(svd.fields("USART2", "SR"), svd.fields("USART1", "SR"));
+        assert_eq!(svd.fields("USART3", "SR"), None);
+    }
+}
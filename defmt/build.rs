@@ -1,4 +1,4 @@
-use std::{env, error::Error, fs, path::PathBuf};
+use std::{env, error::Error, fs, path::PathBuf, process::Command};
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Put the linker script somewhere the linker can find it
@@ -21,5 +21,37 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
         _ => {}
     }
+
+    // `defmt::trace_fn` logs at TRACE level, which is off by default; turn it on for its tests
+    // without affecting the filter given by the user
+    if env::var_os("CARGO_FEATURE_UNSTABLE_TEST").is_some() {
+        // once any `rerun-if` is printed, cargo no longer reruns the script on every change
+        println!("cargo:rerun-if-changed=build.rs");
+        println!("cargo:rerun-if-changed=defmt.x.in");
+        println!("cargo:rerun-if-env-changed=DEFMT_LOG");
+        let defmt_log = match env::var("DEFMT_LOG") {
+            Ok(defmt_log) => format!("{defmt_log},encode::trace_fn=trace"),
+            Err(_) => "encode::trace_fn=trace".to_string(),
+        };
+        println!("cargo:rustc-env=DEFMT_LOG={defmt_log}");
+    }
+
+    // `core::net` is stable since rust 1.77.0; older toolchains need the `ip_in_core` feature
+    println!("cargo:rustc-check-cfg=cfg(core_net)");
+    if rustc_minor_version().is_some_and(|minor| minor >= 77) {
+        println!("cargo:rustc-cfg=core_net");
+    }
     Ok(())
 }
+
+/// Returns the minor version of the compiler, e.g. `77` for `rustc 1.77.2 (25ef9e3d8 2024-04-09)`.
+fn rustc_minor_version() -> Option<u32> {
+    let rustc = env::var_os("RUSTC")?;
+    let output = Command::new(rustc).arg("--version").output().ok()?;
+    let version = String::from_utf8(output.stdout).ok()?;
+    let mut parts = version.split_whitespace().nth(1)?.split('.');
+    match parts.next() {
+        Some("1") => parts.next()?.parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,142 @@
+/// Flag of the first fragment of a frame
+const FIRST: u8 = 0x80;
+/// Flag of the last fragment of a frame
+const LAST: u8 = 0x40;
+const SEQUENCE_MASK: u8 = 0x3f;
+/// Flags and sequence number, then the length of the payload
+const HEADER_LEN: usize = 2;
+
+/// Splits encoded log frames into packets of at most `MTU` bytes, for transports with tiny
+/// packets such as BLE notifications, CAN or LoRa.
+///
+/// Each packet starts with a two-byte fragment header, which lets the host reassemble the frames
+/// and drop those that lost a fragment on the way:
+///
+/// - the flags and sequence number: bit 7 marks the first and bit 6 the last fragment of a frame,
+///   bits 0 to 5 are the sequence number of the packet, counting up and wrapping around
+/// - the length of the payload that follows
+///
+/// As the sequence number wraps around after 64 packets, the host doesn't notice if a frame loses
+/// exactly 64 consecutive packets (or a multiple of it).
+///
+/// Only a single packet is buffered, so `Logger` impls don't need to buffer whole frames. The
+/// data passed through is the output of an [`Encoder`](crate::Encoder):
+///
+/// ```
+/// # let mut encoder = defmt::Encoder::new();
+/// # let send = |packet: &[u8]| {};
+/// let mut fragmenter = defmt::Fragmenter::<20>::new();
+///
+/// // in `acquire()`
+/// fragmenter.start_frame();
+/// encoder.start_frame(|bytes| fragmenter.write(bytes, send));
+/// // in `write()`
+/// encoder.write(&[1, 2, 3], |bytes| fragmenter.write(bytes, send));
+/// // in `release()`
+/// encoder.end_frame(|bytes| fragmenter.write(bytes, send));
+/// fragmenter.end_frame(send);
+/// ```
+///
+/// The fragments of a frame must be sent without fragments of other frames in between; use a
+/// single `Fragmenter` per transport.
+pub struct Fragmenter<const MTU: usize> {
+    packet: [u8; MTU],
+    len: usize,
+    sequence: u8,
+}
+
+impl<const MTU: usize> Default for Fragmenter<MTU> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const MTU: usize> Fragmenter<MTU> {
+    /// Create a new `Fragmenter`.
+    ///
+    /// Panics if `MTU` leaves no room for a payload, or exceeds the 255 bytes of payload a
+    /// fragment header can describe.
+    pub const fn new() -> Self {
+        assert!(MTU > HEADER_LEN && MTU - HEADER_LEN <= u8::MAX as usize);
+        Self {
+            packet: [0; MTU],
+            len: HEADER_LEN,
+            sequence: 0,
+        }
+    }
+
+    /// Start the fragments of a log frame.
+    ///
+    /// `Logger` impls will typically call this from `acquire()`, before `Encoder::start_frame`.
+    pub fn start_frame(&mut self) {
+        self.packet[0] = FIRST;
+        self.len = HEADER_LEN;
+    }
+
+    /// Add encoded data of a log frame.
+    ///
+    /// The `send` closure will be called with each packet that is full. It may be called zero,
+    /// one, or multiple times.
+    pub fn write(&mut self, mut data: &[u8], mut send: impl FnMut(&[u8])) {
+        while !data.is_empty() {
+            // a full packet is only sent once more data follows, so that the last packet of the
+            // frame can be flagged as such
+            if self.len == MTU {
+                self.send(0, &mut send);
+            }
+            let n = (MTU - self.len).min(data.len());
+            self.packet[self.len..self.len + n].copy_from_slice(&data[..n]);
+            self.len += n;
+            data = &data[n..];
+        }
+    }
+
+    /// Finish the fragments of a log frame.
+    ///
+    /// `Logger` impls will typically call this from `release()`, after `Encoder::end_frame`.
+    ///
+    /// The `send` closure will be called once, with the last packet of the frame.
+    pub fn end_frame(&mut self, mut send: impl FnMut(&[u8])) {
+        self.send(LAST, &mut send);
+    }
+
+    fn send(&mut self, flags: u8, send: &mut impl FnMut(&[u8])) {
+        self.packet[0] |= flags | self.sequence;
+        self.packet[1] = (self.len - HEADER_LEN) as u8;
+        send(&self.packet[..self.len]);
+
+        self.sequence = (self.sequence + 1) & SEQUENCE_MASK;
+        self.packet[0] = 0;
+        self.len = HEADER_LEN;
+    }
+}
+
+#[cfg(feature = "unstable-test")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fragments() {
+        let mut fragmenter = Fragmenter::<5>::new();
+        let mut packets = vec![];
+        let mut send = |packet: &[u8]| packets.push(packet.to_vec());
+
+        fragmenter.start_frame();
+        fragmenter.write(&[1, 2], &mut send);
+        fragmenter.write(&[3, 4, 5, 6], &mut send);
+        fragmenter.end_frame(&mut send);
+        fragmenter.start_frame();
+        fragmenter.write(&[7], &mut send);
+        fragmenter.end_frame(&mut send);
+
+        assert_eq!(
+            packets,
+            [
+                vec![0x80, 3, 1, 2, 3],
+                vec![0x41, 3, 4, 5, 6],
+                vec![0xc2, 1, 7],
+            ]
+        );
+    }
+}
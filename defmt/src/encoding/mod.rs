@@ -5,6 +5,10 @@ compile_error!("Multiple `encoding-*` features are enabled. You may only enable
 #[cfg_attr(not(feature = "encoding-raw"), path = "rzcobs.rs")]
 mod inner;
 
+mod fragment;
+
+pub use fragment::Fragmenter;
+
 // This wrapper struct is to avoid copypasting the public docs in all the impls.
 
 /// Encode raw defmt frames for sending over the wire.
@@ -19,14 +23,14 @@ mod inner;
 /// Encodings may perform two functions:
 ///
 /// - Framing: Adds extra data to allow the encoder to know when each frame starts
-/// and ends in the stream. Unframed log frames already contain enough information for
-/// the decoder to know when they end, so framing is optional. However, without framing
-/// the decoder must receive all bytes intact or it may "lose sync". With framing, it can
-/// recover from missing/corrupted data, and can start decoding from the "middle" of an
-/// already-running stream.
+///   and ends in the stream. Unframed log frames already contain enough information for
+///   the decoder to know when they end, so framing is optional. However, without framing
+///   the decoder must receive all bytes intact or it may "lose sync". With framing, it can
+///   recover from missing/corrupted data, and can start decoding from the "middle" of an
+///   already-running stream.
 /// - Compression: The frame data has rather low entropy (for example, it contains many
-/// zero bytes due to encoding all integers in fixed with, and will likely contain many
-/// repetitions). Compression can decrease the on-the-wire required bandwidth.
+///   zero bytes due to encoding all integers in fixed with, and will likely contain many
+///   repetitions). Compression can decrease the on-the-wire required bandwidth.
 ///
 /// defmt provides the `Encoder` separately instead of feeding already-encoded bytes
 /// to the `Logger` because `Logger` implementations may decide to allow
@@ -37,6 +41,12 @@ pub struct Encoder {
     inner: inner::Encoder,
 }
 
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Encoder {
     /// Create a new `Encoder`.
     pub const fn new() -> Self {
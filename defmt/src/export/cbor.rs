@@ -0,0 +1,502 @@
+//! A streaming CBOR (RFC 8949) serializer for the `Serde2Format` adapter
+//!
+//! Values are written straight into the log frame. CBOR items are self-delimiting, so no length
+//! prefix is needed; containers and strings of unknown length use the indefinite-length encoding.
+//!
+//! As the frame can't be rewound, a `Serialize` impl failing halfway still results in one
+//! well-formed item: a value that wasn't written is replaced with `undefined`, and the containers
+//! left open are completed with `undefined` elements and closed.
+
+use core::{
+    fmt::{self, Write as _},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::ser::{self, Serialize};
+
+const UINT: u8 = 0;
+const NINT: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+const FALSE: u8 = 20;
+const TRUE: u8 = 21;
+const NULL: u8 = 22;
+const UNDEFINED: u8 = 23;
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+/// Number of writes so far, to tell whether a failing `Serialize` impl wrote anything
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Implementation detail
+pub fn cbor<T: Serialize + ?Sized>(val: &T) {
+    // errors can only be raised by `Serialize` impls; the item sent is complete regardless
+    item(val).ok();
+}
+
+fn write(bytes: &[u8]) {
+    // the logger is acquired, so no other context writes in between
+    WRITES.store(
+        WRITES.load(Ordering::Relaxed).wrapping_add(1),
+        Ordering::Relaxed,
+    );
+    super::write(bytes);
+}
+
+/// Serializes `value` as one complete item, even if its `Serialize` impl fails.
+fn item<T: Serialize + ?Sized>(value: &T) -> Result<(), Error> {
+    let writes = WRITES.load(Ordering::Relaxed);
+    let result = value.serialize(Serializer);
+    // containers it opened were completed when their `Compound` was dropped
+    if result.is_err() && WRITES.load(Ordering::Relaxed) == writes {
+        write(&[SIMPLE << 5 | UNDEFINED]);
+    }
+    result
+}
+
+/// Writes the initial byte and argument of an item.
+fn head(major: u8, n: u64) {
+    let major = major << 5;
+    match n {
+        0..=23 => write(&[major | n as u8]),
+        24..=0xff => write(&[major | 24, n as u8]),
+        0x100..=0xffff => {
+            write(&[major | 25]);
+            write(&(n as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            write(&[major | 26]);
+            write(&(n as u32).to_be_bytes());
+        }
+        _ => {
+            write(&[major | 27]);
+            write(&n.to_be_bytes());
+        }
+    }
+}
+
+fn container(major: u8, len: Option<usize>) {
+    match len {
+        Some(len) => head(major, len as u64),
+        None => write(&[major << 5 | INDEFINITE]),
+    }
+}
+
+fn text(s: &str) {
+    head(TEXT, s.len() as u64);
+    write(s.as_bytes());
+}
+
+/// Error raised by a `Serialize` impl
+#[derive(Debug)]
+pub struct Error;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to serialize value")
+    }
+}
+
+impl ser::StdError for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Error
+    }
+}
+
+struct Serializer;
+
+/// State of an array or map being serialized
+struct Compound {
+    /// Number of items (elements, or keys and values) announced; `None` for indefinite length
+    len: Option<usize>,
+    /// Number of items serialized so far
+    items: usize,
+    map: bool,
+    finished: bool,
+}
+
+impl Compound {
+    fn new(map: bool, len: Option<usize>) -> Self {
+        container(if map { MAP } else { ARRAY }, len);
+        Compound {
+            len: len.map(|len| if map { 2 * len } else { len }),
+            items: 0,
+            map,
+            finished: false,
+        }
+    }
+
+    fn item<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items += 1;
+        item(value)
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        self.items += 1;
+        text(key);
+        self.item(value)
+    }
+
+    /// Writes `undefined` in place of the items that are missing, e.g. because a `Serialize` impl
+    /// failed, and the closing break if needed.
+    fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let missing = match self.len {
+            Some(len) => len.saturating_sub(self.items),
+            // a key without a value
+            None => usize::from(self.map) * (self.items % 2),
+        };
+        for _ in 0..missing {
+            write(&[SIMPLE << 5 | UNDEFINED]);
+        }
+        if self.len.is_none() {
+            write(&[BREAK]);
+        }
+    }
+}
+
+impl Drop for Compound {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Writes the chunks of an indefinite-length text string.
+struct TextChunks;
+
+impl fmt::Write for TextChunks {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if !s.is_empty() {
+            text(s);
+        }
+        Ok(())
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound;
+    type SerializeTuple = Compound;
+    type SerializeTupleStruct = Compound;
+    type SerializeTupleVariant = Compound;
+    type SerializeMap = Compound;
+    type SerializeStruct = Compound;
+    type SerializeStructVariant = Compound;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        write(&[SIMPLE << 5 | if v { TRUE } else { FALSE }]);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        match v {
+            0.. => head(UINT, v as u64),
+            _ => head(NINT, !v as u64),
+        }
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        match (v, i64::try_from(v)) {
+            (_, Ok(v)) => self.serialize_i64(v),
+            (0.., _) => self.serialize_u128(v as u128),
+            // negative bignum
+            _ => {
+                head(TAG, 3);
+                self.serialize_bytes(&(!v as u128).to_be_bytes())
+            }
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        head(UINT, v);
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            // positive bignum
+            Err(_) => {
+                head(TAG, 2);
+                self.serialize_bytes(&v.to_be_bytes())
+            }
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        write(&[SIMPLE << 5 | 26]);
+        write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        write(&[SIMPLE << 5 | 27]);
+        write(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        text(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        head(BYTES, v.len() as u64);
+        write(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        write(&[SIMPLE << 5 | NULL]);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    // enum variants with data are externally tagged: `{ variant: data }`
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        head(MAP, 1);
+        text(variant);
+        item(value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound, Error> {
+        Ok(Compound::new(false, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Compound, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound, Error> {
+        head(MAP, 1);
+        text(variant);
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound, Error> {
+        Ok(Compound::new(true, len))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound, Error> {
+        head(MAP, 1);
+        text(variant);
+        self.serialize_map(Some(len))
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<(), Error> {
+        write(&[TEXT << 5 | INDEFINITE]);
+        core::write!(TextChunks, "{value}").ok();
+        write(&[BREAK]);
+        Ok(())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for Compound {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Compound {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.item(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.item(value)
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Compound {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.finish();
+        Ok(())
+    }
+}
@@ -11,6 +11,20 @@ macro_rules! write_to_le_bytes {
 
 write_to_le_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
 
+macro_rules! write_slice {
+    ($($name:ident: $s:ident),*) => {
+        $(/// Implementation detail
+        pub fn $name(s: &[$s]) {
+            usize(&s.len());
+            for x in s {
+                $s(x);
+            }
+        })*
+    };
+}
+
+write_slice!(u16_slice: u16, u32_slice: u32, u64_slice: u64);
+
 /// Implementation detail
 pub fn usize(b: &usize) {
     write(&(*b as u32).to_le_bytes())
@@ -1,11 +1,19 @@
+#[cfg(feature = "serde")]
+mod cbor;
 mod integers;
+#[cfg(feature = "snapshot")]
+mod snapshot;
 mod traits;
 
 use core::fmt::Write as _;
 
-use crate::{Format, Formatter, Str};
+use crate::{self as defmt, Format, Formatter, Str};
 
+#[cfg(feature = "serde")]
+pub use self::cbor::cbor;
 pub use self::integers::*;
+#[cfg(feature = "snapshot")]
+pub use self::snapshot::{snapshot, Snapshot};
 pub use bitflags::bitflags;
 
 pub trait UnsignedInt {}
@@ -17,8 +25,8 @@ impl UnsignedInt for u128 {}
 
 #[cfg(feature = "unstable-test")]
 thread_local! {
-    static I: core::sync::atomic::AtomicU16 = core::sync::atomic::AtomicU16::new(0);
-    static BYTES: core::cell::RefCell<Vec<u8>> = core::cell::RefCell::new(Vec::new());
+    static I: core::sync::atomic::AtomicU16 = const { core::sync::atomic::AtomicU16::new(0) };
+    static BYTES: core::cell::RefCell<Vec<u8>> = const { core::cell::RefCell::new(Vec::new()) };
 }
 
 /// For testing purposes
@@ -52,7 +60,9 @@ pub unsafe fn acquire() {
     extern "Rust" {
         fn _defmt_acquire();
     }
-    _defmt_acquire()
+    _defmt_acquire();
+    #[cfg(feature = "snapshot")]
+    snapshot::enter();
 }
 
 /// Only to be used by the defmt macros
@@ -68,6 +78,8 @@ pub unsafe fn release() {
     extern "Rust" {
         fn _defmt_release();
     }
+    #[cfg(feature = "snapshot")]
+    snapshot::leave();
     _defmt_release()
 }
 
@@ -79,6 +91,10 @@ pub fn write(bytes: &[u8]) {
 #[cfg(not(feature = "unstable-test"))]
 #[inline(always)]
 pub fn write(bytes: &[u8]) {
+    #[cfg(feature = "snapshot")]
+    if snapshot::captured(bytes) {
+        return;
+    }
     extern "Rust" {
         fn _defmt_write(bytes: &[u8]);
     }
@@ -98,6 +114,43 @@ pub fn timestamp(fmt: crate::Formatter<'_>) {
     unsafe { _defmt_timestamp(fmt) }
 }
 
+/// For testing purposes
+#[cfg(all(feature = "task-context", feature = "unstable-test"))]
+pub fn task_context(_fmt: crate::Formatter<'_>) {}
+
+#[cfg(all(feature = "task-context", not(feature = "unstable-test")))]
+#[inline(always)]
+pub fn task_context(fmt: crate::Formatter<'_>) {
+    extern "Rust" {
+        fn _defmt_task_context(_: crate::Formatter<'_>);
+    }
+    unsafe { _defmt_task_context(fmt) }
+}
+
+static TIMESTAMP_SOURCE: core::sync::atomic::AtomicU8 = core::sync::atomic::AtomicU8::new(0);
+
+/// Implementation detail
+pub fn set_timestamp_source(source: u8) {
+    TIMESTAMP_SOURCE.store(source, core::sync::atomic::Ordering::Relaxed)
+}
+
+/// Implementation detail
+pub fn timestamp_source() -> u8 {
+    TIMESTAMP_SOURCE.load(core::sync::atomic::Ordering::Relaxed)
+}
+
+/// Hash of the `.defmt` table, written into the ELF file after linking (`defmt-print stamp`)
+///
+/// The placeholder isn't zero, so that the static is stored in the ELF file rather than in `.bss`.
+#[export_name = "_defmt_table_hash"]
+static TABLE_HASH: u64 = u64::MAX;
+
+/// Implementation detail
+pub fn table_hash() -> u64 {
+    // volatile, so that the placeholder is not copied into the code reading it
+    unsafe { core::ptr::read_volatile(&TABLE_HASH) }
+}
+
 /// Returns the interned string at `address`.
 pub fn make_istr(address: u16) -> Str {
     Str { address }
@@ -173,6 +226,22 @@ pub fn slice(s: &[u8]) {
     write(s);
 }
 
+/// Implementation detail
+///
+/// Encoded like `fmt_slice`. The iterator is cloned twice: once to count the elements, once to
+/// format them.
+pub fn fmt_iter<I>(iter: &I)
+where
+    I: IntoIterator + Clone,
+    I::Item: Format,
+{
+    usize(&iter.clone().into_iter().count());
+    istr(&I::Item::_format_tag());
+    for value in iter.clone() {
+        value._format_data();
+    }
+}
+
 // NOTE: This is passed `&[u8; N]` – it's just coerced to a slice.
 pub fn u8_array(a: &[u8]) {
     write(a);
@@ -212,6 +281,78 @@ pub fn display(val: &dyn core::fmt::Display) {
 pub fn header(s: &Str) {
     istr(s);
     timestamp(make_formatter());
+    #[cfg(feature = "task-context")]
+    task_context(make_formatter());
+}
+
+/// Maximum number of bytes of a `{=chunked}` argument sent per continuation frame.
+///
+/// Every continuation frame carries its own length, so the host doesn't depend on this value. It
+/// bounds how long the logger, and with it e.g. the critical section of `defmt-rtt`, is held per
+/// frame, so it is kept small and fixed instead of depending on the logger's buffer size.
+const CHUNK_SIZE: usize = 256;
+
+static NEXT_CHUNKED_ID: core::sync::atomic::AtomicU8 = core::sync::atomic::AtomicU8::new(0);
+
+/// Returns the value of `counter` and increments it.
+///
+/// The logger may be acquired again by a context that interrupts the one logging (see `Logger`),
+/// so this is a `fetch_add`. Targets without atomic read-modify-write, e.g. ARMv6-M, fall back to a
+/// load and a store; there, ids are only unique if the logger excludes nested contexts, like the
+/// critical section of `defmt-rtt` does.
+fn next_id(counter: &core::sync::atomic::AtomicU8) -> u8 {
+    #[cfg(target_has_atomic = "8")]
+    {
+        counter.fetch_add(1, core::sync::atomic::Ordering::Relaxed)
+    }
+    #[cfg(not(target_has_atomic = "8"))]
+    {
+        let id = counter.load(core::sync::atomic::Ordering::Relaxed);
+        counter.store(id.wrapping_add(1), core::sync::atomic::Ordering::Relaxed);
+        id
+    }
+}
+
+/// Implementation detail
+///
+/// Writes the id and length of a `{=chunked}` argument. The data follows in continuation frames,
+/// sent by `chunked_data` once the frame has been released.
+pub fn chunked_header(bytes: &[u8]) -> u8 {
+    let id = next_id(&NEXT_CHUNKED_ID);
+    u8(&id);
+    usize(&bytes.len());
+    id
+}
+
+/// Implementation detail
+pub fn chunked_data(id: u8, bytes: &[u8]) {
+    for chunk in bytes.chunks(CHUNK_SIZE) {
+        // safety: released right after writing the chunk
+        unsafe { acquire() };
+        header(&defmt_macros::internp!("{=__internal_Chunk}"));
+        u8(&id);
+        slice(chunk);
+        // safety: acquire() was called above
+        unsafe { release() }
+    }
+}
+
+static NEXT_RECORD_ID: core::sync::atomic::AtomicU8 = core::sync::atomic::AtomicU8::new(0);
+
+/// Implementation detail
+///
+/// Sends a marker frame with the `flag` of a logical record: 0 begins, 1 continues and 2 ends it.
+/// Without an `id`, a new one is taken. Returns the id of the record.
+pub fn record(id: Option<u8>, flag: u8) -> u8 {
+    // safety: released a few lines further down
+    unsafe { acquire() };
+    header(&defmt_macros::internp!("{=__internal_Record}"));
+    let id = id.unwrap_or_else(|| next_id(&NEXT_RECORD_ID));
+    u8(&id);
+    u8(&flag);
+    // safety: acquire() was called a few lines above
+    unsafe { release() }
+    id
 }
 
 struct FmtWrite;
@@ -0,0 +1,297 @@
+//! Delta encoding of the values logged with `defmt::snapshot!`
+//!
+//! A snapshot is sent as a sequence number and either its `{=?}` encoding in full, or the runs of
+//! bytes of that encoding that changed since the previous snapshot of the call site. Printers apply
+//! the runs to the previous value of the log statement; the sequence number tells them when a
+//! snapshot was lost, and the full snapshot sent every `KEYFRAME_INTERVAL` snapshots lets them
+//! recover.
+//!
+//! The bytes written while a snapshot is formatted are captured by `write`, which is why this is
+//! behind the `snapshot` feature.
+//!
+//! The logger may be acquired again by a context that interrupts the one logging, e.g. with
+//! `defmt-itm`, so all state is kept in atomics. Only one context captures a snapshot at a time:
+//! a nested context writes through to the logger, and sends its own snapshots in full, without
+//! touching the state of their call site.
+
+#[cfg(not(feature = "unstable-test"))]
+use core::sync::atomic::AtomicUsize;
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+use super::{fmt, u8, write};
+use crate::Format;
+
+/// Longest encoding kept to compare the next snapshot with; longer values are always sent in full.
+const CAPACITY: usize = 64;
+/// Every `KEYFRAME_INTERVAL`th snapshot of a call site is sent in full.
+const KEYFRAME_INTERVAL: u8 = 16;
+
+const FULL: u8 = 0;
+const DELTA: u8 = 1;
+
+/// Bytes taken by the offset and length of a run of changed bytes
+const RUN_HEADER: usize = 2;
+
+/// Value of `Snapshot::len` when the last snapshot wasn't kept
+const NOT_KEPT: u8 = u8::MAX;
+
+// offsets and lengths of runs are sent as `u8`, and `NOT_KEPT` is not a length
+const _: () = assert!(CAPACITY < NOT_KEPT as usize);
+
+/// Implementation detail
+///
+/// The last snapshot sent from a call site of `defmt::snapshot!`.
+pub struct Snapshot {
+    /// Whether a context is logging a snapshot of this call site
+    busy: AtomicBool,
+    seq: AtomicU8,
+    /// Length of the encoding of the last snapshot, or `NOT_KEPT`
+    len: AtomicU8,
+    bytes: [AtomicU8; CAPACITY],
+}
+
+impl Snapshot {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self {
+            busy: AtomicBool::new(false),
+            seq: AtomicU8::new(0),
+            len: AtomicU8::new(NOT_KEPT),
+            bytes: [const { AtomicU8::new(0) }; CAPACITY],
+        }
+    }
+}
+
+/// Sets `flag`; returns whether it was set already.
+///
+/// Without atomic read-modify-write, e.g. on ARMv6-M, a load and a store do: a context that
+/// interrupts this one between them also clears the flag again before this one resumes.
+fn test_and_set(flag: &AtomicBool) -> bool {
+    #[cfg(target_has_atomic = "8")]
+    {
+        flag.swap(true, Ordering::Acquire)
+    }
+    #[cfg(not(target_has_atomic = "8"))]
+    {
+        let set = flag.load(Ordering::Acquire);
+        flag.store(true, Ordering::Relaxed);
+        set
+    }
+}
+
+/// Implementation detail
+pub fn snapshot<T: Format + ?Sized>(snapshot: &Snapshot, value: &T) {
+    if test_and_set(&snapshot.busy) {
+        // another context is logging this call site; the sequence number is not taken, so that
+        // printers don't apply the next delta to this value
+        u8(&snapshot.seq.load(Ordering::Relaxed));
+        u8(&FULL);
+        fmt(value);
+        return;
+    }
+
+    let seq = snapshot.seq.load(Ordering::Relaxed);
+    if capturing() {
+        // an interrupted context is capturing another snapshot; sent in full as above
+        u8(&seq);
+        u8(&FULL);
+        fmt(value);
+        snapshot.busy.store(false, Ordering::Release);
+        return;
+    }
+    snapshot.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+    u8(&seq);
+
+    let mut bytes = [0; CAPACITY];
+    // a value longer than `CAPACITY` is sent in full while it is formatted
+    let len = capture(&mut bytes, || fmt(value));
+    let mut previous = [0; CAPACITY];
+    let previous = match snapshot.len.load(Ordering::Relaxed) {
+        NOT_KEPT => None,
+        _ if seq.is_multiple_of(KEYFRAME_INTERVAL) => None,
+        len => {
+            let previous = &mut previous[..usize::from(len)];
+            for (byte, kept) in previous.iter_mut().zip(&snapshot.bytes) {
+                *byte = kept.load(Ordering::Relaxed);
+            }
+            Some(&*previous)
+        }
+    };
+    match (len, previous) {
+        (Some(len), Some(previous))
+            if previous.len() == len && delta_len(previous, &bytes[..len]) < len =>
+        {
+            u8(&DELTA);
+            write_delta(previous, &bytes[..len]);
+        }
+        (Some(len), _) => {
+            u8(&FULL);
+            write(&bytes[..len]);
+        }
+        (None, _) => {}
+    }
+
+    match len {
+        Some(len) => {
+            for (kept, byte) in snapshot.bytes.iter().zip(&bytes[..len]) {
+                kept.store(*byte, Ordering::Relaxed);
+            }
+            snapshot.len.store(len as u8, Ordering::Relaxed);
+        }
+        None => snapshot.len.store(NOT_KEPT, Ordering::Relaxed),
+    }
+    snapshot.busy.store(false, Ordering::Release);
+}
+
+/// Returns the ranges of `current` that differ from `previous`, merging ranges separated by no
+/// more unchanged bytes than the header of another run would take.
+fn runs<'a>(previous: &'a [u8], current: &'a [u8]) -> impl Iterator<Item = Range<usize>> + 'a {
+    let differs = move |i: &usize| previous[*i] != current[*i];
+    let mut start = 0;
+    core::iter::from_fn(move || {
+        let first = (start..current.len()).find(differs)?;
+        let mut end = first + 1;
+        while let Some(next) = (end..current.len()).find(differs) {
+            if next - end > RUN_HEADER {
+                break;
+            }
+            end = next + 1;
+        }
+        start = end;
+        Some(first..end)
+    })
+}
+
+fn delta_len(previous: &[u8], current: &[u8]) -> usize {
+    1 + runs(previous, current)
+        .map(|run| RUN_HEADER + run.len())
+        .sum::<usize>()
+}
+
+/// Writes the number of runs, then the offset of each run from the end of the previous one, its
+/// length and its bytes.
+fn write_delta(previous: &[u8], current: &[u8]) {
+    u8(&(runs(previous, current).count() as u8));
+    let mut end = 0;
+    for run in runs(previous, current) {
+        u8(&((run.start - end) as u8));
+        u8(&(run.len() as u8));
+        write(&current[run.clone()]);
+        end = run.end;
+    }
+}
+
+/// Logger depth of the context capturing a snapshot, or 0 if there is none
+#[cfg(not(feature = "unstable-test"))]
+static CAPTURING: AtomicU8 = AtomicU8::new(0);
+/// Number of contexts that acquired the logger, each interrupting the previous one
+#[cfg(not(feature = "unstable-test"))]
+static DEPTH: AtomicU8 = AtomicU8::new(0);
+/// Whether the snapshot being captured didn't fit and is passed on to the logger instead
+#[cfg(not(feature = "unstable-test"))]
+static OVERFLOWED: AtomicBool = AtomicBool::new(false);
+#[cfg(not(feature = "unstable-test"))]
+static CAPTURED_LEN: AtomicUsize = AtomicUsize::new(0);
+#[cfg(not(feature = "unstable-test"))]
+static CAPTURED: [AtomicU8; CAPACITY] = [const { AtomicU8::new(0) }; CAPACITY];
+
+/// Called when a context acquires the logger.
+///
+/// A context that interrupts another one finishes before that one resumes, so a load and a store
+/// suffice.
+#[cfg(not(feature = "unstable-test"))]
+#[inline(always)]
+pub(super) fn enter() {
+    DEPTH.store(DEPTH.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
+
+/// Called when a context releases the logger.
+#[cfg(not(feature = "unstable-test"))]
+#[inline(always)]
+pub(super) fn leave() {
+    DEPTH.store(DEPTH.load(Ordering::Relaxed) - 1, Ordering::Relaxed);
+}
+
+/// Whether a context is capturing a snapshot
+#[cfg(not(feature = "unstable-test"))]
+fn capturing() -> bool {
+    CAPTURING.load(Ordering::Relaxed) != 0
+}
+
+/// Keeps `bytes` in the snapshot being captured by the current context, if any; returns `false`
+/// if they are to be passed on to the logger.
+///
+/// Once the snapshot doesn't fit, it is sent in full: the bytes kept so far are written, preceded
+/// by `FULL`, and the rest of the snapshot is passed on.
+#[cfg(not(feature = "unstable-test"))]
+#[inline(always)]
+pub(super) fn captured(bytes: &[u8]) -> bool {
+    let depth = CAPTURING.load(Ordering::Relaxed);
+    // nested contexts write through
+    if depth == 0 || depth != DEPTH.load(Ordering::Relaxed) || OVERFLOWED.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    let len = CAPTURED_LEN.load(Ordering::Relaxed);
+    if let Some(slots) = CAPTURED.get(len..len + bytes.len()) {
+        for (slot, byte) in slots.iter().zip(bytes) {
+            slot.store(*byte, Ordering::Relaxed);
+        }
+        CAPTURED_LEN.store(len + bytes.len(), Ordering::Relaxed);
+        return true;
+    }
+
+    OVERFLOWED.store(true, Ordering::Relaxed);
+    let mut kept = [0; CAPACITY];
+    for (byte, slot) in kept.iter_mut().zip(&CAPTURED[..len]) {
+        *byte = slot.load(Ordering::Relaxed);
+    }
+    u8(&FULL);
+    write(&kept[..len]);
+    false
+}
+
+/// Runs `f`, copying what it writes into `bytes` instead of passing it to the logger; returns the
+/// number of bytes written, or `None` if they didn't fit and were sent in full.
+#[cfg(not(feature = "unstable-test"))]
+fn capture(bytes: &mut [u8; CAPACITY], f: impl FnOnce()) -> Option<usize> {
+    CAPTURED_LEN.store(0, Ordering::Relaxed);
+    OVERFLOWED.store(false, Ordering::Relaxed);
+    CAPTURING.store(DEPTH.load(Ordering::Relaxed), Ordering::Relaxed);
+    f();
+    CAPTURING.store(0, Ordering::Relaxed);
+    if OVERFLOWED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let len = CAPTURED_LEN.load(Ordering::Relaxed);
+    for (byte, slot) in bytes.iter_mut().zip(&CAPTURED[..len]) {
+        *byte = slot.load(Ordering::Relaxed);
+    }
+    Some(len)
+}
+
+/// For testing purposes
+#[cfg(feature = "unstable-test")]
+fn capturing() -> bool {
+    false
+}
+
+/// For testing purposes
+#[cfg(feature = "unstable-test")]
+fn capture(bytes: &mut [u8; CAPACITY], f: impl FnOnce()) -> Option<usize> {
+    let start = super::BYTES.with(|b| b.borrow().len());
+    f();
+    super::BYTES.with(|b| {
+        let mut b = b.borrow_mut();
+        let len = b.len() - start;
+        if len > CAPACITY {
+            b.insert(start, FULL);
+            return None;
+        }
+        bytes[..len].copy_from_slice(&b.split_off(start));
+        Some(len)
+    })
+}
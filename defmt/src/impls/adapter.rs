@@ -20,6 +20,9 @@ use crate::{export, Format, Formatter, Str};
 /// //                                        must `#[derive(Debug)]`
 /// ```
 ///
+/// The text is not collected on the device: each piece the `Debug` impl produces is passed to
+/// the logger right away, so large outputs don't need a buffer.
+///
 /// Note that any provided defmt display hints will be ignored
 /// because this always uses `{:?}` to format the contained value.
 pub struct Debug2Format<'a, T: fmt::Debug + ?Sized>(pub &'a T);
@@ -58,6 +61,9 @@ impl<T: fmt::Debug + ?Sized> Format for Debug2Format<'_, T> {
 /// //                                        must implement `fmt::Display`
 /// ```
 ///
+/// The text is not collected on the device: each piece the `Display` impl produces is passed to
+/// the logger right away, so large outputs don't need a buffer.
+///
 /// Note that any provided defmt display hints will be ignored
 /// because this always uses `{}` to format the contained value.
 pub struct Display2Format<'a, T: fmt::Display + ?Sized>(pub &'a T);
@@ -73,3 +79,83 @@ impl<T: fmt::Display + ?Sized> Format for Display2Format<'_, T> {
         export::display(&self.0);
     }
 }
+
+/// An "adapter" type to log the elements of an iterator, without collecting them into a slice
+/// first.
+///
+/// The elements are streamed into the log frame, preceded by their count, and printed like a slice.
+/// To get the count, the iterator is cloned and run twice, so it should be cheap and free of side
+/// effects (like the adapters of `core::iter`).
+///
+/// # Examples
+///
+/// ```rust
+/// let readings: &[u16] = &[1, 900, 42, 1000];
+///
+/// defmt::info!("{}", defmt::FormatIter(readings.iter().filter(|r| **r > 100)));
+/// // -> INFO [900, 1000]
+/// ```
+///
+/// The `{=iter}` parameter does the same without the adapter:
+///
+/// ```rust
+/// # let readings: &[u16] = &[1, 900, 42, 1000];
+/// defmt::info!("{=iter}", readings.iter().map(|r| r / 10));
+/// ```
+pub struct FormatIter<I>(pub I);
+
+impl<I> Format for FormatIter<I>
+where
+    I: Iterator + Clone,
+    I::Item: Format,
+{
+    default_format!();
+
+    fn _format_tag() -> Str {
+        defmt_macros::internp!("{=[?]}")
+    }
+
+    fn _format_data(&self) {
+        export::fmt_iter(&self.0);
+    }
+}
+
+/// An "adapter" type to feed `serde::Serialize` values into defmt macros, which expect
+/// `defmt::Format` values.
+///
+/// The value is serialized to CBOR on-device and pretty-printed by the host. This is an escape
+/// hatch for deeply nested types, like configuration structures, for which deriving `Format` is
+/// impractical. It is less compact than `defmt::Format`, since field names are sent as well.
+///
+/// Requires the `serde` feature.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[derive(serde::Serialize)]
+/// struct Config {
+///     name: &'static str,
+///     retries: u8,
+/// }
+///
+/// let config = Config { name: "uplink", retries: 3 };
+/// defmt::info!("{}", defmt::Serde2Format(&config));
+/// // -> INFO {"name": "uplink", "retries": 3}
+/// ```
+///
+/// Note that any provided defmt display hints will be ignored.
+#[cfg(feature = "serde")]
+pub struct Serde2Format<'a, T: serde::Serialize + ?Sized>(pub &'a T);
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + ?Sized> Format for Serde2Format<'_, T> {
+    default_format!();
+
+    fn _format_tag() -> Str {
+        defmt_macros::internp!("{=__internal_Cbor}")
+    }
+
+    fn _format_data(&self) {
+        export::cbor(self.0);
+    }
+}
@@ -0,0 +1,54 @@
+use embedded_time::{duration, fixed_point::FixedPoint, rate, TimeInt};
+
+use super::*;
+
+macro_rules! embedded_time {
+    ($hint:literal, $($ty:ty),+) => {
+        $(
+            impl<T: TimeInt + Into<u64>> Format for $ty {
+                fn format(&self, fmt: Formatter) {
+                    let ratio = <$ty>::SCALING_FACTOR;
+                    let packed =
+                        pack_ratio(self.integer().into(), *ratio.numerator(), *ratio.denominator());
+                    crate::write!(fmt, $hint, packed);
+                }
+            }
+        )+
+    };
+}
+
+embedded_time!(
+    "{=u128:__internal_duration}",
+    duration::Hours<T>,
+    duration::Minutes<T>,
+    duration::Seconds<T>,
+    duration::Milliseconds<T>,
+    duration::Microseconds<T>,
+    duration::Nanoseconds<T>
+);
+
+// Only the frequency units; bit and baud rates would be rendered in hertz.
+embedded_time!(
+    "{=u128:__internal_rate}",
+    rate::Mebihertz<T>,
+    rate::Megahertz<T>,
+    rate::Kibihertz<T>,
+    rate::Kilohertz<T>,
+    rate::Hertz<T>,
+    rate::Decihertz<T>,
+    rate::Centihertz<T>,
+    rate::Millihertz<T>,
+    rate::Microhertz<T>
+);
+
+impl<T: TimeInt + Into<u64>> Format for duration::Generic<T> {
+    fn format(&self, fmt: Formatter) {
+        let ratio = self.scaling_factor();
+        let packed = pack_ratio(
+            self.integer().into(),
+            *ratio.numerator(),
+            *ratio.denominator(),
+        );
+        crate::write!(fmt, "{=u128:__internal_duration}", packed);
+    }
+}
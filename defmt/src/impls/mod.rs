@@ -32,9 +32,18 @@ pub mod adapter;
 mod alloc_;
 mod arrays;
 mod core_;
+#[cfg(feature = "embedded-time")]
+mod embedded_time_;
 mod primitives;
 mod tuples;
 
 use defmt_macros::internp;
 
 use crate::{self as defmt, Format, Formatter, Str};
+
+/// Packs a tick count and the `nom / denom` ratio converting it into seconds or hertz, as expected
+/// by the `__internal_duration` and `__internal_rate` display hints.
+#[cfg(feature = "embedded-time")]
+fn pack_ratio(ticks: u64, nom: u32, denom: u32) -> u128 {
+    ticks as u128 | (nom as u128) << 64 | (denom as u128) << 96
+}
@@ -14,7 +14,7 @@
 // NOTE if you change this URL you'll also need to update all other crates in this repo
 #![doc(html_logo_url = "https://knurling.ferrous-systems.com/knurling_logo_light_text.svg")]
 #![warn(missing_docs)]
-#![cfg_attr(feature = "ip_in_core", feature(ip_in_core))]
+#![cfg_attr(all(feature = "ip_in_core", not(core_net)), feature(ip_in_core))]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
@@ -50,17 +50,22 @@ mod encoding;
 pub mod export;
 mod formatter;
 mod impls;
+mod record;
 #[cfg(all(test, feature = "unstable-test"))]
 mod tests;
 mod traits;
 
 pub use crate::{
-    encoding::Encoder,
+    encoding::{Encoder, Fragmenter},
     formatter::{Formatter, Str},
-    impls::adapter::{Debug2Format, Display2Format},
+    impls::adapter::{Debug2Format, Display2Format, FormatIter},
+    record::Record,
     traits::{Format, Logger},
 };
 
+#[cfg(feature = "serde")]
+pub use crate::impls::adapter::Serde2Format;
+
 #[cfg(all(test, not(feature = "unstable-test")))]
 compile_error!(
     "to run unit tests enable the `unstable-test` feature, e.g. `cargo t --features unstable-test`"
@@ -204,6 +209,29 @@ pub use defmt_macros::unwrap;
 /// This attribute cannot be used together with the `export_name` or `no_mangle` attributes
 pub use defmt_macros::panic_handler;
 
+/// Logs the entry into, and exit from, a function at the TRACE level.
+///
+/// The entry frame includes the arguments bound to an identifier, and the exit frame the return
+/// value, so all of them must implement [`Format`]. Arguments can be left out with `skip(..)`, and
+/// the return value with `skip_return`.
+///
+/// # Examples
+///
+/// ```
+/// # struct Spi;
+/// #[defmt::trace_fn(skip(spi))]
+/// fn read_register(spi: &mut Spi, address: u8) -> Result<u8, ()> {
+///     Ok(0)
+/// }
+/// // TRACE → read_register(address: 12)
+/// // TRACE ← read_register = Ok(0)
+/// ```
+///
+/// The `return`s and `?`s of the body are rewritten so that they still pass through the exit
+/// frame; `?` is therefore only supported if the function returns a `Result` or `Option`.
+/// This attribute cannot be used on `const` and `async` functions.
+pub use defmt_macros::trace_fn;
+
 /// Creates an interned string ([`Str`]) from a string literal.
 ///
 /// This must be called on a string literal, and will allocate the literal in the object file. At
@@ -219,6 +247,21 @@ pub use defmt_macros::panic_handler;
 /// [`Str`]: struct.Str.html
 pub use defmt_macros::intern;
 
+/// Sends a handshake frame identifying the `.defmt` table of the firmware.
+///
+/// The frame carries a hash of the table, which `defmt-print stamp -e <ELF>` writes into the ELF
+/// file after linking; run it before flashing. Host tools that are given several ELF files, e.g.
+/// `defmt-print -e <DIRECTORY>`, use it to pick the one matching the device. Send it at boot,
+/// before any other log frame.
+///
+/// # Example
+///
+/// ```
+/// defmt::handshake!();
+/// defmt::info!("booted");
+/// ```
+pub use defmt_macros::handshake;
+
 /// Always logs data irrespective of log level.
 ///
 /// Please refer to [the manual] for documentation on the syntax.
@@ -262,6 +305,35 @@ pub use defmt_macros::warn;
 /// [`std::dbg!`]: https://doc.rust-lang.org/std/macro.dbg.html
 pub use defmt_macros::dbg;
 
+/// Logs the value of an expression implementing [`Format`], sending only the bytes that changed
+/// since the previous snapshot logged at the same call site.
+///
+/// Printers reconstruct the full value from the previous one, so periodic status structs cost
+/// little bandwidth while they stay mostly unchanged. Every 16th snapshot, and any snapshot whose
+/// encoding is longer than 64 bytes, is sent in full.
+///
+/// The value is logged at `INFO` level, unless another level is given first, as in
+/// `defmt::snapshot!(debug, status)`.
+///
+/// Only available with the `snapshot` feature, which adds a check to every write to the logger.
+///
+/// ```
+/// #[derive(defmt::Format)]
+/// struct Status {
+///     uptime: u32,
+///     battery: u8,
+///     charging: bool,
+/// }
+///
+/// # let status = Status { uptime: 120, battery: 87, charging: false };
+/// defmt::snapshot!(status);
+/// // INFO status = Status { uptime: 120, battery: 87, charging: false }
+/// ```
+///
+/// [`Format`]: trait.Format.html
+#[cfg(feature = "snapshot")]
+pub use defmt_macros::snapshot;
+
 /// Writes formatted data to a [`Formatter`].
 ///
 /// [`Formatter`]: struct.Formatter.html
@@ -313,6 +385,10 @@ pub use defmt_macros::global_logger;
 ///
 /// If no crate defines a timestamp, no timestamp will be included in the logged messages.
 ///
+/// Several timestamp sources can be defined by separating them with `;`. They are numbered in
+/// order, starting at 0, and the active one is selected with [`set_timestamp_source`]. Every frame
+/// then records which source produced its timestamp.
+///
 /// # Examples
 ///
 /// ```
@@ -321,8 +397,37 @@ pub use defmt_macros::global_logger;
 /// static COUNT: AtomicU32 = AtomicU32::new(0);
 /// defmt::timestamp!("{=u32:us}", COUNT.fetch_add(1, Ordering::Relaxed));
 /// ```
+///
+/// [`set_timestamp_source`]: fn.set_timestamp_source.html
 pub use defmt_macros::timestamp;
 
+/// Defines the task context attached to every defmt message, e.g. the task or interrupt handler
+/// that logged it.
+///
+/// The syntax is the same as that of [`timestamp!`]; the context is written after the timestamp,
+/// and printed in brackets before the message. Interned strings (`{=istr}`) keep it down to two
+/// bytes per frame. `task_context!` must only be used once across the crate graph.
+///
+/// Only available with the `task-context` feature. defmt does not know about executors, so it is
+/// up to the application, or the crate integrating defmt with an executor like RTIC or embassy, to
+/// enable the feature and provide the current task, e.g. from a `static` that each task updates
+/// when it starts running.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicU16, Ordering};
+///
+/// /// Set by each task when it is polled; `0` is the idle loop.
+/// static CURRENT_TASK: AtomicU16 = AtomicU16::new(0);
+///
+/// defmt::task_context!("task {=u16}", CURRENT_TASK.load(Ordering::Relaxed));
+/// ```
+///
+/// [`timestamp!`]: macro.timestamp.html
+#[cfg(feature = "task-context")]
+pub use defmt_macros::task_context;
+
 /// Generates a bitflags structure that can be formatted with defmt.
 ///
 /// This macro is a wrapper around the [`bitflags!`] crate, and provides an (almost) identical
@@ -363,11 +468,26 @@ pub use defmt_macros::Format;
 #[export_name = "__defmt_default_timestamp"]
 fn default_timestamp(_f: Formatter<'_>) {}
 
+// Likewise, frames only carry a task context if `task_context!` was used (with the `task-context`
+// feature).
+#[export_name = "__defmt_default_task_context"]
+fn default_task_context(_f: Formatter<'_>) {}
+
 #[export_name = "__defmt_default_panic"]
 fn default_panic() -> ! {
     core::panic!()
 }
 
+/// Selects which of the sources defined with [`timestamp!`] is used for subsequent log frames.
+///
+/// Sources are numbered in the order they appear in the `timestamp!` invocation. An out-of-range
+/// `source` selects the first one. This has no effect if only a single source is defined.
+///
+/// [`timestamp!`]: macro.timestamp.html
+pub fn set_timestamp_source(source: u8) {
+    export::set_timestamp_source(source)
+}
+
 /// Block until host has read all pending data.
 ///
 /// The flush operation will not fail, but might not succeed in flushing _all_ pending data. It is
@@ -0,0 +1,55 @@
+use crate::export;
+
+const BEGIN: u8 = 0;
+const CONTINUE: u8 = 1;
+const END: u8 = 2;
+
+/// A sequence of log frames that the printer shows together, as one logical record
+///
+/// Frames logged by the task that began a record are held back by the printer until the record
+/// ends, and are then shown without frames of other tasks in between. Useful for multi-part dumps,
+/// e.g. of a configuration or a register table.
+///
+/// Tasks are told apart by the context set with `task_context!` (see the `task-context` feature);
+/// without it, the frames of different tasks can't be told apart, and are shown as they arrive.
+/// The printer shows the frames held back for a record once there are too many of them, even if
+/// the record hasn't ended.
+///
+/// The record ends when it is dropped, or with [`Record::end`].
+///
+/// ```
+/// let record = defmt::Record::begin();
+/// defmt::info!("config:");
+/// defmt::info!("  baudrate = {}", 115_200);
+/// defmt::info!("  parity = {}", "none");
+/// record.end();
+/// ```
+pub struct Record {
+    id: u8,
+}
+
+impl Record {
+    /// Begins a record; the frames subsequently logged by the calling task belong to it.
+    pub fn begin() -> Self {
+        Self {
+            id: export::record(None, BEGIN),
+        }
+    }
+
+    /// Continues the record in the calling task, e.g. an interrupt handler adding to a record
+    /// begun by another task; the frames subsequently logged by it belong to the record.
+    pub fn resume(&self) {
+        export::record(Some(self.id), CONTINUE);
+    }
+
+    /// Ends the record; the printer shows all of its frames.
+    pub fn end(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Record {
+    fn drop(&mut self) {
+        export::record(Some(self.id), END);
+    }
+}
@@ -1,5 +1,27 @@
 fn main() {
     defmt::info!("hello");
+
+    traced(1, (2, 3));
+    traced_fallible("4", 5).ok();
+    traced_unit(true);
+    Traced(6).get();
+    Traced(7).iter(8).count();
+    Traced(9).try_iter(10).ok();
+    Traced(11).try_get(12).ok();
+    *Traced(13).get_mut() += 1;
+    if let Some(first) = traced_first(&mut [14, 15]) {
+        *first += 1;
+    }
+
+    defmt::info!(
+        "{} {}",
+        Credentials {
+            user: "admin",
+            pin: 1234,
+            token: b"secret",
+        },
+        Auth::Pin(1234)
+    );
 }
 
 #[defmt::global_logger]
@@ -13,3 +35,75 @@ unsafe impl defmt::Logger for Logger {
 }
 
 defmt::timestamp!("{=u32}", 0);
+
+#[defmt::trace_fn]
+fn traced(a: u8, (b, c): (u8, u8)) -> u16 {
+    if a == 0 {
+        return 0;
+    }
+    u16::from(a + b + c)
+}
+
+#[defmt::trace_fn(skip(x), skip_return)]
+fn traced_fallible(x: &str, y: u32) -> Result<u32, core::num::ParseIntError> {
+    Ok(x.parse::<u32>()? + y)
+}
+
+#[defmt::trace_fn]
+fn traced_unit(_ignored: bool) {}
+
+#[defmt::trace_fn]
+fn traced_first(data: &mut [u8]) -> Option<&mut u8> {
+    let first = data.first_mut()?;
+    Some(first)
+}
+
+struct Traced(u8);
+
+impl Traced {
+    #[defmt::trace_fn]
+    fn get(&self) -> &u8 {
+        &self.0
+    }
+
+    #[defmt::trace_fn]
+    fn get_mut(&mut self) -> &mut u8 {
+        &mut self.0
+    }
+
+    #[defmt::trace_fn]
+    fn iter(&self, n: usize) -> impl Iterator<Item = u8> + '_ {
+        (0..n as u8).map(move |i| self.0.wrapping_add(i))
+    }
+
+    #[defmt::trace_fn(skip_return)]
+    fn try_iter(&self, n: usize) -> Result<impl Iterator<Item = u8> + '_, ()> {
+        if n == 0 {
+            return Err(());
+        }
+        Ok(self.iter(n))
+    }
+
+    #[defmt::trace_fn]
+    #[track_caller]
+    fn try_get(&self, n: usize) -> Result<u8, core::num::TryFromIntError> {
+        if n == 0 {
+            return Ok(self.0);
+        }
+        Ok(u8::try_from(n)?.wrapping_add(self.0))
+    }
+}
+
+#[derive(defmt::Format)]
+struct Credentials<'a> {
+    user: &'a str,
+    #[defmt(sensitive)]
+    pin: u16,
+    #[defmt(sensitive, Debug2Format)]
+    token: &'a [u8],
+}
+
+#[derive(defmt::Format)]
+enum Auth {
+    Pin(#[defmt(sensitive)] u16),
+}
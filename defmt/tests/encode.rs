@@ -36,7 +36,9 @@
 //
 // - the mocked index is 7 bits so its LEB128 encoding is the input byte
 
-use defmt::{export::fetch_string_index, write, Debug2Format, Display2Format, Format, Formatter};
+use defmt::{
+    export::fetch_string_index, write, Debug2Format, Display2Format, Format, FormatIter, Formatter,
+};
 
 // Increase the 7-bit mocked interned index
 fn inc(index: u16, n: u16) -> u16 {
@@ -73,6 +75,53 @@ macro_rules! check_format {
     }
 }
 
+defmt::timestamp!(
+    "{=u8}", 1;
+    "{=u16:us}", 2;
+);
+
+#[test]
+fn timestamp_sources() {
+    extern "Rust" {
+        fn _defmt_timestamp(_: Formatter<'_>);
+    }
+    // `defmt::export::timestamp` does nothing when testing
+    let timestamp = || unsafe { _defmt_timestamp(defmt::export::make_formatter()) };
+
+    timestamp();
+    check!([
+        0u8, // source
+        1u8, // `{=u8}`
+    ]);
+
+    defmt::set_timestamp_source(1);
+    timestamp();
+    check!([
+        1u8,  // source
+        2u16, // `{=u16:us}`
+    ]);
+
+    // an id without a matching source selects the first one
+    defmt::set_timestamp_source(2);
+    timestamp();
+    check!([0u8, 1u8]);
+    defmt::set_timestamp_source(0);
+}
+
+#[cfg(feature = "task-context")]
+defmt::task_context!("{=u8}", 7);
+
+#[cfg(feature = "task-context")]
+#[test]
+fn task_context() {
+    extern "Rust" {
+        fn _defmt_task_context(_: Formatter<'_>);
+    }
+    // `defmt::export::task_context` does nothing when testing
+    unsafe { _defmt_task_context(defmt::export::make_formatter()) };
+    check!([7u8]);
+}
+
 #[test]
 fn write() {
     let index = fetch_string_index();
@@ -92,6 +141,284 @@ fn write() {
     ]);
 }
 
+#[test]
+fn word_slice() {
+    let index = fetch_string_index();
+    let g = defmt::export::make_formatter();
+    let words: &[u16] = &[0xbeef, 0xf00d];
+    write!(g, "{=[u16]:x}", words);
+    check!([
+        index,     // "{=[u16]:x}"
+        2u32,      // length
+        0xbeefu16, // words[0]
+        0xf00du16, // words[1]
+    ]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_cbor() {
+    let index = fetch_string_index();
+    let val = ("hi", 300u16, [-1i8, 24], None::<bool>);
+    check_format!(
+        &defmt::Serde2Format(&val),
+        [
+            index,  // "{=__internal_Cbor}"
+            0x84u8, // array(4)
+            0x62u8, // text(2)
+            b'h',
+            b'i',
+            0x19u8, // unsigned(300)
+            300u16.swap_bytes(),
+            0x82u8, // array(2)
+            0x20u8, // negative(-1)
+            0x18u8, // unsigned(24)
+            24u8,
+            0xf6u8, // null
+        ],
+    )
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_cbor_error() {
+    use serde::ser::{Error as _, SerializeSeq as _};
+
+    /// Announces three elements but fails after the first one
+    struct Failing;
+
+    impl serde::Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(Some(3))?;
+            seq.serialize_element(&1u8)?;
+            Err(S::Error::custom("failed"))
+        }
+    }
+
+    /// Fails before writing anything
+    struct Nothing;
+
+    impl serde::Serialize for Nothing {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(S::Error::custom("failed"))
+        }
+    }
+
+    let index = fetch_string_index();
+    check_format!(
+        &defmt::Serde2Format(&(Failing, 2u8)),
+        [
+            index,  // "{=__internal_Cbor}"
+            0x82u8, // array(2)
+            0x83u8, // array(3)
+            0x01u8, // unsigned(1)
+            0xf7u8, // undefined, in place of the missing elements
+            0xf7u8, // undefined
+            0xf7u8, // undefined, in place of `2u8`: serializing the tuple failed too
+        ],
+    );
+
+    let index = fetch_string_index();
+    check_format!(
+        &defmt::Serde2Format(&Nothing),
+        [
+            index,  // "{=__internal_Cbor}"
+            0xf7u8, // undefined
+        ],
+    );
+}
+
+#[cfg(feature = "embedded-time")]
+#[test]
+fn embedded_time() {
+    use embedded_time::{duration::Milliseconds, rate::Kilohertz};
+
+    let index = fetch_string_index();
+    check_format!(
+        &Milliseconds(1_500u32),
+        [
+            index,                                     // "{=__internal_FormatSequence}"
+            inc(index, 1),                             // "{=u128:__internal_duration}"
+            1_500u128 | 1u128 << 64 | 1_000u128 << 96, // ticks, then ratio to seconds
+            0u16,                                      // terminator
+        ],
+    );
+
+    let index = fetch_string_index();
+    check_format!(
+        &Kilohertz(8u32),
+        [
+            index,                                 // "{=__internal_FormatSequence}"
+            inc(index, 1),                         // "{=u128:__internal_rate}"
+            8u128 | 1_000u128 << 64 | 1u128 << 96, // ticks, then ratio to hertz
+            0u16,                                  // terminator
+        ],
+    );
+}
+
+#[cfg(feature = "caller-location")]
+#[track_caller]
+fn check_positive(x: i32) {
+    defmt::assert!(x > 0);
+}
+
+#[cfg(feature = "caller-location")]
+#[test]
+fn assert_reports_caller_location() {
+    let index = fetch_string_index();
+    let line = line!() + 1;
+    let result = std::panic::catch_unwind(|| check_positive(-1));
+    assert!(result.is_err());
+
+    let file = file!();
+    let mut expected = index.to_le_bytes().to_vec(); // "panicked at '...', {0=str}:{1=u32}:{2=u32}"
+    expected.extend((file.len() as u32).to_le_bytes()); // file name length
+    expected.extend(file.as_bytes()); // file name
+    expected.extend(line.to_le_bytes()); // line of the `check_positive` call, not the `assert!`
+
+    // the column is not checked
+    let bytes = defmt::export::fetch_bytes();
+    assert_eq!(bytes[..bytes.len() - 4], expected);
+}
+
+#[test]
+fn chunked() {
+    let index = fetch_string_index();
+    let data = (0..300).map(|x| x as u8).collect::<Vec<_>>();
+    defmt::error!("{=chunked}", &data[..]);
+
+    let mut expected = index.to_le_bytes().to_vec(); // "{=chunked}"
+    expected.push(0); // id
+    expected.extend(300u32.to_le_bytes()); // length
+    for (i, chunk) in data.chunks(256).enumerate() {
+        expected.extend(inc(index, i as u16 + 1).to_le_bytes()); // "{=__internal_Chunk}"
+        expected.push(0); // id
+        expected.extend((chunk.len() as u32).to_le_bytes());
+        expected.extend(chunk);
+    }
+    assert_eq!(defmt::export::fetch_bytes(), expected);
+}
+
+#[test]
+fn handshake() {
+    let index = fetch_string_index();
+    defmt::handshake!();
+
+    let bytes = defmt::export::fetch_bytes();
+    assert_eq!(bytes[..2], index.to_le_bytes()); // "handshake"
+    assert_eq!(bytes[2..], u64::MAX.to_le_bytes()); // no timestamp, unstamped table hash
+}
+
+#[test]
+fn record() {
+    let index = fetch_string_index();
+    let record = defmt::Record::begin();
+    defmt::error!("part");
+    record.resume();
+    record.end();
+    {
+        let _record = defmt::Record::begin();
+    }
+
+    check!([
+        index,         // "{=__internal_Record}"
+        0u8,           // id
+        0u8,           // begin
+        inc(index, 1), // "part"
+        inc(index, 2), // "{=__internal_Record}"
+        0u8,           // id
+        1u8,           // continue
+        inc(index, 3), // "{=__internal_Record}"
+        0u8,           // id
+        2u8,           // end
+        inc(index, 4), // "{=__internal_Record}"
+        1u8,           // id
+        0u8,           // begin
+        inc(index, 5), // "{=__internal_Record}"
+        1u8,           // id
+        2u8,           // end, when dropped
+    ]);
+}
+
+#[cfg(feature = "snapshot")]
+#[test]
+fn snapshot() {
+    #[derive(Format)]
+    struct Status {
+        uptime: u32,
+        battery: u8,
+        charging: bool,
+    }
+
+    let index = fetch_string_index();
+    for battery in [5, 6, 6] {
+        let status = Status {
+            uptime: 1000,
+            battery,
+            charging: false,
+        };
+        defmt::snapshot!(error, status);
+    }
+
+    check!([
+        index,               // "status = {=__internal_Snapshot}"
+        0u8,                 // sequence number
+        0u8,                 // full
+        inc(index, 1),       // "Status {{ uptime: {=u32}, battery: {=u8}, charging: {=bool} }}"
+        1000u32,             // uptime
+        5u8,                 // battery
+        0u8,                 // charging
+        inc(index, 2),       // "status = {=__internal_Snapshot}"
+        1u8,                 // sequence number
+        1u8,                 // delta
+        2u8,                 // runs
+        0u8,                 // offset
+        1u8,                 // length
+        inc(index, 3) as u8, // the mocked interner returns another index every time
+        5u8,                 // offset from the end of the previous run
+        1u8,                 // length
+        6u8,                 // battery
+        inc(index, 4),       // "status = {=__internal_Snapshot}"
+        2u8,                 // sequence number
+        1u8,                 // delta
+        1u8,                 // runs
+        0u8,                 // offset
+        1u8,                 // length
+        inc(index, 5) as u8,
+    ]);
+}
+
+#[cfg(feature = "snapshot")]
+#[test]
+fn snapshot_too_long() {
+    use core::cell::Cell;
+
+    // counts how often it is formatted
+    struct Long(Cell<u32>);
+
+    impl Format for Long {
+        fn format(&self, f: Formatter) {
+            self.0.set(self.0.get() + 1);
+            write!(f, "{=[u8]}", &[0; 70][..])
+        }
+    }
+
+    let index = fetch_string_index();
+    let long = Long(Cell::new(0));
+    defmt::snapshot!(error, long);
+
+    let mut expected = Vec::new();
+    expected.extend(index.to_le_bytes()); // "long = {=__internal_Snapshot}"
+    expected.extend([0, 0]); // sequence number, full
+    expected.extend(inc(index, 1).to_le_bytes()); // "{=__internal_FormatSequence}"
+    expected.extend(inc(index, 2).to_le_bytes()); // "{=[u8]}"
+    expected.extend(70u32.to_le_bytes()); // length
+    expected.extend([0; 70]);
+    expected.extend(0u16.to_le_bytes()); // terminator
+    assert_eq!(defmt::export::fetch_bytes(), expected);
+    assert_eq!(long.0.get(), 1);
+}
+
 #[test]
 fn bitfields_mixed() {
     let index = fetch_string_index();
@@ -439,6 +766,7 @@ fn c_like_enum() {
 
 #[test]
 fn uninhabited_enum() {
+    #[allow(dead_code)]
     #[derive(Format)]
     enum Void {}
 }
@@ -559,6 +887,38 @@ fn slice() {
     )
 }
 
+#[test]
+fn format_iter() {
+    let index = fetch_string_index();
+    let val: &[u8] = &[23u8, 1, 42u8];
+    let iter = FormatIter(val.iter().filter(|x| **x > 1));
+    check_format!(
+        &iter,
+        [
+            index,         // "{=[?]}"
+            2u32,          // number of elements
+            inc(index, 1), // "{=u8}"
+            23u8,          // first element
+            42u8,          // second element
+        ],
+    )
+}
+
+#[test]
+fn iter_param() {
+    let index = fetch_string_index();
+    let g = defmt::export::make_formatter();
+    write!(g, "{=iter}", (1u8..4).map(|x| x * 2));
+    check!([
+        index,         // "{=iter}"
+        3u32,          // number of elements
+        inc(index, 1), // "{=u8}"
+        2u8,
+        4u8,
+        6u8,
+    ]);
+}
+
 #[test]
 fn slice_of_usize() {
     let index = fetch_string_index();
@@ -1001,6 +1361,30 @@ fn derive_str() {
     );
 }
 
+#[test]
+fn core_fmt_adapters_stream() {
+    // records what reached the logger after each piece of text
+    struct Pieces(core::cell::RefCell<Vec<Vec<u8>>>);
+
+    impl core::fmt::Display for Pieces {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            for piece in ["first", "second"] {
+                f.write_str(piece)?;
+                self.0.borrow_mut().push(defmt::export::fetch_bytes());
+            }
+            Ok(())
+        }
+    }
+
+    let pieces = Pieces(Default::default());
+    let index = fetch_string_index();
+    write_format(&Display2Format(&pieces));
+    let mut first = index.to_le_bytes().to_vec();
+    first.extend_from_slice(b"first");
+    assert_eq!(pieces.0.into_inner(), [first, b"second".to_vec()]);
+    assert_eq!(defmt::export::fetch_bytes(), [0xff]);
+}
+
 #[test]
 fn core_fmt_adapters() {
     let index = fetch_string_index();
@@ -1008,3 +1392,86 @@ fn core_fmt_adapters() {
     let index = fetch_string_index();
     check_format!(&Display2Format(&123u8), [index, b'1', b'2', b'3', 0xffu8]);
 }
+
+// TRACE is enabled for this module by the build script of `defmt`
+mod trace_fn {
+    use super::{fetch_string_index, inc};
+
+    #[defmt::trace_fn]
+    fn add(a: u8, b: u8) -> u8 {
+        a + b
+    }
+
+    #[defmt::trace_fn(skip(b))]
+    #[track_caller]
+    fn checked_add(a: u8, b: u8) -> Option<u8> {
+        if a == 0 {
+            return Some(b);
+        }
+        let sum = a.checked_add(b)?;
+        Some(sum)
+    }
+
+    #[test]
+    fn entry_and_exit() {
+        let index = fetch_string_index();
+        assert_eq!(add(1, 2), 3);
+        check!([
+            index,         // "→ add(a: {}, b: {})"
+            inc(index, 1), // "{=u8}"
+            1u8,           // a
+            inc(index, 2), // "{=u8}"
+            2u8,           // b
+            inc(index, 3), // "← add = {}"
+            inc(index, 4), // "{=u8}"
+            3u8,           // return value
+        ]);
+    }
+
+    #[test]
+    fn early_exit_with_track_caller() {
+        let index = fetch_string_index();
+        assert_eq!(checked_add(255, 1), None);
+        check!([
+            index,         // "→ checked_add(a: {})"
+            inc(index, 1), // "{=u8}"
+            255u8,         // a
+            inc(index, 2), // "← checked_add = {}"
+            inc(index, 3), // "{=?}" / impl Format for Option<u8>
+            0u8,           // None
+        ]);
+    }
+
+    #[defmt::trace_fn(skip(data))]
+    fn last(data: &mut [u8]) -> &mut u8 {
+        let len = data.len();
+        &mut data[len - 1]
+    }
+
+    #[test]
+    fn returns_reborrow() {
+        let mut data = [1, 2];
+        let index = fetch_string_index();
+        *last(&mut data) += 1;
+        assert_eq!(data, [1, 3]);
+        check!([
+            index,         // "→ last()"
+            inc(index, 1), // "← last = {}"
+            inc(index, 2), // "{=u8}"
+            2u8,           // return value
+        ]);
+    }
+
+    #[defmt::trace_fn(skip_return)]
+    #[track_caller]
+    fn caller_line() -> u32 {
+        core::panic::Location::caller().line()
+    }
+
+    #[test]
+    fn keeps_track_caller() {
+        let line = line!() + 1;
+        assert_eq!(caller_line(), line);
+        defmt::export::fetch_bytes();
+    }
+}
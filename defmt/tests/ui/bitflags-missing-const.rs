@@ -0,0 +1,7 @@
+defmt::bitflags! {
+    struct Flags: u8 {
+        A = 1;
+    }
+}
+
+fn main() {}
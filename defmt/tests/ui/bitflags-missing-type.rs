@@ -0,0 +1,7 @@
+defmt::bitflags! {
+    struct Flags {
+        const A = 1;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(defmt::Format)]
+struct S {
+    #[defmt(Debug2Format, sensitive, Display2Format)]
+    f: bool,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(defmt::Format)]
+union U {
+    a: u8,
+    b: u16,
+}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[defmt::global_logger(foo)]
+struct Logger;
+
+fn main() {}
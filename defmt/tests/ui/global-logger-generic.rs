@@ -0,0 +1,4 @@
+#[defmt::global_logger]
+struct Logger<T>(T);
+
+fn main() {}
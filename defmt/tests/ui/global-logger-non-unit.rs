@@ -0,0 +1,6 @@
+#[defmt::global_logger]
+struct Logger {
+    x: u8,
+}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[defmt::trace_fn(skip(y))]
+fn foo(x: u8) {}
+
+fn main() {}
@@ -10,16 +10,39 @@
 //!
 //! defmt::info!("Hello");
 //! ```
+//!
+//! # Logging from interrupt handlers
+//!
+//! The logger never masks interrupts. A log frame started while no other frame is in progress is
+//! written to the ITM directly. A frame logged by an interrupt handler that preempted a frame in
+//! progress is staged in a buffer of its own, one per level of preemption, and written to the ITM
+//! by the preempted context once its own frame is complete; that is, frames are merged at the
+//! lowest priority that is logging.
+//!
+//! The staging buffers are indexed by the number of frames in progress, not by interrupt priority:
+//! a handler only takes a staging level if it preempted a frame in progress, so handlers of many
+//! different priorities can share a level, one after another. Two contexts at the same level never
+//! run concurrently, as one would have to preempt the other, and be a level deeper.
+//!
+//! Frames that don't fit in their staging buffer, or are logged more than [`STAGING_LEVELS`] levels
+//! of preemption deep, are dropped; see [`dropped_frames`].
+//!
+//! `defmt::flush` writes the staged frames to the ITM when called with no other frame in progress.
+//! A context that preempted a frame in progress and never returns, e.g. a panic or fault handler,
+//! has to call [`flush_staged`] instead, or its frames are never written.
 
 #![doc(html_logo_url = "https://knurling.ferrous-systems.com/knurling_logo_light_text.svg")]
 #![no_std]
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::{
+    cell::UnsafeCell,
+    slice,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
 
 use cortex_m::{
-    asm, interrupt, itm,
+    asm, itm,
     peripheral::{itm::Stim, ITM},
-    register,
 };
 
 #[cfg(armv6m)]
@@ -27,6 +50,12 @@ compile_error!(
     "`defmt-itm` cannot be used on Cortex-M0(+) chips, because it requires an ITM peripheral"
 );
 
+/// Number of nested levels of preemption whose frames can be staged
+pub const STAGING_LEVELS: usize = 4;
+
+/// Size of the staging buffer of each level of preemption, in bytes
+pub const STAGING_BUFFER_SIZE: usize = 256;
+
 static ENABLED: AtomicBool = AtomicBool::new(false);
 
 /// Enables defmt logging over the ITM stimulus port 0.
@@ -38,12 +67,59 @@ pub fn enable(itm: ITM) {
     ENABLED.store(true, Ordering::Relaxed);
 }
 
+/// Returns the number of frames dropped because their staging buffer was full, or because they
+/// were logged too many levels of preemption deep.
+pub fn dropped_frames() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Writes the frames staged by interrupt handlers to the ITM, abandoning the frame in progress they
+/// preempted.
+///
+/// Meant for handlers that never return, e.g. the `HardFault` handler that `panic-probe` ends in:
+/// the frames they log while another frame is in progress, like the panic message, are staged,
+/// but the context writing the staged frames would only do so once it resumes.
+///
+/// ``` ignore
+/// use cortex_m_rt::{exception, ExceptionFrame};
+///
+/// #[exception]
+/// unsafe fn HardFault(_frame: &ExceptionFrame) -> ! {
+///     defmt_itm::flush_staged();
+///     loop {}
+/// }
+/// ```
+///
+/// The abandoned frame is ended right away; with the default `rzcobs` encoding, the host skips it
+/// as malformed.
+///
+/// # Safety
+///
+/// The contexts preempted by the caller must never resume, and the caller must not be logging a
+/// frame itself.
+pub unsafe fn flush_staged() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if DEPTH.swap(0, Ordering::Acquire) > 0 {
+        // safety: the context that logged the frame in progress never resumes
+        ENCODER.end_frame(do_write);
+    }
+    drain();
+    // safety: `enable` has run
+    while !stim_0().is_fifo_ready() {}
+}
+
 #[defmt::global_logger]
 struct Logger;
 
-static TAKEN: AtomicBool = AtomicBool::new(false);
-static INTERRUPTS_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Number of frames in progress; frames in progress are nested, because a context can't resume
+/// before all contexts that preempted it have returned.
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
 static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+static STAGING: [Staging; STAGING_LEVELS] = [Staging::NEW; STAGING_LEVELS];
 
 unsafe impl defmt::Logger for Logger {
     fn acquire() {
@@ -51,23 +127,25 @@ unsafe impl defmt::Logger for Logger {
             panic!("defmt ITM logger is not enabled")
         }
 
-        let primask = register::primask::read();
-        interrupt::disable();
-
-        if TAKEN.load(Ordering::Relaxed) {
-            panic!("defmt logger taken reentrantly")
+        match DEPTH.fetch_add(1, Ordering::Acquire) {
+            // safety: accessing the `static mut` is OK because only the outermost frame uses it.
+            0 => unsafe { ENCODER.start_frame(do_write) },
+            depth => {
+                if let Some(staging) = staging(depth) {
+                    // safety: only this context uses the staging buffer of its depth.
+                    unsafe { staging.start_frame() }
+                }
+            }
         }
-
-        // no need for CAS because interrupts are disabled
-        TAKEN.store(true, Ordering::Relaxed);
-
-        INTERRUPTS_ACTIVE.store(primask.is_active(), Ordering::Relaxed);
-
-        // safety: accessing the `static mut` is OK because we have disabled interrupts.
-        unsafe { ENCODER.start_frame(do_write) }
     }
 
     unsafe fn flush() {
+        // the staged frames can only be written by the outermost frame; otherwise, they are
+        // written once the preempted frame is complete
+        if current_depth() == 0 {
+            drain();
+        }
+
         // wait for the queue to be able to accept more data
         while !stim_0().is_fifo_ready() {}
 
@@ -77,19 +155,163 @@ unsafe impl defmt::Logger for Logger {
     }
 
     unsafe fn release() {
-        // safety: accessing the `static mut` is OK because we have disabled interrupts.
-        ENCODER.end_frame(do_write);
+        match current_depth() {
+            0 => {
+                // safety: accessing the `static mut` is OK because only the outermost frame uses it.
+                ENCODER.end_frame(do_write);
+
+                loop {
+                    drain();
+                    DEPTH.store(0, Ordering::Release);
 
-        TAKEN.store(false, Ordering::Relaxed);
-        if INTERRUPTS_ACTIVE.load(Ordering::Relaxed) {
-            // re-enable interrupts
-            interrupt::enable()
+                    // a frame staged after `drain` checked its buffer would otherwise wait for the
+                    // next frame to be logged
+                    let pending = STAGING.iter().any(Staging::is_pending);
+                    if !pending
+                        || DEPTH
+                            .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+                            .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+            depth => {
+                match staging(depth) {
+                    Some(staging) => staging.commit(),
+                    None => {
+                        DROPPED.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                DEPTH.fetch_sub(1, Ordering::Release);
+            }
         }
     }
 
     unsafe fn write(bytes: &[u8]) {
-        // safety: accessing the `static mut` is OK because we have disabled interrupts.
-        ENCODER.write(bytes, do_write);
+        match current_depth() {
+            // safety: accessing the `static mut` is OK because only the outermost frame uses it.
+            0 => ENCODER.write(bytes, do_write),
+            depth => {
+                if let Some(staging) = staging(depth) {
+                    staging.write(bytes)
+                }
+            }
+        }
+    }
+}
+
+/// Returns the depth of the frame in progress in the current context.
+fn current_depth() -> usize {
+    // all contexts that preempted this one have returned, restoring `DEPTH`
+    DEPTH.load(Ordering::Relaxed) - 1
+}
+
+/// Returns the staging buffer of the frames logged at `depth`, which must be at least 1.
+fn staging(depth: usize) -> Option<&'static Staging> {
+    STAGING.get(depth - 1)
+}
+
+/// Writes the staged frames to the ITM; must only be called by the outermost frame.
+fn drain() {
+    for staging in &STAGING {
+        staging.drain();
+    }
+}
+
+/// Single-producer, single-consumer ring buffer of encoded frames
+///
+/// The producer is the context logging at the buffer's depth, the consumer is the outermost frame.
+/// Positions are free-running and wrap around `usize`.
+struct Staging {
+    buffer: UnsafeCell<[u8; STAGING_BUFFER_SIZE]>,
+    /// End of the committed frames; only written by the producer
+    committed: AtomicUsize,
+    /// End of the data written to the ITM; only written by the consumer
+    read: AtomicUsize,
+    /// End of the frame in progress; only used by the producer
+    head: UnsafeCell<usize>,
+    /// Whether the frame in progress didn't fit; only used by the producer
+    overflowed: UnsafeCell<bool>,
+    encoder: UnsafeCell<defmt::Encoder>,
+}
+
+// safety: see the comments on the fields
+unsafe impl Sync for Staging {}
+
+impl Staging {
+    #[allow(clippy::declare_interior_mutable_const)] // only used to initialize `STAGING`
+    const NEW: Self = Self {
+        buffer: UnsafeCell::new([0; STAGING_BUFFER_SIZE]),
+        committed: AtomicUsize::new(0),
+        read: AtomicUsize::new(0),
+        head: UnsafeCell::new(0),
+        overflowed: UnsafeCell::new(false),
+        encoder: UnsafeCell::new(defmt::Encoder::new()),
+    };
+
+    /// # Safety
+    /// Must only be called by the producer.
+    unsafe fn start_frame(&self) {
+        *self.head.get() = self.committed.load(Ordering::Relaxed);
+        *self.overflowed.get() = false;
+        (*self.encoder.get()).start_frame(|bytes| self.push(bytes));
+    }
+
+    /// # Safety
+    /// Must only be called by the producer.
+    unsafe fn write(&self, bytes: &[u8]) {
+        (*self.encoder.get()).write(bytes, |bytes| self.push(bytes));
+    }
+
+    /// Makes the frame in progress available to the consumer, or drops it if it didn't fit.
+    ///
+    /// # Safety
+    /// Must only be called by the producer.
+    unsafe fn commit(&self) {
+        (*self.encoder.get()).end_frame(|bytes| self.push(bytes));
+        if *self.overflowed.get() {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.committed.store(*self.head.get(), Ordering::Release);
+        }
+    }
+
+    /// # Safety
+    /// Must only be called by the producer.
+    unsafe fn push(&self, bytes: &[u8]) {
+        let head = &mut *self.head.get();
+        let free = STAGING_BUFFER_SIZE - head.wrapping_sub(self.read.load(Ordering::Acquire));
+        if *self.overflowed.get() || bytes.len() > free {
+            *self.overflowed.get() = true;
+            return;
+        }
+
+        let buffer = self.buffer.get().cast::<u8>();
+        for &byte in bytes {
+            buffer.add(*head % STAGING_BUFFER_SIZE).write(byte);
+            *head = head.wrapping_add(1);
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        self.committed.load(Ordering::Acquire) != self.read.load(Ordering::Relaxed)
+    }
+
+    /// Writes the committed frames to the ITM; must only be called by the consumer.
+    fn drain(&self) {
+        let end = self.committed.load(Ordering::Acquire);
+        let mut start = self.read.load(Ordering::Relaxed);
+        while start != end {
+            let offset = start % STAGING_BUFFER_SIZE;
+            let len = end.wrapping_sub(start).min(STAGING_BUFFER_SIZE - offset);
+            // safety: the producer doesn't write to committed data that hasn't been read yet
+            let bytes =
+                unsafe { slice::from_raw_parts(self.buffer.get().cast::<u8>().add(offset), len) };
+            do_write(bytes);
+            start = start.wrapping_add(len);
+        }
+        self.read.store(end, Ordering::Release);
     }
 }
 
@@ -1,14 +1,10 @@
 use std::{env, path::PathBuf};
 
 fn main() {
-    println!("cargo:rerun-if-env-changed=DEFMT_RTT_BUFFER_SIZE");
-
-    let size = env::var("DEFMT_RTT_BUFFER_SIZE")
-        .map(|s| {
-            s.parse()
-                .expect("could not parse DEFMT_RTT_BUFFER_SIZE as usize")
-        })
-        .unwrap_or(1024_usize);
+    let size = buffer_size("DEFMT_RTT_BUFFER_SIZE", 1024);
+    let (mode, overflow) = channel_mode("DEFMT_RTT_MODE");
+    let data_size = buffer_size("DEFMT_RTT_DATA_BUFFER_SIZE", 0);
+    let (data_mode, data_overflow) = channel_mode("DEFMT_RTT_DATA_MODE");
 
     let out_dir_path = PathBuf::from(env::var_os("OUT_DIR").unwrap());
     let out_file_path = out_dir_path.join("consts.rs");
@@ -20,9 +16,55 @@ fn main() {
             ///
             /// Can be customized by setting the `DEFMT_RTT_BUFFER_SIZE` environment variable.
             /// Use a power of 2 for best performance.
-            pub(crate) const BUF_SIZE: usize = {};",
-            size
+            pub(crate) const BUF_SIZE: usize = {size};
+
+            /// Initial mode of the defmt channel (default: trim).
+            ///
+            /// Can be customized by setting the `DEFMT_RTT_MODE` environment variable.
+            pub(crate) const MODE: usize = crate::{mode};
+
+            /// What the defmt channel does with data that doesn't fit, unless it blocks.
+            pub(crate) const OVERFLOW: crate::channel::Overflow =
+                crate::channel::Overflow::{overflow};
+
+            /// Size of the buffer of the data channel (default: 0, no data channel).
+            ///
+            /// Can be customized by setting the `DEFMT_RTT_DATA_BUFFER_SIZE` environment variable.
+            pub(crate) const DATA_BUF_SIZE: usize = {data_size};
+
+            /// Initial mode of the data channel (default: trim).
+            ///
+            /// Can be customized by setting the `DEFMT_RTT_DATA_MODE` environment variable.
+            pub(crate) const DATA_MODE: usize = crate::{data_mode};
+
+            /// What the data channel does with data that doesn't fit, unless it blocks.
+            pub(crate) const DATA_OVERFLOW: crate::channel::Overflow =
+                crate::channel::Overflow::{data_overflow};",
         ),
     )
     .unwrap();
 }
+
+fn buffer_size(var: &str, default: usize) -> usize {
+    println!("cargo:rerun-if-env-changed={var}");
+
+    env::var(var)
+        .map(|s| {
+            s.parse()
+                .unwrap_or_else(|_| panic!("could not parse {var} as usize"))
+        })
+        .unwrap_or(default)
+}
+
+/// Returns the name of the mode constant selected by `var`, and of the `Overflow` variant.
+fn channel_mode(var: &str) -> (&'static str, &'static str) {
+    println!("cargo:rerun-if-env-changed={var}");
+
+    match env::var(var).as_deref() {
+        Ok("block") => ("MODE_BLOCK_IF_FULL", "Overwrite"),
+        Ok("trim") | Err(_) => ("MODE_NON_BLOCKING_TRIM", "Overwrite"),
+        // the host can't tell this from `trim`
+        Ok("drop") => ("MODE_NON_BLOCKING_TRIM", "DropNewest"),
+        Ok(other) => panic!("invalid {var} `{other}`, expected `block`, `trim` or `drop`"),
+    }
+}
@@ -3,7 +3,7 @@ use core::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use crate::{consts::BUF_SIZE, MODE_BLOCK_IF_FULL, MODE_MASK};
+use crate::{MODE_BLOCK_IF_FULL, MODE_MASK};
 
 /// RTT Up channel
 #[repr(C)]
@@ -23,23 +23,28 @@ pub(crate) struct Channel {
 }
 
 impl Channel {
-    pub fn write_all(&self, mut bytes: &[u8]) {
+    /// Writes `bytes`, waiting for the host to make room in blocking mode; otherwise the bytes
+    /// that don't fit into the buffer are handled according to `overflow`.
+    pub fn write_all(&self, mut bytes: &[u8], overflow: Overflow) {
         // the host-connection-status is only modified after RAM initialization while the device is
         // halted, so we only need to check it once before the write-loop
-        let write = match self.host_is_connected() {
-            true => Self::blocking_write,
-            false => Self::nonblocking_write,
-        };
+        let blocking = self.host_is_connected();
 
         while !bytes.is_empty() {
-            let consumed = write(self, bytes);
-            if consumed != 0 {
-                bytes = &bytes[consumed..];
+            let consumed = match (blocking, overflow) {
+                (false, Overflow::Overwrite) => self.overwrite(bytes),
+                _ => self.write_available(bytes),
+            };
+            if consumed == 0 && !blocking {
+                return;
             }
+            bytes = &bytes[consumed..];
         }
     }
 
-    fn blocking_write(&self, bytes: &[u8]) -> usize {
+    /// Writes as much of `bytes` as fits into the contiguous free space of the buffer; returns
+    /// the number of bytes written.
+    fn write_available(&self, bytes: &[u8]) -> usize {
         if bytes.is_empty() {
             return 0;
         }
@@ -47,7 +52,7 @@ impl Channel {
         // calculate how much space is left in the buffer
         let read = self.read.load(Ordering::Relaxed);
         let write = self.write.load(Ordering::Acquire);
-        let available = available_buffer_size(read, write);
+        let available = available_buffer_size(self.size, read, write);
 
         // abort if buffer is full
         if available == 0 {
@@ -57,11 +62,13 @@ impl Channel {
         self.write_impl(bytes, write, available)
     }
 
-    fn nonblocking_write(&self, bytes: &[u8]) -> usize {
+    /// Writes `bytes` regardless of the read cursor, overwriting unread data.
+    fn overwrite(&self, bytes: &[u8]) -> usize {
         let write = self.write.load(Ordering::Acquire);
 
-        // NOTE truncate at BUF_SIZE to avoid more than one "wrap-around" in a single `write` call
-        self.write_impl(bytes, write, BUF_SIZE)
+        // NOTE truncate at the buffer size to avoid more than one "wrap-around" in a single `write`
+        // call
+        self.write_impl(bytes, write, self.size)
     }
 
     fn write_impl(&self, bytes: &[u8], cursor: usize, available: usize) -> usize {
@@ -69,9 +76,9 @@ impl Channel {
 
         // copy `bytes[..len]` to the RTT buffer
         unsafe {
-            if cursor + len > BUF_SIZE {
+            if cursor + len > self.size {
                 // split memcpy
-                let pivot = BUF_SIZE - cursor;
+                let pivot = self.size - cursor;
                 ptr::copy_nonoverlapping(bytes.as_ptr(), self.buffer.add(cursor), pivot);
                 ptr::copy_nonoverlapping(bytes.as_ptr().add(pivot), self.buffer, len - pivot);
             } else {
@@ -82,7 +89,7 @@ impl Channel {
 
         // adjust the write pointer, so the host knows that there is new data
         self.write
-            .store(cursor.wrapping_add(len) % BUF_SIZE, Ordering::Release);
+            .store(cursor.wrapping_add(len) % self.size, Ordering::Release);
 
         // return the number of bytes written
         len
@@ -106,13 +113,90 @@ impl Channel {
     }
 }
 
+/// What a channel that is not in blocking mode does with the data that doesn't fit into its buffer
+// which variants are used depends on `DEFMT_RTT_MODE` and `DEFMT_RTT_DATA_MODE`
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+pub(crate) enum Overflow {
+    /// Overwrite the oldest unread data, so the buffer always holds the newest data
+    Overwrite,
+    /// Drop the new data that doesn't fit, keeping the unread data
+    DropNewest,
+}
+
 /// How much space is left in the buffer?
-fn available_buffer_size(read_cursor: usize, write_cursor: usize) -> usize {
+fn available_buffer_size(size: usize, read_cursor: usize, write_cursor: usize) -> usize {
     if read_cursor > write_cursor {
         read_cursor - write_cursor - 1
     } else if read_cursor == 0 {
-        BUF_SIZE - write_cursor - 1
+        size - write_cursor - 1
     } else {
-        BUF_SIZE - write_cursor
+        size - write_cursor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MODE_NON_BLOCKING_TRIM;
+
+    fn channel(buffer: &mut [u8], mode: usize) -> Channel {
+        Channel {
+            name: ptr::null(),
+            buffer: buffer.as_mut_ptr(),
+            size: buffer.len(),
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            flags: AtomicUsize::new(mode),
+        }
+    }
+
+    #[test]
+    fn overwrite_keeps_the_newest_data() {
+        let mut buffer = [0; 8];
+        let channel = channel(&mut buffer, MODE_NON_BLOCKING_TRIM);
+
+        channel.write_all(&[1, 2, 3, 4, 5], Overflow::Overwrite);
+        channel.write_all(&[6, 7, 8, 9], Overflow::Overwrite);
+        assert_eq!(channel.write.load(Ordering::Relaxed), 1);
+
+        // more than the buffer holds
+        channel.write_all(
+            &[10, 11, 12, 13, 14, 15, 16, 17, 18, 19],
+            Overflow::Overwrite,
+        );
+        assert_eq!(channel.write.load(Ordering::Relaxed), 3);
+
+        assert_eq!(buffer, [17, 18, 19, 12, 13, 14, 15, 16]);
+    }
+
+    #[test]
+    fn drop_newest_drops_what_does_not_fit() {
+        let mut buffer = [0; 8];
+        let channel = channel(&mut buffer, MODE_NON_BLOCKING_TRIM);
+
+        channel.write_all(&[1, 2, 3, 4, 5], Overflow::DropNewest);
+        // one byte is always left free, to tell a full buffer from an empty one
+        channel.write_all(&[6, 7, 8, 9], Overflow::DropNewest);
+        assert_eq!(channel.write.load(Ordering::Relaxed), 7);
+
+        // the host reads 4 bytes; the free space wraps around the end of the buffer
+        channel.read.store(4, Ordering::Relaxed);
+        channel.write_all(&[10, 11, 12, 13, 14], Overflow::DropNewest);
+        assert_eq!(channel.write.load(Ordering::Relaxed), 3);
+
+        assert_eq!(buffer, [11, 12, 13, 4, 5, 6, 7, 10]);
+    }
+
+    #[test]
+    fn drop_newest_when_full() {
+        let mut buffer = [0; 4];
+        let channel = channel(&mut buffer, MODE_NON_BLOCKING_TRIM);
+
+        channel.write_all(&[1, 2, 3], Overflow::DropNewest);
+        channel.write_all(&[4], Overflow::DropNewest);
+        assert_eq!(channel.write.load(Ordering::Relaxed), 3);
+
+        assert_eq!(buffer, [1, 2, 3, 0]);
     }
 }
@@ -18,6 +18,17 @@
 //!
 //! `defmt::flush` would also block forever in that case.
 //!
+//! The initial mode of the channel can be set at build time with the `DEFMT_RTT_MODE` environment
+//! variable: `trim` (the default) overwrites the oldest data if the buffer is full, `drop` drops
+//! the new data that doesn't fit instead, and `block` waits for the host. Note that the host may
+//! still change the mode when it attaches.
+//!
+//! # Data channel
+//!
+//! Setting `DEFMT_RTT_DATA_BUFFER_SIZE` adds a second RTT up channel, named `data`, for raw binary
+//! data written with [`write_data`]. It has its own buffer and its own mode, set with
+//! `DEFMT_RTT_DATA_MODE`, so e.g. the defmt channel can block while the data channel trims.
+//!
 //! # Critical section implementation
 //!
 //! This crate uses [`critical-section`](https://github.com/rust-embedded/critical-section) to ensure only one thread
@@ -31,14 +42,17 @@
 //! cortex-m = { version = "0.7.6", features = ["critical-section-single-core"]}
 //! ```
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 mod channel;
 mod consts;
 
 use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
-use crate::{channel::Channel, consts::BUF_SIZE};
+use crate::{
+    channel::Channel,
+    consts::{BUF_SIZE, DATA_BUF_SIZE, DATA_MODE, DATA_OVERFLOW, MODE, OVERFLOW},
+};
 
 #[defmt::global_logger]
 struct Logger;
@@ -94,7 +108,19 @@ unsafe impl defmt::Logger for Logger {
 }
 
 fn do_write(bytes: &[u8]) {
-    unsafe { handle().write_all(bytes) }
+    unsafe { handle().write_all(bytes, OVERFLOW) }
+}
+
+/// Writes `bytes` to the data channel.
+///
+/// Does nothing unless the data channel was enabled by setting `DEFMT_RTT_DATA_BUFFER_SIZE`.
+pub fn write_data(bytes: &[u8]) {
+    if DATA_BUF_SIZE == 0 {
+        return;
+    }
+
+    // safety: the critical section ensures the data channel is not written re-entrantly
+    critical_section::with(|_| unsafe { header().data_channel.write_all(bytes, DATA_OVERFLOW) })
 }
 
 #[repr(C)]
@@ -103,6 +129,7 @@ struct Header {
     max_up_channels: usize,
     max_down_channels: usize,
     up_channel: Channel,
+    data_channel: Channel,
 }
 
 const MODE_MASK: usize = 0b11;
@@ -116,6 +143,12 @@ const MODE_NON_BLOCKING_TRIM: usize = 1;
 /// `Channel` API is not re-entrant; this handle should not be held from different execution
 /// contexts (e.g. thread-mode, interrupt context)
 unsafe fn handle() -> &'static Channel {
+    &header().up_channel
+}
+
+/// # Safety
+/// See [`handle`]
+unsafe fn header() -> &'static Header {
     // NOTE the `rtt-target` API is too permissive. It allows writing arbitrary data to any
     // channel (`set_print_channel` + `rprint*`) and that can corrupt defmt log frames.
     // So we declare the RTT control block here and make it impossible to use `rtt-target` together
@@ -123,7 +156,8 @@ unsafe fn handle() -> &'static Channel {
     #[no_mangle]
     static mut _SEGGER_RTT: Header = Header {
         id: *b"SEGGER RTT\0\0\0\0\0\0",
-        max_up_channels: 1,
+        // the host only looks at the data channel if it is enabled
+        max_up_channels: if DATA_BUF_SIZE == 0 { 1 } else { 2 },
         max_down_channels: 0,
         up_channel: Channel {
             name: &NAME as *const _ as *const u8,
@@ -131,7 +165,15 @@ unsafe fn handle() -> &'static Channel {
             size: BUF_SIZE,
             write: AtomicUsize::new(0),
             read: AtomicUsize::new(0),
-            flags: AtomicUsize::new(MODE_NON_BLOCKING_TRIM),
+            flags: AtomicUsize::new(MODE),
+        },
+        data_channel: Channel {
+            name: &DATA_NAME as *const _ as *const u8,
+            buffer: unsafe { &mut DATA_BUFFER as *mut _ as *mut u8 },
+            size: DATA_BUF_SIZE,
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            flags: AtomicUsize::new(DATA_MODE),
         },
     };
 
@@ -139,10 +181,20 @@ unsafe fn handle() -> &'static Channel {
     #[cfg_attr(not(target_os = "macos"), link_section = ".uninit.defmt-rtt.BUFFER")]
     static mut BUFFER: [u8; BUF_SIZE] = [0; BUF_SIZE];
 
+    #[cfg_attr(target_os = "macos", link_section = ".uninit,defmt-rtt.DATA_BUFFER")]
+    #[cfg_attr(
+        not(target_os = "macos"),
+        link_section = ".uninit.defmt-rtt.DATA_BUFFER"
+    )]
+    static mut DATA_BUFFER: [u8; DATA_BUF_SIZE] = [0; DATA_BUF_SIZE];
+
     // Place NAME in data section, so the whole RTT header can be read from RAM.
     // This is useful if flash access gets disabled by the firmware at runtime.
     #[link_section = ".data"]
     static NAME: [u8; 6] = *b"defmt\0";
 
-    &_SEGGER_RTT.up_channel
+    #[link_section = ".data"]
+    static DATA_NAME: [u8; 5] = *b"data\0";
+
+    &_SEGGER_RTT
 }
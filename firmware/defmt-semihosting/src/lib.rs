@@ -2,15 +2,17 @@
 //!
 //! NOTE this is meant to only be used with QEMU
 //!
-//! WARNING using `cortex_m_semihosting`'s `hprintln!` macro or `HStdout` API will corrupt `defmt`
-//! log frames so don't use those APIs.
+//! WARNING using `cortex_m_semihosting`'s (or `riscv_semihosting`'s) `hprintln!` macro or `HStdout`
+//! API will corrupt `defmt` log frames so don't use those APIs.
 
 #![no_std]
 
 use core::sync::atomic::{AtomicBool, Ordering};
 
-use cortex_m::{interrupt, register};
+#[cfg(target_arch = "arm")]
 use cortex_m_semihosting::hio;
+#[cfg(target_arch = "riscv32")]
+use riscv_semihosting::hio;
 
 #[defmt::global_logger]
 struct Logger;
@@ -21,8 +23,7 @@ static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
 
 unsafe impl defmt::Logger for Logger {
     fn acquire() {
-        let primask = register::primask::read();
-        interrupt::disable();
+        let interrupts_active = interrupt::disable();
 
         if TAKEN.load(Ordering::Relaxed) {
             panic!("defmt logger taken reentrantly")
@@ -31,7 +32,7 @@ unsafe impl defmt::Logger for Logger {
         // no need for CAS because interrupts are disabled
         TAKEN.store(true, Ordering::Relaxed);
 
-        INTERRUPTS_ACTIVE.store(primask.is_active(), Ordering::Relaxed);
+        INTERRUPTS_ACTIVE.store(interrupts_active, Ordering::Relaxed);
 
         // safety: accessing the `static mut` is OK because we have disabled interrupts.
         unsafe { ENCODER.start_frame(do_write) }
@@ -67,3 +68,36 @@ fn do_write(bytes: &[u8]) {
         hstdout.write_all(bytes).ok();
     }
 }
+
+#[cfg(target_arch = "arm")]
+mod interrupt {
+    use cortex_m::{interrupt, register};
+
+    /// Disables interrupts; returns `true` if they were enabled.
+    pub fn disable() -> bool {
+        let primask = register::primask::read();
+        interrupt::disable();
+        primask.is_active()
+    }
+
+    pub unsafe fn enable() {
+        interrupt::enable()
+    }
+}
+
+#[cfg(target_arch = "riscv32")]
+mod interrupt {
+    use riscv::{interrupt, register::mstatus};
+
+    /// Disables (machine mode) interrupts; returns `true` if they were enabled.
+    pub fn disable() -> bool {
+        let enabled = mstatus::read().mie();
+        // safety: interrupts are only disabled, which can't break any critical section
+        unsafe { interrupt::disable() };
+        enabled
+    }
+
+    pub unsafe fn enable() {
+        interrupt::enable()
+    }
+}
@@ -5,7 +5,7 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::{format_ident, quote, quote_spanned};
-use syn::{parse, spanned::Spanned, Attribute, Item, ItemFn, ItemMod, ReturnType, Type};
+use syn::{parse, spanned::Spanned, Attribute, Item, ItemFn, ItemMod, LitStr, ReturnType, Type};
 
 #[proc_macro_attribute]
 pub fn tests(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -45,6 +45,8 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                 let mut test_kind = None;
                 let mut should_error = false;
                 let mut ignore = false;
+                let mut allow_leaks = false;
+                let mut tag_attrs = vec![];
 
                 f.attrs.retain(|attr| {
                     if attr.path.is_ident("init") {
@@ -65,6 +67,12 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                     } else if attr.path.is_ident("ignore") {
                         ignore = true;
                         false
+                    } else if attr.path.is_ident("allow_leaks") {
+                        allow_leaks = true;
+                        false
+                    } else if attr.path.is_ident("tag") {
+                        tag_attrs.push(attr.clone());
+                        false
                     } else {
                         true
                     }
@@ -80,6 +88,15 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                     }
                 };
 
+                let tags = tag_attrs
+                    .iter()
+                    .map(|attr| {
+                        attr.parse_args::<LitStr>().map_err(|_| {
+                            parse::Error::new(attr.tokens.span(), "expected `#[tag(\"name\")]`")
+                        })
+                    })
+                    .collect::<parse::Result<Vec<_>>>()?;
+
                 match attr {
                     Attr::Init => {
                         if init.is_some() {
@@ -103,6 +120,20 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                             ));
                         }
 
+                        if allow_leaks {
+                            return Err(parse::Error::new(
+                                f.sig.ident.span(),
+                                "`#[allow_leaks]` is not allowed on the `#[init]` function",
+                            ));
+                        }
+
+                        if !tags.is_empty() {
+                            return Err(parse::Error::new(
+                                f.sig.ident.span(),
+                                "`#[tag]` is not allowed on the `#[init]` function",
+                            ));
+                        }
+
                         if check_fn_sig(&f.sig).is_err() || !f.sig.inputs.is_empty() {
                             return Err(parse::Error::new(
                                 f.sig.ident.span(),
@@ -150,6 +181,8 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                             input,
                             should_error,
                             ignore,
+                            allow_leaks,
+                            tags,
                         })
                     }
                     Attr::BeforeEach => {
@@ -174,6 +207,20 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                             ));
                         }
 
+                        if allow_leaks {
+                            return Err(parse::Error::new(
+                                f.sig.ident.span(),
+                                "`#[allow_leaks]` is not allowed on the `#[before_each]` function",
+                            ));
+                        }
+
+                        if !tags.is_empty() {
+                            return Err(parse::Error::new(
+                                f.sig.ident.span(),
+                                "`#[tag]` is not allowed on the `#[before_each]` function",
+                            ));
+                        }
+
                         if check_fn_sig(&f.sig).is_err() || f.sig.inputs.len() > 1 {
                             return Err(parse::Error::new(
                                 f.sig.ident.span(),
@@ -223,6 +270,20 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                             ));
                         }
 
+                        if allow_leaks {
+                            return Err(parse::Error::new(
+                                f.sig.ident.span(),
+                                "`#[allow_leaks]` is not allowed on the `#[after_each]` function",
+                            ));
+                        }
+
+                        if !tags.is_empty() {
+                            return Err(parse::Error::new(
+                                f.sig.ident.span(),
+                                "`#[tag]` is not allowed on the `#[after_each]` function",
+                            ));
+                        }
+
                         if check_fn_sig(&f.sig).is_err() || f.sig.inputs.len() > 1 {
                             return Err(parse::Error::new(
                                 f.sig.ident.span(),
@@ -345,6 +406,7 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
     for test in &tests {
         let should_error = test.should_error;
         let ignore = test.ignore;
+        let allow_leaks = test.allow_leaks;
         let ident = &test.func.sig.ident;
         let span = test.func.sig.ident.span();
         let call = if let Some(input) = test.input.as_ref() {
@@ -372,10 +434,34 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
         if ignore {
             unit_test_calls.push(quote!(let _ = #call;));
         } else {
+            let name = ident.to_string();
+            // spanned so that the caller location of a failure is the test function
+            let check_outcome = quote_spanned!(span=>
+                #krate::export::check_outcome(
+                    __defmt_test_outcome,
+                    #should_error,
+                    __defmt_test_cycles,
+                )
+            );
             unit_test_calls.push(quote!(
                 #before_each_call;
-                #krate::export::check_outcome(#call, #should_error);
+                // always 0 unless the `alloc` feature is enabled
+                let __defmt_test_heap = #krate::export::heap_in_use();
+                let __defmt_test_start = #krate::export::cycle_count();
+                let __defmt_test_outcome = #call;
+                let __defmt_test_cycles =
+                    #krate::export::cycle_count().wrapping_sub(__defmt_test_start);
+                #check_outcome;
+                // no-op unless the `alloc` feature is enabled
+                #krate::export::check_heap(__defmt_test_heap, #allow_leaks);
                 #after_each_call;
+                #krate::export::report_stack_usage();
+                #krate::export::report_passed(
+                    __defmt_test_number,
+                    DEFMT_TEST_COUNT,
+                    defmt::intern!(#name),
+                    __defmt_test_cycles,
+                );
             ));
         }
     }
@@ -412,6 +498,18 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
             }
         })
         .collect::<Vec<_>>();
+    let unit_test_selected = tests
+        .iter()
+        .map(|test| {
+            // ignored tests are announced as such, whatever the selection
+            if test.ignore {
+                return quote!(true);
+            }
+            let tags = &test.tags;
+            quote!(#krate::export::is_selected(&[#(#tags),*]))
+        })
+        .collect::<Vec<_>>();
+    let test_names = tests.iter().map(|test| test.func.sig.ident.to_string());
     Ok(quote!(
     #[cfg(test)]
     mod #ident {
@@ -420,18 +518,33 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
         #[export_name = "main"]
         unsafe extern "C" fn __defmt_test_entry() -> ! {
             #declare_test_count
+            // no-op unless the `stack-usage` feature is enabled
+            #krate::export::paint_stack();
+            // no-op unless the `cycle-count` feature is enabled
+            #krate::export::start_cycle_counter();
+            // no-op unless the `tag-filter` feature is enabled
+            #krate::export::read_tag_filter();
             #init_expr
 
             let mut __defmt_test_number: usize = 1;
             #(
                 #(#test_cfgs)*
                 {
-                    #unit_test_progress
-                    #unit_test_calls
+                    if #unit_test_selected {
+                        #unit_test_progress
+                        #unit_test_calls
+                    } else {
+                        #krate::export::report_skipped(
+                            __defmt_test_number,
+                            DEFMT_TEST_COUNT,
+                            defmt::intern!(#test_names),
+                        );
+                    }
                     __defmt_test_number += 1;
                 }
             )*
 
+            #krate::export::report_max_stack_usage();
             defmt::println!("all tests passed!");
             #krate::export::exit()
         }
@@ -478,6 +591,8 @@ struct Test {
     input: Option<Input>,
     should_error: bool,
     ignore: bool,
+    allow_leaks: bool,
+    tags: Vec<LitStr>,
 }
 
 struct Input {
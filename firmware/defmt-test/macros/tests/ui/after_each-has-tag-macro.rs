@@ -0,0 +1,8 @@
+fn main() {}
+
+#[defmt_test_macros::tests]
+mod tests {
+    #[after_each]
+    #[tag("slow")]
+    fn init() {}
+}
@@ -0,0 +1,8 @@
+fn main() {}
+
+#[defmt_test_macros::tests]
+mod tests {
+    #[before_each]
+    #[tag("slow")]
+    fn init() {}
+}
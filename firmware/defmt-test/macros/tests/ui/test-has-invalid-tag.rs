@@ -0,0 +1,8 @@
+fn main() {}
+
+#[defmt_test_macros::tests]
+mod tests {
+    #[test]
+    #[tag(slow)]
+    fn slow_test() {}
+}
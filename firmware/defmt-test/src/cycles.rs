@@ -0,0 +1,25 @@
+//! Test duration measurement, enabled by the `cycle-count` feature.
+//!
+//! The duration is measured with the cycle counter of the DWT unit, which is not available on
+//! ARMv6-M (Cortex-M0/M0+) devices.
+//!
+//! The `defmt::timestamp!` source can't be used instead: it only encodes the timestamp into the
+//! log frames, there is no way to read its value on the device.
+
+use cortex_m::peripheral::{DCB, DWT};
+
+/// Enables the cycle counter.
+pub fn start_cycle_counter() {
+    // SAFETY: the DCB and DWT are only used to enable the cycle counter, which the tests under
+    // measurement are not expected to reconfigure
+    let mut peripherals = unsafe { cortex_m::Peripherals::steal() };
+    peripherals.DCB.enable_trace();
+    peripherals.DWT.enable_cycle_counter();
+    // make sure the write to `DEMCR` has completed before the counter is read
+    let _ = DCB::is_debugger_attached();
+}
+
+/// Returns the current value of the cycle counter.
+pub fn cycle_count() -> u32 {
+    DWT::cycle_count()
+}
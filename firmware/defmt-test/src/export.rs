@@ -3,19 +3,121 @@ pub use defmt::info;
 
 use crate::TestOutcome;
 
+#[cfg(feature = "cycle-count")]
+pub use crate::cycles::{cycle_count, start_cycle_counter};
+#[cfg(feature = "alloc")]
+pub use crate::heap::{check_heap, heap_in_use};
+#[cfg(feature = "stack-usage")]
+pub use crate::stack::{paint_stack, report_max_stack_usage, report_stack_usage};
+#[cfg(feature = "tag-filter")]
+pub use crate::tags::{is_selected, read_tag_filter};
+
+/// No-op; enable the `cycle-count` feature to measure the duration of the tests.
+#[cfg(not(feature = "cycle-count"))]
+#[inline(always)]
+pub fn start_cycle_counter() {}
+
+/// Always 0; enable the `cycle-count` feature to measure the duration of the tests.
+#[cfg(not(feature = "cycle-count"))]
+#[inline(always)]
+pub fn cycle_count() -> u32 {
+    0
+}
+
+/// No-op; enable the `stack-usage` feature to measure the stack usage of the tests.
+#[cfg(not(feature = "stack-usage"))]
+#[inline(always)]
+pub fn paint_stack() {}
+
+/// No-op; enable the `stack-usage` feature to measure the stack usage of the tests.
+#[cfg(not(feature = "stack-usage"))]
+#[inline(always)]
+pub fn report_stack_usage() {}
+
+/// No-op; enable the `stack-usage` feature to measure the stack usage of the tests.
+#[cfg(not(feature = "stack-usage"))]
+#[inline(always)]
+pub fn report_max_stack_usage() {}
+
+/// Always 0; enable the `alloc` feature to detect heap leaks.
+#[cfg(not(feature = "alloc"))]
+#[inline(always)]
+pub fn heap_in_use() -> usize {
+    0
+}
+
+/// No-op; enable the `alloc` feature to detect heap leaks.
+#[cfg(not(feature = "alloc"))]
+#[inline(always)]
+pub fn check_heap(_before: usize, _allow_leaks: bool) {}
+
+/// No-op; enable the `tag-filter` feature to select the tests to run by tag.
+#[cfg(not(feature = "tag-filter"))]
+#[inline(always)]
+pub fn read_tag_filter() {}
+
+/// Always `true`; enable the `tag-filter` feature to select the tests to run by tag.
+#[cfg(not(feature = "tag-filter"))]
+#[inline(always)]
+pub fn is_selected(_tags: &[&str]) -> bool {
+    true
+}
+
 pub fn exit() -> ! {
     loop {
         cortex_m::asm::bkpt()
     }
 }
 
-pub fn check_outcome<T: TestOutcome>(outcome: T, should_error: bool) {
+/// Panics if the test failed; with the `caller-location` feature of `defmt`, the message points at
+/// the test function.
+#[track_caller]
+pub fn check_outcome<T: TestOutcome>(outcome: T, should_error: bool, cycles: u32) {
     if outcome.is_success() == should_error {
         let note = if should_error {
             defmt::intern!("`#[should_error]` ")
         } else {
             defmt::intern!("")
         };
-        defmt::panic!("{}test failed with outcome: {}", note, outcome);
+        if cfg!(feature = "cycle-count") {
+            defmt::panic!(
+                "{}test failed after {=u32} cycles with outcome: {}",
+                note,
+                cycles,
+                outcome
+            );
+        } else {
+            defmt::panic!("{}test failed with outcome: {}", note, outcome);
+        }
     }
 }
+
+/// Reports that a test passed, and how many cycles it took.
+///
+/// The timestamp of this frame, compared to that of the frame announcing the test, gives its wall
+/// time.
+#[cfg(feature = "cycle-count")]
+pub fn report_passed(number: usize, count: usize, name: defmt::Str, cycles: u32) {
+    defmt::println!(
+        "({=usize}/{=usize}) `{=istr}` passed in {=u32} cycles",
+        number,
+        count,
+        name,
+        cycles
+    );
+}
+
+/// No-op; enable the `cycle-count` feature to report each passed test with its duration.
+#[cfg(not(feature = "cycle-count"))]
+#[inline(always)]
+pub fn report_passed(_number: usize, _count: usize, _name: defmt::Str, _cycles: u32) {}
+
+/// Reports that a test was skipped because the tag filter didn't select it.
+pub fn report_skipped(number: usize, count: usize, name: defmt::Str) {
+    defmt::println!(
+        "({=usize}/{=usize}) skipping `{=istr}` (not selected by tag)",
+        number,
+        count,
+        name
+    );
+}
@@ -0,0 +1,83 @@
+//! Heap-leak detection, enabled by the `alloc` feature.
+//!
+//! [`CountingAllocator`] keeps track of the number of bytes allocated on the heap. The harness
+//! compares it before and after each test; a test that returns with more memory allocated than
+//! before has leaked it.
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+};
+
+use cortex_m::interrupt::{self, Mutex};
+
+static IN_USE: Mutex<Cell<usize>> = Mutex::new(Cell::new(0));
+
+/// A global allocator wrapper that lets `defmt-test` detect heap leaks.
+///
+/// ```ignore
+/// #[global_allocator]
+/// static HEAP: defmt_test::CountingAllocator<Heap> = defmt_test::CountingAllocator::new(Heap::empty());
+/// ```
+pub struct CountingAllocator<A> {
+    allocator: A,
+}
+
+impl<A> CountingAllocator<A> {
+    /// Wraps `allocator`.
+    pub const fn new(allocator: A) -> Self {
+        Self { allocator }
+    }
+
+    /// Returns the wrapped allocator, e.g. to initialize it.
+    pub const fn inner(&self) -> &A {
+        &self.allocator
+    }
+}
+
+fn update(f: impl FnOnce(usize) -> usize) {
+    interrupt::free(|cs| {
+        let in_use = IN_USE.borrow(cs);
+        in_use.set(f(in_use.get()));
+    })
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.allocator.alloc(layout);
+        if !ptr.is_null() {
+            update(|in_use| in_use + layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.allocator.dealloc(ptr, layout);
+        update(|in_use| in_use - layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.allocator.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            update(|in_use| in_use - layout.size() + new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Returns the number of bytes currently allocated through [`CountingAllocator`].
+pub fn heap_in_use() -> usize {
+    interrupt::free(|cs| IN_USE.borrow(cs).get())
+}
+
+/// Fails the test (or, with `allow_leaks`, warns) if the heap grew since `before` was taken.
+pub fn check_heap(before: usize, allow_leaks: bool) {
+    let after = heap_in_use();
+    if after > before {
+        if allow_leaks {
+            defmt::println!("warning: test leaked {=usize} bytes", after - before);
+        } else {
+            defmt::panic!("test leaked {=usize} bytes", after - before);
+        }
+    }
+}
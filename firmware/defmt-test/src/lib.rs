@@ -7,14 +7,24 @@
 //! - <https://github.com/knurling-rs/defmt/tree/main/firmware/defmt-test> (git version)
 
 #![doc(html_logo_url = "https://knurling.ferrous-systems.com/knurling_logo_light_text.svg")]
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 use defmt::Format;
 pub use defmt_test_macros::tests;
+#[cfg(feature = "alloc")]
+pub use heap::CountingAllocator;
 
+#[cfg(feature = "cycle-count")]
+mod cycles;
 /// Private implementation details used by the proc macro.
 #[doc(hidden)]
 pub mod export;
+#[cfg(feature = "alloc")]
+mod heap;
+#[cfg(feature = "stack-usage")]
+mod stack;
+#[cfg(feature = "tag-filter")]
+mod tags;
 
 mod sealed {
     pub trait Sealed {}
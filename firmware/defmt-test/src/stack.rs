@@ -0,0 +1,114 @@
+//! Stack high-water-mark measurement, enabled by the `stack-usage` feature.
+//!
+//! The unused part of the stack is "painted" with a known pattern before the `#[init]` function
+//! runs. After each test the stack is scanned from its end (lowest address) upwards; the first
+//! word that no longer holds the pattern marks the deepest point the stack has grown to.
+//!
+//! Reporting the high-water mark uses stack too, below the frame of the test runner. That part is
+//! painted again after each report, so that it isn't counted as used by the next test; only the
+//! frame of [`paint_stack`] itself, a few words, is included in the reported values.
+
+use core::{
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Pattern written to every word of the unused stack.
+const PAINT: u32 = 0xCCCC_CCCC;
+
+/// Highest high-water mark measured so far, in bytes
+static MAX_USAGE: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" {
+    /// Start (highest address) of the stack; provided by `cortex-m-rt`.
+    static _stack_start: u32;
+    /// End (lowest address) of the stack; provided by `cortex-m-rt`.
+    static _stack_end: u32;
+}
+
+fn stack_start() -> usize {
+    // SAFETY: only the address of the linker symbol is taken, it is never read
+    unsafe { &_stack_start as *const u32 as usize }
+}
+
+fn stack_end() -> usize {
+    // SAFETY: only the address of the linker symbol is taken, it is never read
+    unsafe { &_stack_end as *const u32 as usize }
+}
+
+/// Fills the stack between its end and the current stack pointer with [`PAINT`].
+#[inline(never)]
+pub fn paint_stack() {
+    let sp = cortex_m::register::msp::read() as usize;
+
+    let mut addr = stack_end();
+    while addr < sp {
+        // SAFETY: `[stack_end, sp)` is unused stack memory below this function's frame
+        unsafe { ptr::write_volatile(addr as *mut u32, PAINT) };
+        addr += 4;
+    }
+}
+
+/// Returns the number of bytes of stack that have been used since [`paint_stack`] was called.
+#[inline(always)]
+fn high_water_mark() -> usize {
+    used((stack_end()..stack_start()).step_by(4).map(|addr| {
+        // SAFETY: `[stack_end, stack_start)` is the stack region defined by `cortex-m-rt`
+        unsafe { ptr::read_volatile(addr as *const u32) }
+    }))
+}
+
+/// Returns the number of bytes above the lowest word of `stack` that no longer holds [`PAINT`].
+///
+/// `stack` yields the words of the stack from its end (lowest address) upwards.
+fn used(stack: impl ExactSizeIterator<Item = u32>) -> usize {
+    let len = stack.len();
+    let unused = stack.take_while(|&word| word == PAINT).count();
+    (len - unused) * 4
+}
+
+/// Reports the stack high-water mark reached so far.
+///
+/// Inlined into the test runner, so that the stack it uses is painted again before the next test.
+#[inline(always)]
+pub fn report_stack_usage() {
+    let usage = high_water_mark().max(MAX_USAGE.load(Ordering::Relaxed));
+    MAX_USAGE.store(usage, Ordering::Relaxed);
+
+    defmt::println!(
+        "stack high-water mark: {=usize} of {=usize} bytes",
+        usage,
+        stack_start() - stack_end()
+    );
+    paint_stack();
+}
+
+/// Reports the stack high-water mark reached by the whole test suite.
+pub fn report_max_stack_usage() {
+    defmt::println!(
+        "maximum stack usage: {=usize} of {=usize} bytes",
+        MAX_USAGE.load(Ordering::Relaxed),
+        stack_start() - stack_end()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unused_stack() {
+        assert_eq!(used([PAINT; 4].into_iter()), 0);
+    }
+
+    #[test]
+    fn counts_from_the_lowest_overwritten_word() {
+        assert_eq!(used([PAINT, PAINT, 0, PAINT].into_iter()), 8);
+        assert_eq!(used([0, PAINT, PAINT, PAINT].into_iter()), 16);
+    }
+
+    #[test]
+    fn empty_stack() {
+        assert_eq!(used([].into_iter()), 0);
+    }
+}
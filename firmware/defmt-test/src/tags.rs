@@ -0,0 +1,60 @@
+//! Selection of tests by tag, enabled by the `tag-filter` feature.
+//!
+//! The filter is read from the semihosting command line, whose words may include
+//! `tags=TAG[,TAG..]`, to only run tests with one of the given tags, and `skip-tags=TAG[,TAG..]`,
+//! to skip tests with one of the given tags. Other words, like the program name, are ignored.
+//!
+//! Reading the command line is a semihosting operation, i.e. a breakpoint instruction, which
+//! causes a HardFault if no debugger is attached. That's why this is behind a feature.
+
+use core::{
+    ptr::{addr_of, addr_of_mut},
+    slice, str,
+};
+
+use cortex_m_semihosting::{nr, syscall1};
+
+const COMMAND_LINE_CAPACITY: usize = 256;
+
+static mut COMMAND_LINE: [u8; COMMAND_LINE_CAPACITY] = [0; COMMAND_LINE_CAPACITY];
+static mut COMMAND_LINE_LEN: usize = 0;
+
+/// Reads the semihosting command line; must be called before the tests run.
+///
+/// HardFaults if no debugger is attached.
+pub fn read_tag_filter() {
+    // SAFETY: called once, before any test runs, so nothing else accesses the `static mut`s
+    unsafe {
+        let mut block = [addr_of_mut!(COMMAND_LINE) as usize, COMMAND_LINE_CAPACITY];
+        // the host overwrites the length with that of the command line
+        if syscall1(nr::GET_CMDLINE, block.as_mut_ptr() as usize) == 0 {
+            COMMAND_LINE_LEN = block[1].min(COMMAND_LINE_CAPACITY);
+        }
+    }
+}
+
+/// Returns `true` if a test with the given `tags` is selected by the filter.
+pub fn is_selected(tags: &[&str]) -> bool {
+    let mut included = None;
+    for word in command_line().split_ascii_whitespace() {
+        if let Some(list) = word.strip_prefix("tags=") {
+            included = Some(included.unwrap_or(false) || intersects(list, tags));
+        } else if let Some(list) = word.strip_prefix("skip-tags=") {
+            if intersects(list, tags) {
+                return false;
+            }
+        }
+    }
+    included.unwrap_or(true)
+}
+
+fn command_line() -> &'static str {
+    // SAFETY: only written by `read_tag_filter`, before the tests run
+    let bytes =
+        unsafe { slice::from_raw_parts(addr_of!(COMMAND_LINE) as *const u8, COMMAND_LINE_LEN) };
+    str::from_utf8(bytes).unwrap_or("")
+}
+
+fn intersects(list: &str, tags: &[&str]) -> bool {
+    list.split(',').any(|tag| tags.contains(&tag))
+}
@@ -6,12 +6,17 @@ use std::path::PathBuf;
 fn main() {
     // Put the linker script somewhere the linker can find it
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let memory_x: &[u8] = match env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+        Ok("riscv32") => include_bytes!("memory-riscv.x"),
+        _ => include_bytes!("memory.x"),
+    };
     File::create(out.join("memory.x"))
         .unwrap()
-        .write_all(include_bytes!("memory.x"))
+        .write_all(memory_x)
         .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=memory-riscv.x");
 }
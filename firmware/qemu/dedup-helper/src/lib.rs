@@ -0,0 +1,14 @@
+//! Interns the same strings as the `dedup` snapshot test, from a second crate.
+
+#![no_std]
+
+/// Returns the interned string `"shared"`
+pub fn shared() -> defmt::Str {
+    defmt::intern!("shared")
+}
+
+/// Same name and fields as the struct of the `dedup` snapshot test, so it derives the same string
+#[derive(defmt::Format)]
+pub struct Sample {
+    pub x: u8,
+}
@@ -0,0 +1,12 @@
+/* QEMU `virt` machine; `qemu-run` loads the program straight into RAM */
+MEMORY
+{
+  RAM : ORIGIN = 0x80000000, LENGTH = 16M
+}
+
+REGION_ALIAS("REGION_TEXT", RAM);
+REGION_ALIAS("REGION_RODATA", RAM);
+REGION_ALIAS("REGION_DATA", RAM);
+REGION_ALIAS("REGION_BSS", RAM);
+REGION_ALIAS("REGION_HEAP", RAM);
+REGION_ALIAS("REGION_STACK", RAM);
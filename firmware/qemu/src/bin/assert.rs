@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 
-use cortex_m_rt::entry;
+use firmware::entry;
 
 use defmt_semihosting as _; // global logger
 
@@ -20,7 +20,7 @@ fn main() -> ! {
 #[cfg(target_os = "none")]
 #[panic_handler]
 fn panic(_: &core::panic::PanicInfo) -> ! {
-    use cortex_m_semihosting::debug;
+    use firmware::debug;
 
     loop {
         debug::exit(debug::EXIT_SUCCESS)
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+use firmware::{debug, entry};
+
+use defmt_semihosting as _; // global logger
+
+#[derive(defmt::Format)]
+struct Sample {
+    x: u8,
+}
+
+// with the `dedup-helper` feature, the same strings are also interned by the `dedup-helper` crate;
+// `xtask` checks that this changes neither the output nor the size of the table
+#[cfg(feature = "dedup-helper")]
+use dedup_helper::{shared, Sample as OtherSample};
+
+#[cfg(not(feature = "dedup-helper"))]
+use self::Sample as OtherSample;
+
+#[cfg(not(feature = "dedup-helper"))]
+fn shared() -> defmt::Str {
+    defmt::intern!("shared")
+}
+
+#[entry]
+fn main() -> ! {
+    defmt::info!("{=istr}", defmt::intern!("shared"));
+    defmt::info!("{=istr}", shared());
+    defmt::info!("{}", Sample { x: 42 });
+    defmt::info!("{}", OtherSample { x: 42 });
+
+    loop {
+        debug::exit(debug::EXIT_SUCCESS)
+    }
+}
+
+// like `panic-semihosting` but doesn't print to stdout (that would corrupt the defmt stream)
+#[cfg(target_os = "none")]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        debug::exit(debug::EXIT_FAILURE)
+    }
+}
@@ -3,8 +3,7 @@
 
 use core::sync::atomic::{AtomicU32, Ordering};
 
-use cortex_m_rt::entry;
-use cortex_m_semihosting::debug;
+use firmware::{debug, entry};
 
 use defmt::{intern, write, Format, Formatter};
 use defmt_semihosting as _; // global logger
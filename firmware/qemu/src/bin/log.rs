@@ -2,9 +2,8 @@
 #![no_main]
 
 use core::{marker::PhantomData, num};
-use cortex_m_rt::entry;
-use cortex_m_semihosting::debug;
 use defmt::{Debug2Format, Display2Format, Format, Formatter};
+use firmware::{debug, entry};
 
 use defmt_semihosting as _; // global logger
 
@@ -6,8 +6,7 @@ use core::net::{
     AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6,
 };
 
-use cortex_m_rt::entry;
-use cortex_m_semihosting::debug;
+use firmware::{debug, entry};
 
 use defmt_semihosting as _; // global logger
 
@@ -1,9 +1,8 @@
 #![no_std]
 #![no_main]
 
-use cortex_m_rt::entry;
-use cortex_m_semihosting::debug;
 use defmt::{write, Format, Formatter};
+use firmware::{debug, entry};
 
 use defmt_semihosting as _; // global logger
 
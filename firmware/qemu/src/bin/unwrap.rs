@@ -1,8 +1,7 @@
 #![no_std]
 #![no_main]
 
-use cortex_m_rt::entry;
-use cortex_m_semihosting::debug;
+use firmware::{debug, entry};
 
 use defmt_semihosting as _; // global logger
 
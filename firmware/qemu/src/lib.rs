@@ -0,0 +1,11 @@
+//! The runtime and semihosting crates of the target the snapshot tests are built for.
+//!
+//! The tests run on ARM (`thumbv7m-none-eabi`) as well as RISC-V (`riscv32imac-unknown-none-elf`);
+//! they use `entry` and `debug` from here instead of the target-specific crates.
+
+#![no_std]
+
+#[cfg(target_arch = "arm")]
+pub use {cortex_m_rt::entry, cortex_m_semihosting::debug};
+#[cfg(target_arch = "riscv32")]
+pub use {riscv_rt::entry, riscv_semihosting::debug};
@@ -40,6 +40,7 @@ mod tests {
     }
 
     #[test]
+    #[tag("smoke")]
     fn assert_true() -> () {
         assert!(true);
     }
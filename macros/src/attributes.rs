@@ -2,3 +2,4 @@
 
 pub(crate) mod global_logger;
 pub(crate) mod panic_handler;
+pub(crate) mod trace_fn;
@@ -0,0 +1,223 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use proc_macro_error::abort;
+use quote::quote;
+use syn::{
+    parse::{self, Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    visit_mut::{self, VisitMut},
+    Expr, FnArg, Ident, Item, ItemFn, Pat, ReturnType, Token, Type,
+};
+
+pub(crate) fn expand(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as Args);
+    let fun = parse_macro_input!(item as ItemFn);
+
+    validate(&fun, &args);
+
+    codegen(&fun, &args).into()
+}
+
+/// `skip(arg, ..)` and `skip_return`, in any order
+#[derive(Default)]
+struct Args {
+    skip: Vec<Ident>,
+    skip_return: bool,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> parse::Result<Self> {
+        let mut args = Args::default();
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            if ident == "skip" {
+                let content;
+                syn::parenthesized!(content in input);
+                let skip = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+                args.skip.extend(skip);
+            } else if ident == "skip_return" {
+                args.skip_return = true;
+            } else {
+                return Err(parse::Error::new(
+                    ident.span(),
+                    "expected `skip(..)` or `skip_return`",
+                ));
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn validate(fun: &ItemFn, args: &Args) {
+    if fun.sig.constness.is_some() {
+        abort!(
+            fun.sig.constness,
+            "`#[defmt::trace_fn]` cannot be used on `const` functions"
+        );
+    }
+    if fun.sig.asyncness.is_some() {
+        abort!(
+            fun.sig.asyncness,
+            "`#[defmt::trace_fn]` cannot be used on `async` functions"
+        );
+    }
+
+    for skipped in &args.skip {
+        if !arg_idents(fun).any(|ident| ident == skipped) {
+            abort!(skipped, "no argument named `{}`", skipped);
+        }
+    }
+}
+
+/// The arguments bound to a plain identifier; `self` and destructuring patterns are not logged.
+fn arg_idents(fun: &ItemFn) -> impl Iterator<Item = &Ident> {
+    fun.sig.inputs.iter().filter_map(|arg| match arg {
+        FnArg::Typed(arg) => match &*arg.pat {
+            Pat::Ident(pat) => Some(&pat.ident),
+            _ => None,
+        },
+        FnArg::Receiver(_) => None,
+    })
+}
+
+fn codegen(fun: &ItemFn, args: &Args) -> TokenStream2 {
+    let name = fun.sig.ident.to_string();
+    let logged = arg_idents(fun)
+        .filter(|ident| !args.skip.contains(ident))
+        .collect::<Vec<_>>();
+
+    let params = logged
+        .iter()
+        .map(|ident| format!("{ident}: {{}}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let entry = format!("→ {name}({params})");
+    let exit_unit = format!("← {name}");
+    let exit_value = format!("← {name} = {{}}");
+
+    let body = body(fun);
+    let (call, exit) = match &fun.sig.output {
+        ReturnType::Default => (quote!(#body;), quote!(defmt::trace!(#exit_unit);)),
+        ReturnType::Type(_, ty) => {
+            // `impl Trait` can't be written in a `let` statement; let inference figure it out
+            let mut ty = (**ty).clone();
+            InferImplTrait.visit_type_mut(&mut ty);
+            let exit = match args.skip_return {
+                true => quote!(defmt::trace!(#exit_unit);),
+                false => quote!(defmt::trace!(#exit_value, __defmt_trace_fn_ret);),
+            };
+            (
+                quote!(let __defmt_trace_fn_ret: #ty = #body;),
+                quote!(#exit __defmt_trace_fn_ret),
+            )
+        }
+    };
+    let attrs = &fun.attrs;
+    let vis = &fun.vis;
+    let sig = &fun.sig;
+    quote!(
+        #(#attrs)*
+        #vis #sig {
+            defmt::trace!(#entry #(, #logged)*);
+            #call
+            #exit
+        }
+    )
+}
+
+/// The body of `fun` as an expression whose `return`s and `?`s pass through the exit frame.
+///
+/// They are turned into `break`s out of a labeled block. A closure would lose the caller location
+/// of `#[track_caller]` functions, and can't return a reborrow of a `&mut` argument.
+fn body(fun: &ItemFn) -> TokenStream2 {
+    let mut block = (*fun.block).clone();
+    ReturnToBreak {
+        output: Output::of(&fun.sig.output),
+    }
+    .visit_block_mut(&mut block);
+    quote!('__defmt_trace_fn: #block)
+}
+
+/// Replaces `impl Trait`, at any depth of a type, with `_`.
+struct InferImplTrait;
+
+impl VisitMut for InferImplTrait {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        match ty {
+            Type::ImplTrait(_) => *ty = parse_quote!(_),
+            _ => visit_mut::visit_type_mut(self, ty),
+        }
+    }
+}
+
+/// What `?` converts the error into, depending on the return type of the function
+enum Output {
+    Result,
+    Option,
+    Other,
+}
+
+impl Output {
+    fn of(output: &ReturnType) -> Self {
+        let ReturnType::Type(_, ty) = output else {
+            return Output::Other;
+        };
+        let Type::Path(path) = &**ty else {
+            return Output::Other;
+        };
+        match path.path.segments.last() {
+            Some(segment) if segment.ident == "Result" => Output::Result,
+            Some(segment) if segment.ident == "Option" => Output::Option,
+            _ => Output::Other,
+        }
+    }
+}
+
+/// Turns the `return`s and `?`s of a function body into `break`s out of `'__defmt_trace_fn`.
+struct ReturnToBreak {
+    output: Output,
+}
+
+impl VisitMut for ReturnToBreak {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            // their `return`s and `?`s don't leave the function
+            Expr::Closure(_) | Expr::Async(_) => {}
+            Expr::Return(ret) => {
+                visit_mut::visit_expr_return_mut(self, ret);
+                let value = &ret.expr;
+                *expr = parse_quote!(break '__defmt_trace_fn #value);
+            }
+            Expr::Try(try_) => {
+                visit_mut::visit_expr_try_mut(self, try_);
+                let value = &try_.expr;
+                *expr = match self.output {
+                    Output::Result => parse_quote!(match #value {
+                        ::core::result::Result::Ok(value) => value,
+                        ::core::result::Result::Err(error) => break '__defmt_trace_fn
+                            ::core::result::Result::Err(::core::convert::From::from(error)),
+                    }),
+                    Output::Option => parse_quote!(match #value {
+                        ::core::option::Option::Some(value) => value,
+                        ::core::option::Option::None => break '__defmt_trace_fn
+                            ::core::option::Option::None,
+                    }),
+                    Output::Other => abort!(
+                        try_.question_token,
+                        "`#[defmt::trace_fn]` only supports `?` in functions returning `Result` \
+                         or `Option`"
+                    ),
+                };
+            }
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    // nested items have `return`s of their own
+    fn visit_item_mut(&mut self, _: &mut Item) {}
+}
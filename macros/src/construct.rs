@@ -8,7 +8,7 @@ use proc_macro2::{Ident as Ident2, Span as Span2, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
 use syn::{parse_quote, Expr, Ident, LitStr};
 
-pub(crate) use symbol::mangled as mangled_symbol_name;
+pub(crate) use symbol::{deduplicated as deduplicated_symbol_name, mangled as mangled_symbol_name};
 
 mod symbol;
 
@@ -38,6 +38,12 @@ pub(crate) fn interned_string(string: &str, tag: &str, is_log_statement: bool) -
 
     let var_addr = if cfg!(feature = "unstable-test") {
         quote!({ defmt::export::fetch_add_string_index() })
+    } else if cfg!(feature = "intern-dedup") && DEDUP_TAGS.contains(&tag) {
+        let var_item = deduplicated_variable(&var_name, string, tag);
+        quote!({
+            #var_item
+            core::ptr::addr_of!(#var_name) as u16
+        })
     } else {
         let var_item = static_variable(&var_name, string, tag);
         quote!({
@@ -82,6 +88,45 @@ pub(crate) fn static_variable(name: &Ident2, data: &str, tag: &str) -> TokenStre
     )
 }
 
+/// Tags of the strings that don't depend on where they are interned, and are deduplicated across
+/// crates with the `intern-dedup` feature
+const DEDUP_TAGS: &[&str] = &["derived", "str", "write"];
+
+/// Like `static_variable`, but the string is placed in a COMDAT group named after its contents,
+/// of which the linker keeps a single copy for all crates. The `.ifndef` skips copies in the same
+/// object file.
+///
+/// Mach-O has no COMDAT groups, so on macOS this falls back to `static_variable`.
+fn deduplicated_variable(name: &Ident2, data: &str, tag: &str) -> TokenStream2 {
+    let sym_name = deduplicated_symbol_name(tag, data);
+    let section = format!(".defmt.dedup.{:x}", hash(&sym_name));
+    let asm = [
+        format!(".ifndef {sym_name}"),
+        format!(".pushsection {section},\"aG\",%progbits,{sym_name},comdat"),
+        format!(".globl {sym_name}"),
+        format!("{sym_name}:"),
+        ".byte 0".to_string(),
+        ".popsection".to_string(),
+        ".endif".to_string(),
+    ];
+    let fallback = static_variable(name, data, tag);
+
+    quote!(
+        #[cfg(target_os = "macos")]
+        #fallback
+
+        #[cfg(not(target_os = "macos"))]
+        mod defmt_dedup {
+            core::arch::global_asm!(#(#asm),*);
+        }
+        #[cfg(not(target_os = "macos"))]
+        unsafe extern "C" {
+            #[link_name = #sym_name]
+            static #name: u8;
+        }
+    )
+}
+
 pub(crate) fn string_literal(content: &str) -> LitStr {
     LitStr::new(content, Span2::call_site())
 }
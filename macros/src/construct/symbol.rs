@@ -6,6 +6,20 @@ pub(crate) fn mangled(defmt_tag: &str, data: &str) -> String {
     Symbol::new(defmt_tag, data).mangle()
 }
 
+/// Prefix of the symbols of deduplicated strings
+const DEDUP_PREFIX: &str = "defmt.dedup.";
+
+/// Returns the name of the symbol of a string deduplicated across crates; it only depends on the
+/// tag and the string, and is a valid assembler identifier: `defmt.dedup.TAG.HEX` where `HEX` are
+/// the hex-encoded bytes of the string.
+pub(crate) fn deduplicated(defmt_tag: &str, data: &str) -> String {
+    let mut name = format!("{DEDUP_PREFIX}{defmt_tag}.");
+    for byte in data.bytes() {
+        write!(name, "{byte:02x}").unwrap();
+    }
+    name
+}
+
 struct Symbol<'a> {
     /// Name of the Cargo package in which the symbol is being instantiated. Used for avoiding
     /// symbol name collisions.
@@ -36,7 +36,10 @@ pub(crate) fn codegen(
             format_string.push_str(", ");
         }
 
-        let format_opt = get_defmt_format_option(field)?;
+        let FieldOptions {
+            format: format_opt,
+            sensitive,
+        } = get_field_options(field)?;
         let ty = as_native_type(&field.ty).unwrap_or_else(|| consts::TYPE_FORMAT.to_string());
         let ident = field
             .ident
@@ -54,14 +57,22 @@ pub(crate) fn codegen(
             stmts.push(quote!(defmt::export::#method(#ident)));
         }
 
+        // if the decoder shows the value of a sensitive field, it's formatted like with `:?`
+        let hint = if sensitive {
+            ":__internal_sensitive"
+        } else if field.ident.is_some() {
+            ":?"
+        } else {
+            ""
+        };
         if field.ident.is_some() {
             // Named field.
-            write!(format_string, "{ident}: {{={ty}:?}}").ok();
+            write!(format_string, "{ident}: {{={ty}{hint}}}").ok();
 
             patterns.push(quote!( #ident ));
         } else {
             // Unnamed (tuple) field.
-            write!(format_string, "{{={ty}}}").ok();
+            write!(format_string, "{{={ty}{hint}}}").ok();
 
             let index = Index::from(index);
             patterns.push(quote!( #index: #ident ));
@@ -83,10 +94,18 @@ enum FormatOption {
     Display2Format,
 }
 
-/// If the field has a valid defmt attribute (e.g. `#[defmt(Debug2Format)]`), returns `Ok(Some(FormatOption))`.
+/// Options set with the `#[defmt(..)]` attribute of a field
+#[derive(Default)]
+struct FieldOptions {
+    format: Option<FormatOption>,
+    /// The decoder redacts the value unless told otherwise
+    sensitive: bool,
+}
+
+/// Parses the defmt attribute of the field (e.g. `#[defmt(Debug2Format)]` or
+/// `#[defmt(sensitive)]`), if any.
 /// Returns `Err` if we can't parse a valid defmt attribute.
-/// Returns `Ok(None)` if there are no `defmt` attributes on the field.
-fn get_defmt_format_option(field: &Field) -> syn::Result<Option<FormatOption>> {
+fn get_field_options(field: &Field) -> syn::Result<FieldOptions> {
     use syn::Error;
     let attrs = field
         .attrs
@@ -95,7 +114,7 @@ fn get_defmt_format_option(field: &Field) -> syn::Result<Option<FormatOption>> {
         .map(|a| a.parse_meta())
         .collect::<syn::Result<Vec<_>>>()?;
     if attrs.is_empty() {
-        return Ok(None);
+        return Ok(FieldOptions::default());
     } else if attrs.len() > 1 {
         return Err(Error::new_spanned(
             field,
@@ -107,31 +126,45 @@ fn get_defmt_format_option(field: &Field) -> syn::Result<Option<FormatOption>> {
         Meta::List(list) => &list.nested,
         bad => return Err(syn::Error::new_spanned(bad, "unrecognized attribute")),
     };
-    if args.len() != 1 {
+    if args.is_empty() {
         return Err(syn::Error::new_spanned(
             attr,
-            "expected 1 attribute argument",
+            "expected at least 1 attribute argument",
         ));
     }
-    let arg = match &args[0] {
-        NestedMeta::Meta(Meta::Path(arg)) => arg,
-        bad => {
+
+    let mut options = FieldOptions::default();
+    for arg in args {
+        let arg = match arg {
+            NestedMeta::Meta(Meta::Path(arg)) => arg,
+            bad => {
+                return Err(syn::Error::new_spanned(
+                    bad,
+                    "expected `Debug2Format`, `Display2Format` or `sensitive`",
+                ))
+            }
+        };
+        let format = if arg.is_ident("Debug2Format") {
+            FormatOption::Debug2Format
+        } else if arg.is_ident("Display2Format") {
+            FormatOption::Display2Format
+        } else if arg.is_ident("sensitive") {
+            options.sensitive = true;
+            continue;
+        } else {
+            return Err(syn::Error::new_spanned(
+                arg,
+                "expected `Debug2Format`, `Display2Format` or `sensitive`",
+            ));
+        };
+        if options.format.replace(format).is_some() {
             return Err(syn::Error::new_spanned(
-                bad,
-                "expected `Debug2Format` or `Display2Format`",
-            ))
+                arg,
+                "only one of `Debug2Format` and `Display2Format` can be used",
+            ));
         }
-    };
-    if arg.is_ident("Debug2Format") {
-        Ok(Some(FormatOption::Debug2Format))
-    } else if arg.is_ident("Display2Format") {
-        Ok(Some(FormatOption::Display2Format))
-    } else {
-        Err(syn::Error::new_spanned(
-            arg,
-            "expected `Debug2Format` or `Display2Format`",
-        ))
     }
+    Ok(options)
 }
 
 /// Returns `Some` if `ty` refers to a builtin Rust type that has native support from defmt and does
@@ -1,9 +1,11 @@
 pub(crate) mod assert_binop;
 pub(crate) mod assert_like;
 pub(crate) mod dbg;
+pub(crate) mod handshake;
 pub(crate) mod intern;
 pub(crate) mod internp;
 pub(crate) mod log;
 pub(crate) mod panic_like;
 pub(crate) mod println;
+pub(crate) mod snapshot;
 pub(crate) mod write;
@@ -3,7 +3,10 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, punctuated::Punctuated};
 
-use crate::{construct, function_like::log};
+use crate::{
+    construct,
+    function_like::{log, panic_like},
+};
 
 use self::args::Args;
 
@@ -43,17 +46,19 @@ fn expand(args: TokenStream, binop: BinOp) -> TokenStream {
         formatting_args.push(construct::variable(val));
     }
 
+    let location = panic_like::caller_location(&mut formatting_args);
+
     let panic_msg = match binop {
         BinOp::Eq => format!(
-            "panicked at 'assertion failed: `(left == right)`{}'
+            "panicked at 'assertion failed: `(left == right)`{}'{}
  left: `{{:?}}`
 right: `{{:?}}`",
-            extra_string
+            extra_string, location
         ),
         BinOp::Ne => format!(
-            "panicked at 'assertion failed: `(left != right)`{}'
+            "panicked at 'assertion failed: `(left != right)`{}'{}
 left/right: `{{:?}}`",
-            extra_string
+            extra_string, location
         ),
     };
 
@@ -3,7 +3,10 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse_macro_input;
 
-use crate::{construct, function_like::log};
+use crate::{
+    construct,
+    function_like::{log, panic_like},
+};
 
 pub(crate) fn expand(args: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as super::Args);
@@ -20,12 +23,15 @@ pub(crate) fn expand(args: TokenStream) -> TokenStream {
         (format_string, None)
     };
 
-    let format_string = construct::string_literal(&format_string);
+    let mut formatting_args = formatting_args.unwrap_or_default();
+    let location = panic_like::caller_location(&mut formatting_args);
+
+    let format_string = construct::string_literal(&format!("{format_string}{location}"));
     let log_stmt = log::expand_parsed(
         Level::Error,
         log::Args {
             format_string,
-            formatting_args,
+            formatting_args: Some(formatting_args),
         },
     );
 
@@ -3,25 +3,35 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, punctuated::Punctuated};
 
-use crate::{construct, function_like::log};
+use crate::{
+    construct,
+    function_like::{log, panic_like},
+};
 
 pub(crate) fn expand(args: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as super::Args);
 
     let condition = args.condition;
     let (format_string, formatting_args) = if let Some(log_args) = args.log_args {
-        let format_string = format!("panicked at '{}'", log_args.format_string.value());
-        (format_string, log_args.formatting_args)
-    } else {
+        let mut formatting_args = log_args.formatting_args.unwrap_or_default();
+        let location = panic_like::caller_location(&mut formatting_args);
         let format_string = format!(
-            "panicked at 'unwrap failed: {}'\nerror: `{{:?}}`",
-            construct::escaped_expr_string(&condition)
+            "panicked at '{}'{}",
+            log_args.format_string.value(),
+            location
         );
-
+        (format_string, formatting_args)
+    } else {
         let mut formatting_args = Punctuated::new();
         formatting_args.push(construct::variable("_unwrap_err"));
+        let location = panic_like::caller_location(&mut formatting_args);
 
-        (format_string, Some(formatting_args))
+        let format_string = format!(
+            "panicked at 'unwrap failed: {}'{}\nerror: `{{:?}}`",
+            construct::escaped_expr_string(&condition),
+            location
+        );
+        (format_string, formatting_args)
     };
 
     let format_string = construct::string_literal(&format_string);
@@ -29,7 +39,7 @@ pub(crate) fn expand(args: TokenStream) -> TokenStream {
         Level::Error,
         log::Args {
             format_string,
-            formatting_args,
+            formatting_args: Some(formatting_args),
         },
     );
 
@@ -0,0 +1,21 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse::Nothing, parse_macro_input};
+
+use crate::construct;
+
+pub(crate) fn expand(args: TokenStream) -> TokenStream {
+    parse_macro_input!(args as Nothing);
+
+    let header = construct::interned_string("handshake", "handshake", false);
+    quote!({
+        // safety: will be released a few lines further down
+        unsafe { defmt::export::acquire() };
+        // NOTE: no timestamp, so that the frame can be recognized without knowing the table
+        defmt::export::istr(&#header);
+        defmt::export::u64(&defmt::export::table_hash());
+        // safety: acquire() was called a few lines above
+        unsafe { defmt::export::release() }
+    })
+    .into()
+}
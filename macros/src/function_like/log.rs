@@ -25,12 +25,16 @@ pub(crate) fn expand_parsed(level: Level, args: Args) -> TokenStream2 {
         Err(e) => abort!(args.format_string, "{}", e),
     };
 
-    let formatting_exprs = args
+    let formatting_exprs: Vec<_> = args
         .formatting_args
         .map(|punctuated| punctuated.into_iter().collect())
-        .unwrap_or_else(Vec::new);
+        .unwrap_or_default();
 
-    let Codegen { patterns, exprs } = Codegen::new(
+    let Codegen {
+        patterns,
+        exprs,
+        chunked,
+    } = Codegen::new(
         &fragments,
         formatting_exprs.len(),
         args.format_string.span(),
@@ -50,6 +54,7 @@ pub(crate) fn expand_parsed(level: Level, args: Args) -> TokenStream2 {
                         #(#exprs;)*
                         // safety: acquire() was called a few lines above
                         unsafe { defmt::export::release() }
+                        #(#chunked;)*
                     }
                 }
             }
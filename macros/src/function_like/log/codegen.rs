@@ -6,6 +6,9 @@ use quote::{format_ident, quote};
 pub(crate) struct Codegen {
     pub(crate) exprs: Vec<TokenStream2>,
     pub(crate) patterns: Vec<Ident2>,
+    /// Statements sending the continuation frames of `{=chunked}` arguments; they must run after
+    /// the frame has been released.
+    pub(crate) chunked: Vec<TokenStream2>,
 }
 
 impl Codegen {
@@ -41,6 +44,7 @@ impl Codegen {
 
         let mut exprs = vec![];
         let mut patterns = vec![];
+        let mut chunked = vec![];
 
         for arg_index in 0..expected_arg_count {
             let arg_ident = format_ident!("arg{}", arg_index);
@@ -49,13 +53,34 @@ impl Codegen {
                 .find(|param| param.index == arg_index)
                 .unwrap();
 
-            let expr = encode_arg(&matching_param.ty, &params, arg_index, &arg_ident);
+            let expr = if matching_param.ty == Type::Chunked {
+                let id = format_ident!("chunked{}", arg_index);
+                chunked.push(quote!(defmt::export::chunked_data(#id, #arg_ident)));
+                quote!(let #id = defmt::export::chunked_header(#arg_ident))
+            } else {
+                encode_arg(&matching_param.ty, &params, arg_index, &arg_ident)
+            };
 
             exprs.push(expr);
             patterns.push(arg_ident);
         }
 
-        Codegen { exprs, patterns }
+        Codegen {
+            exprs,
+            patterns,
+            chunked,
+        }
+    }
+
+    /// Aborts if there are `{=chunked}` arguments, for macros that don't produce a log frame of
+    /// their own.
+    pub(crate) fn forbid_chunked(&self, span: Span2) {
+        if !self.chunked.is_empty() {
+            abort!(
+                span,
+                "`{{=chunked}}` can only be used in logging macros and `println!`"
+            )
+        }
     }
 }
 
@@ -86,16 +111,23 @@ fn encode_arg(ty: &Type, params: &[Parameter], arg_index: usize, arg: &Ident2) -
 
         Type::Format => quote!(defmt::export::fmt(#arg)),
         Type::FormatSlice => quote!(defmt::export::fmt_slice(#arg)),
+        Type::FormatIter => quote!(defmt::export::fmt_iter(#arg)),
         Type::FormatArray(len) => quote!(defmt::export::fmt_array({
             let tmp: &[_; #len] = #arg;
             tmp
         })),
 
+        Type::Cbor => quote!(defmt::export::cbor(#arg)),
         Type::Debug => quote!(defmt::export::debug(#arg)),
         Type::Display => quote!(defmt::export::display(#arg)),
+        Type::Snapshot => quote!(defmt::export::snapshot(#arg.0, #arg.1)),
         Type::FormatSequence => unreachable!(),
+        Type::Chunk | Type::Chunked | Type::Record => unreachable!(),
 
         Type::U8Slice => quote!(defmt::export::slice(#arg)),
+        Type::U16Slice => quote!(defmt::export::u16_slice(#arg)),
+        Type::U32Slice => quote!(defmt::export::u32_slice(#arg)),
+        Type::U64Slice => quote!(defmt::export::u64_slice(#arg)),
 
         // We cast to the expected array type (which should be a no-op cast) to provoke
         // a type mismatch error on mismatched lengths:
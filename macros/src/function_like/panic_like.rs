@@ -3,7 +3,7 @@ use std::borrow::Cow;
 use defmt_parser::Level;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::parse_macro_input;
+use syn::{parse_macro_input, parse_quote, punctuated::Punctuated, Expr, Token};
 
 use crate::{construct, function_like::log};
 
@@ -23,12 +23,15 @@ pub(crate) fn expand(
         (Cow::from(format_string), log_args.formatting_args)
     };
 
-    let format_string = construct::string_literal(&format_string);
+    let mut formatting_args = formatting_args.unwrap_or_default();
+    let location = caller_location(&mut formatting_args);
+
+    let format_string = construct::string_literal(&format!("{format_string}{location}"));
     let log_stmt = log::expand_parsed(
         Level::Error,
         log::Args {
             format_string,
-            formatting_args,
+            formatting_args: Some(formatting_args),
         },
     );
 
@@ -40,3 +43,27 @@ pub(crate) fn expand(
     )
     .into()
 }
+
+/// Appends the caller location to `formatting_args` and returns the matching `, file:line:column`
+/// part of the panic message, if the `caller-location` feature is enabled.
+///
+/// The location is that of the macro invocation, or of the caller if the invocation is inside a
+/// `#[track_caller]` function, so that panics in helpers point at the real call site. Explicit
+/// indices keep it independent of the implicit ones of the user's format string.
+pub(crate) fn caller_location(formatting_args: &mut Punctuated<Expr, Token![,]>) -> String {
+    if !cfg!(feature = "caller-location") {
+        return String::new();
+    }
+
+    let index = formatting_args.len();
+    formatting_args.push(parse_quote!(::core::panic::Location::caller().file()));
+    formatting_args.push(parse_quote!(::core::panic::Location::caller().line()));
+    formatting_args.push(parse_quote!(::core::panic::Location::caller().column()));
+
+    format!(
+        ", {{{}=str}}:{{{}=u32}}:{{{}=u32}}",
+        index,
+        index + 1,
+        index + 2
+    )
+}
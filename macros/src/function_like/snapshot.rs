@@ -0,0 +1,28 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse_macro_input;
+
+use crate::construct;
+
+use self::args::Args;
+
+mod args;
+
+pub(crate) fn expand(args: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as Args);
+    codegen(&args)
+}
+
+fn codegen(args: &Args) -> TokenStream {
+    let expr = &args.expr;
+    let escaped_expr = construct::escaped_expr_string(expr);
+    let format_string = format!("{escaped_expr} = {{=__internal_Snapshot}}");
+    let level = format_ident!("{}", args.level.as_str());
+
+    // every call site keeps the last snapshot it sent, to send only what changed the next time
+    quote!({
+        static SNAPSHOT: defmt::export::Snapshot = defmt::export::Snapshot::new();
+        defmt::#level!(#format_string, (&SNAPSHOT, &(#expr)))
+    })
+    .into()
+}
@@ -0,0 +1,40 @@
+use defmt_parser::Level;
+use syn::{
+    parse::{Parse, ParseStream},
+    Expr, Ident, Token,
+};
+
+pub(crate) struct Args {
+    pub(crate) level: Level,
+    pub(crate) expr: Expr,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let level = if input.peek(Ident) && input.peek2(Token![,]) {
+            let ident: Ident = input.parse()?;
+            let _comma: Token![,] = input.parse()?;
+            match ident.to_string().as_str() {
+                "trace" => Level::Trace,
+                "debug" => Level::Debug,
+                "info" => Level::Info,
+                "warn" => Level::Warn,
+                "error" => Level::Error,
+                _ => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "expected one of `trace`, `debug`, `info`, `warn` or `error`",
+                    ))
+                }
+            }
+        } else {
+            Level::Info
+        };
+
+        let expr = input.parse()?;
+        if !input.is_empty() {
+            let _comma: Option<Token![,]> = input.parse()?;
+        }
+        Ok(Self { level, expr })
+    }
+}
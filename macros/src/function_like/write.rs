@@ -28,11 +28,15 @@ pub(crate) fn expand(args: TokenStream) -> TokenStream {
         .map(|punctuated| punctuated.into_iter().collect())
         .unwrap_or_default();
 
-    let log::Codegen { patterns, exprs } = log::Codegen::new(
+    let codegen = log::Codegen::new(
         &fragments,
         formatting_exprs.len(),
         log_args.format_string.span(),
     );
+    codegen.forbid_chunked(log_args.format_string.span());
+    let log::Codegen {
+        patterns, exprs, ..
+    } = codegen;
 
     let format_tag = construct::interned_string(&format_string, "write", false);
     quote!({
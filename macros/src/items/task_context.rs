@@ -0,0 +1,35 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse_macro_input;
+
+use crate::{construct, function_like::log, items::timestamp};
+
+pub(crate) fn expand(args: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as log::Args);
+
+    let write = timestamp::codegen(&args);
+    let var_name = format_ident!("S");
+    let var_item =
+        construct::static_variable(&var_name, &args.format_string.value(), "task_context");
+
+    quote!(
+        const _: () = {
+            #[export_name = "_defmt_task_context"]
+            #[inline(never)]
+            fn defmt_task_context(fmt: ::defmt::Formatter<'_>) {
+                // NOTE: No format string index, and no finalize call.
+                #write
+            }
+
+            #var_item;
+
+            // Unique symbol name to prevent multiple `task_context!` invocations in the crate
+            // graph. This symbol itself is retained via a `EXTERN` directive in the linker script.
+            #[no_mangle]
+            #[cfg_attr(target_os = "macos", link_section = ".defmt,end.task_context")]
+            #[cfg_attr(not(target_os = "macos"), link_section = ".defmt.end.task_context")]
+            static __DEFMT_MARKER_TASK_CONTEXT_WAS_DEFINED: &u8 = &#var_name;
+        };
+    )
+    .into()
+}
@@ -1,59 +1,186 @@
 use defmt_parser::ParserMode;
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use proc_macro_error::abort;
 use quote::format_ident;
 use quote::quote;
-use syn::parse_macro_input;
+use syn::{
+    parse::{self, Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Token,
+};
 
 use crate::{construct, function_like::log};
 
+/// One or more timestamp sources, separated by `;`
+struct Sources(Vec<log::Args>);
+
+impl Parse for Sources {
+    fn parse(input: ParseStream) -> parse::Result<Self> {
+        let mut sources = vec![];
+        loop {
+            let format_string = input.parse()?;
+            let formatting_args = if input.is_empty() || input.peek(Token![;]) {
+                None
+            } else {
+                let _comma: Token![,] = input.parse()?;
+                let mut args = Punctuated::new();
+                while !input.is_empty() && !input.peek(Token![;]) {
+                    args.push_value(input.parse()?);
+                    if input.is_empty() || input.peek(Token![;]) {
+                        break;
+                    }
+                    args.push_punct(input.parse()?);
+                }
+                Some(args)
+            };
+            sources.push(log::Args {
+                format_string,
+                formatting_args,
+            });
+
+            if input.is_empty() {
+                break;
+            }
+            let _semi: Token![;] = input.parse()?;
+            // allow a trailing `;`
+            if input.is_empty() {
+                break;
+            }
+        }
+        Ok(Self(sources))
+    }
+}
+
 pub(crate) fn expand(args: TokenStream) -> TokenStream {
-    let args = parse_macro_input!(args as log::Args);
+    let Sources(sources) = parse_macro_input!(args as Sources);
 
-    let format_string = args.format_string.value();
+    if sources.len() > usize::from(u8::MAX) + 1 {
+        abort!(
+            sources[256].format_string,
+            "at most 256 timestamp sources can be defined"
+        );
+    }
 
-    let fragments = match defmt_parser::parse(&format_string, ParserMode::Strict) {
-        Ok(args) => args,
-        Err(e) => abort!(args.format_string, "{}", e),
+    // Unique symbol name to prevent multiple `timestamp!` invocations in the crate graph.
+    // Uses the interned format strings to ensure they are not discarded by the linker.
+    // This symbol itself is retained via a `EXTERN` directive in the linker script.
+    let marker = |ty: TokenStream2, value: TokenStream2| {
+        quote!(
+            #[no_mangle]
+            #[cfg_attr(target_os = "macos", link_section = ".defmt,end.timestamp")]
+            #[cfg_attr(not(target_os = "macos"), link_section = ".defmt.end.timestamp")]
+            static __DEFMT_MARKER_TIMESTAMP_WAS_DEFINED: #ty = #value;
+        )
     };
 
-    let formatting_exprs: Vec<_> = args
-        .formatting_args
-        .map(|punctuated| punctuated.into_iter().collect())
-        .unwrap_or_default();
+    if let [source] = &sources[..] {
+        let write = codegen(source);
+        let var_name = format_ident!("S");
+        let format_string = source.format_string.value();
+        let var_item = construct::static_variable(&var_name, &format_string, "timestamp");
+        let marker = marker(quote!(&u8), quote!(&#var_name));
 
-    let log::Codegen { patterns, exprs } = log::Codegen::new(
-        &fragments,
-        formatting_exprs.len(),
-        args.format_string.span(),
-    );
+        return quote!(
+            const _: () = {
+                #[export_name = "_defmt_timestamp"]
+                #[inline(never)]
+                fn defmt_timestamp(fmt: ::defmt::Formatter<'_>) {
+                    // NOTE: No format string index, and no finalize call.
+                    #write
+                }
+
+                #var_item;
+
+                #marker
+            };
+        )
+        .into();
+    }
+
+    // With several sources, every frame starts with the `u8` id of the source that was used,
+    // followed by that source's data. The id is prepended to the interned format string.
+    let last = sources.len() - 1;
+    let mut arms = vec![];
+    let mut var_items = vec![];
+    let mut var_names = vec![];
+    for (id, source) in sources.iter().enumerate() {
+        let id = id as u8;
+        let write = codegen(source);
+        let pattern = if usize::from(id) == last {
+            quote!(_)
+        } else {
+            quote!(#id)
+        };
+        arms.push(quote!(#pattern => { #write }));
 
-    let var_name = format_ident!("S");
-    let var_item = construct::static_variable(&var_name, &format_string, "timestamp");
+        let var_name = format_ident!("S{}", id);
+        let format_string = format!("{}:{}", id, source.format_string.value());
+        var_items.push(construct::static_variable(
+            &var_name,
+            &format_string,
+            "timestamp_source",
+        ));
+        var_names.push(var_name);
+    }
 
+    let len = sources.len();
+    let marker = marker(quote!([&u8; #len]), quote!([#(&#var_names),*]));
     quote!(
         const _: () = {
             #[export_name = "_defmt_timestamp"]
             #[inline(never)]
             fn defmt_timestamp(fmt: ::defmt::Formatter<'_>) {
-                match (#(&(#formatting_exprs)),*) {
-                    (#(#patterns),*) => {
-                    // NOTE: No format string index, and no finalize call.
-                        #(#exprs;)*
-                    }
+                // an id without a matching source selects the first one
+                let mut source = ::defmt::export::timestamp_source();
+                if usize::from(source) >= #len {
+                    source = 0;
+                }
+                ::defmt::export::u8(&source);
+                match source {
+                    #(#arms)*
                 }
             }
 
-            #var_item;
+            #(#var_items;)*
 
-            // Unique symbol name to prevent multiple `timestamp!` invocations in the crate graph.
-            // Uses `#var_name` to ensure it is not discarded by the linker.
-            // This symbol itself is retained via a `EXTERN` directive in the linker script.
-            #[no_mangle]
-            #[cfg_attr(target_os = "macos", link_section = ".defmt,end.timestamp")]
-            #[cfg_attr(not(target_os = "macos"), link_section = ".defmt.end.timestamp")]
-            static __DEFMT_MARKER_TIMESTAMP_WAS_DEFINED: &u8 = &#var_name;
+            #marker
         };
     )
     .into()
 }
+
+/// Generates the code writing the arguments of one timestamp source.
+pub(crate) fn codegen(source: &log::Args) -> TokenStream2 {
+    let format_string = source.format_string.value();
+
+    let fragments = match defmt_parser::parse(&format_string, ParserMode::Strict) {
+        Ok(args) => args,
+        Err(e) => abort!(source.format_string, "{}", e),
+    };
+
+    let formatting_exprs: Vec<_> = source
+        .formatting_args
+        .clone()
+        .map(|punctuated| punctuated.into_iter().collect())
+        .unwrap_or_default();
+
+    let codegen = log::Codegen::new(
+        &fragments,
+        formatting_exprs.len(),
+        source.format_string.span(),
+    );
+    codegen.forbid_chunked(source.format_string.span());
+    let log::Codegen {
+        patterns, exprs, ..
+    } = codegen;
+
+    quote!(
+        match (#(&(#formatting_exprs)),*) {
+            (#(#patterns),*) => {
+                #(#exprs;)*
+            }
+        }
+    )
+}
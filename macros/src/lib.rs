@@ -33,6 +33,12 @@ pub fn panic_handler(args: TokenStream, item: TokenStream) -> TokenStream {
     attributes::panic_handler::expand(args, item)
 }
 
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn trace_fn(args: TokenStream, item: TokenStream) -> TokenStream {
+    attributes::trace_fn::expand(args, item)
+}
+
 /* # Derives */
 #[proc_macro_derive(Format, attributes(defmt))]
 #[proc_macro_error]
@@ -101,6 +107,12 @@ pub fn dbg(args: TokenStream) -> TokenStream {
     function_like::dbg::expand(args)
 }
 
+#[proc_macro]
+#[proc_macro_error]
+pub fn handshake(args: TokenStream) -> TokenStream {
+    function_like::handshake::expand(args)
+}
+
 #[proc_macro]
 #[proc_macro_error]
 pub fn intern(args: TokenStream) -> TokenStream {
@@ -119,6 +131,12 @@ pub fn println(args: TokenStream) -> TokenStream {
     function_like::println::expand(args)
 }
 
+#[proc_macro]
+#[proc_macro_error]
+pub fn snapshot(args: TokenStream) -> TokenStream {
+    function_like::snapshot::expand(args)
+}
+
 /* ## Logging macros */
 
 #[proc_macro]
@@ -207,3 +225,9 @@ pub fn bitflags(ts: TokenStream) -> TokenStream {
 pub fn timestamp(args: TokenStream) -> TokenStream {
     items::timestamp::expand(args)
 }
+
+#[proc_macro]
+#[proc_macro_error]
+pub fn task_context(args: TokenStream) -> TokenStream {
+    items::task_context::expand(args)
+}
@@ -23,8 +23,10 @@ pub enum DisplayHint {
     Debug,
     /// `:us`, formats integers as timestamps in microseconds
     Microseconds,
-    /// `:iso8601{ms,s}`, formats integers as timestamp in ISO8601 date time format
+    /// `:iso8601{ms,s}` OR `:ts{ms,}`, formats integers as timestamp in ISO8601 date time format
     ISO8601(TimePrecision),
+    /// `:tick`, formats integers as ticks of a counter whose rate is configured in the decoder
+    Ticks,
     /// `__internal_bitflags_NAME` instructs the decoder to print the flags that are set, instead of
     /// the raw value.
     Bitflags {
@@ -33,6 +35,21 @@ pub enum DisplayHint {
         disambiguator: String,
         crate_name: String,
     },
+    /// `__internal_duration` instructs the decoder to interpret a `u128` as packed ticks plus the
+    /// ratio converting them to seconds, and print it in the most natural unit.
+    Duration,
+    /// `__internal_rate` instructs the decoder to interpret a `u128` as packed ticks plus the ratio
+    /// converting them to hertz, and print it in the most natural unit.
+    Rate,
+    /// `reg:PERIPHERAL.REGISTER` instructs the decoder to print the named bit-fields of the
+    /// register, as described by a CMSIS-SVD file, instead of the raw value.
+    Register {
+        peripheral: String,
+        register: String,
+    },
+    /// `__internal_sensitive` marks the value of a `#[defmt(sensitive)]` field, which the decoder
+    /// redacts unless told otherwise. Shown values are formatted like with `:?`.
+    Sensitive,
     /// Display hints currently not supported / understood
     Unknown(String),
 }
@@ -41,6 +58,18 @@ impl DisplayHint {
     /// Parses the display hint (e.g. the `#x` in `{=u8:#x}`)
     pub(crate) fn parse(mut s: &str) -> Option<Self> {
         const BITFLAGS_HINT_START: &str = "__internal_bitflags_";
+        const REGISTER_HINT_START: &str = "reg:";
+
+        if let Some(stripped) = s.strip_prefix(REGISTER_HINT_START) {
+            let (peripheral, register) = stripped.split_once('.')?;
+            if peripheral.is_empty() || register.is_empty() {
+                return None;
+            }
+            return Some(DisplayHint::Register {
+                peripheral: peripheral.into(),
+                register: register.into(),
+            });
+        }
 
         // The `#` comes before any padding hints (I think this matches core::fmt).
         // It is ignored for types that don't have an alternate representation.
@@ -94,7 +123,13 @@ impl DisplayHint {
             },
             "iso8601ms" => DisplayHint::ISO8601(TimePrecision::Millis),
             "iso8601s" => DisplayHint::ISO8601(TimePrecision::Seconds),
+            "tsms" => DisplayHint::ISO8601(TimePrecision::Millis),
+            "ts" => DisplayHint::ISO8601(TimePrecision::Seconds),
+            "tick" => DisplayHint::Ticks,
             "?" => DisplayHint::Debug,
+            "__internal_duration" => DisplayHint::Duration,
+            "__internal_rate" => DisplayHint::Rate,
+            "__internal_sensitive" => DisplayHint::Sensitive,
             _ => return None,
         })
     }
@@ -111,7 +146,7 @@ pub enum TimePrecision {
 ///
 /// Returns the integer and remaining text, if `s` started with an integer. Any errors parsing the
 /// number (which we already know only contains digits) are silently ignored.
-fn parse_integer<T: FromStr>(s: &str) -> Option<(&str, usize)> {
+fn parse_integer<T: FromStr>(s: &str) -> Option<(&str, T)> {
     let start_digits = s
         .as_bytes()
         .iter()
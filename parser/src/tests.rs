@@ -33,7 +33,14 @@ fn all_parse_param_cases(
 #[case(":#X", DisplayHint::Hexadecimal { alternate: true, uppercase: true, zero_pad: 0 })]
 #[case(":iso8601ms", DisplayHint::ISO8601(TimePrecision::Millis))]
 #[case(":iso8601s", DisplayHint::ISO8601(TimePrecision::Seconds))]
+#[case(":tsms", DisplayHint::ISO8601(TimePrecision::Millis))]
+#[case(":ts", DisplayHint::ISO8601(TimePrecision::Seconds))]
+#[case(":tick", DisplayHint::Ticks)]
 #[case(":?", DisplayHint::Debug)]
+#[case(":__internal_duration", DisplayHint::Duration)]
+#[case(":__internal_sensitive", DisplayHint::Sensitive)]
+#[case(":reg:USART1.SR", DisplayHint::Register { peripheral: "USART1".into(), register: "SR".into() })]
+#[case(":__internal_rate", DisplayHint::Rate)]
 #[case(":02", DisplayHint::NoHint { zero_pad: 2 })]
 fn all_display_hints(#[case] input: &str, #[case] hint: DisplayHint) {
     assert_eq!(
@@ -78,6 +85,11 @@ fn display_hint_unknown() {
 #[case("=?", Type::Format)]
 #[case("=str", Type::Str)]
 #[case("=[u8]", Type::U8Slice)]
+#[case("=[u16]", Type::U16Slice)]
+#[case("=[u32]", Type::U32Slice)]
+#[case("=[u64]", Type::U64Slice)]
+#[case("=iter", Type::FormatIter)]
+#[case("=chunked", Type::Chunked)]
 fn all_types(#[case] input: &str, #[case] ty: Type) {
     assert_eq!(
         parse_param(input, ParserMode::Strict),
@@ -181,6 +193,7 @@ fn arrays_err(#[case] input: &str) {
 #[case("{dunno=u8:x}", Error::UnexpectedContentInFormatString("dunno=u8:x".to_string()))]
 #[case("{0dunno}", Error::UnexpectedContentInFormatString("dunno".to_string()))]
 #[case("{:}", Error::MalformedFormatString)]
+#[case("{=u32:reg:USART1}", Error::UnknownDisplayHint("reg:USART1".to_string()))]
 #[case::stray_braces_1("}string", Error::UnmatchedCloseBracket)]
 #[case::stray_braces_2("{string", Error::UnmatchedOpenBracket)]
 #[case::stray_braces_3("}", Error::UnmatchedCloseBracket)]
@@ -7,9 +7,19 @@ pub enum Type {
     /// A single Unicode character
     Char,
 
+    /// CBOR-encoded `serde::Serialize` value
+    Cbor,
+    /// Part of a `{=chunked}` byte slice, sent in a continuation frame
+    Chunk,
+    /// `{=chunked}`, byte slice split across continuation frames
+    Chunked,
     Debug,
     Display,
     FormatSequence,
+    /// Begin, continue or end flag of a logical record, sent in a marker frame
+    Record,
+    /// `defmt::snapshot!` value, sent in full or as the bytes that changed since the last one
+    Snapshot,
 
     F32,
     F64,
@@ -20,6 +30,8 @@ pub enum Type {
     FormatArray(usize), // FIXME: This `usize` is not the target's `usize`; use `u64` instead?
     /// `{=[?]}`
     FormatSlice,
+    /// `{=iter}`, encoded like a `{=[?]}`
+    FormatIter,
 
     I8,
     I16,
@@ -42,6 +54,10 @@ pub enum Type {
 
     /// Byte slice `{=[u8]}`.
     U8Slice,
+    /// Word slices `{=[u16]}`, `{=[u32]}` and `{=[u64]}`.
+    U16Slice,
+    U32Slice,
+    U64Slice,
     U8Array(usize), // FIXME: This `usize` is not the target's `usize`; use `u64` instead?
 }
 
@@ -68,12 +84,21 @@ impl FromStr for Type {
             "bool" => Type::Bool,
             "str" => Type::Str,
             "istr" => Type::IStr,
+            "__internal_Cbor" => Type::Cbor,
+            "__internal_Chunk" => Type::Chunk,
             "__internal_Debug" => Type::Debug,
             "__internal_Display" => Type::Display,
             "__internal_FormatSequence" => Type::FormatSequence,
+            "__internal_Record" => Type::Record,
+            "__internal_Snapshot" => Type::Snapshot,
             "[u8]" => Type::U8Slice,
+            "[u16]" => Type::U16Slice,
+            "[u32]" => Type::U32Slice,
+            "[u64]" => Type::U64Slice,
             "?" => Type::Format,
             "[?]" => Type::FormatSlice,
+            "iter" => Type::FormatIter,
+            "chunked" => Type::Chunked,
             "char" => Type::Char,
             _ => return Err(()),
         })
@@ -0,0 +1,127 @@
+//! Inter-arrival statistics of each log statement, for `defmt-print analyze`
+
+use std::{
+    io::{self, Read},
+    num::NonZeroU64,
+};
+
+use anyhow::bail;
+use clap::Args;
+use defmt_decoder::{Timing, TimingAnalysis};
+
+use crate::{Firmware, Stream, StreamOpts, READ_BUFFER_SIZE};
+
+#[derive(Args)]
+pub(crate) struct AnalyzeOpts {
+    /// Number of bars of the histogram of the periods of each log statement
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    bins: usize,
+}
+
+/// Width of the longest bar of a histogram, in characters
+const BAR_WIDTH: usize = 40;
+
+/// Decodes stdin until it is closed, then prints the timing of the frames of each log statement.
+pub(crate) fn run(
+    opts: AnalyzeOpts,
+    firmwares: &[Firmware],
+    select: bool,
+    stream_opts: &StreamOpts,
+) -> anyhow::Result<()> {
+    if opts.bins == 0 {
+        bail!("the histogram needs at least one bin");
+    }
+
+    let mut stream = Stream::new(firmwares, select, stream_opts);
+    let mut analysis = TimingAnalysis::new();
+    let mut buf = [0; READ_BUFFER_SIZE];
+    let mut stdin = io::stdin().lock();
+    loop {
+        let n = stdin.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        stream.received(&buf[..n], |frame, _| analysis.frame(frame))?;
+    }
+    stream.finish(|frame, _| analysis.frame(frame))?;
+
+    let mut timings = analysis.timings().peekable();
+    if timings.peek().is_none() {
+        println!("no timestamped frames received; see `defmt::timestamp!`");
+    }
+    for timing in timings {
+        for line in report(timing, opts.bins) {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+/// Lays out the statistics and histogram of the periods of a log statement.
+fn report(timing: &Timing, bins: usize) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{:#06x} {:?}: {} frame{}",
+        timing.index,
+        timing.format,
+        timing.frames,
+        if timing.frames == 1 { "" } else { "s" }
+    )];
+    let (Some(min), Some(mean), Some(max), Some(jitter)) =
+        (timing.min(), timing.mean(), timing.max(), timing.jitter())
+    else {
+        return lines;
+    };
+
+    let period = |counts: f64| period(counts, timing.rate);
+    lines.push(format!(
+        "  period: min {}, avg {}, max {}, jitter {}",
+        period(min as f64),
+        period(mean),
+        period(max as f64),
+        period(jitter)
+    ));
+    let histogram = timing.histogram(bins);
+    let most = histogram.iter().map(|bin| bin.count).max().unwrap_or(0);
+    let labels = histogram
+        .iter()
+        .map(|bin| match bin.start == bin.end {
+            true => period(bin.start as f64),
+            false => format!("{} - {}", period(bin.start as f64), period(bin.end as f64)),
+        })
+        .collect::<Vec<_>>();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    for (bin, label) in histogram.iter().zip(labels) {
+        let bar = "#".repeat((bin.count * BAR_WIDTH).div_ceil(most));
+        lines.push(format!("  {label:>label_width$} | {bar} {}", bin.count));
+    }
+    lines
+}
+
+/// Formats a number of timestamp counts as a duration, if the `rate` of the timestamps is known.
+fn period(counts: f64, rate: Option<NonZeroU64>) -> String {
+    let Some(rate) = rate else {
+        return format!("{counts:.1}");
+    };
+    let (value, unit) = match counts / rate.get() as f64 {
+        s if s >= 1.0 => (s, "s"),
+        s if s >= 1e-3 => (s * 1e3, "ms"),
+        s if s >= 1e-6 => (s * 1e6, "us"),
+        s => (s * 1e9, "ns"),
+    };
+    format!("{value:.3}{unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn period() {
+        let rate = NonZeroU64::new(32_768);
+        assert_eq!(super::period(32_768.0, rate), "1.000s");
+        assert_eq!(super::period(328.0, rate), "10.010ms");
+        assert_eq!(super::period(1.0, rate), "30.518us");
+        assert_eq!(super::period(0.0, rate), "0.000ns");
+        assert_eq!(super::period(12.0, None), "12.0");
+    }
+}
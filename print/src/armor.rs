@@ -0,0 +1,119 @@
+//! Decoding of ASCII-armored input, e.g. pasted from a serial terminal
+
+use anyhow::{anyhow, bail};
+use clap::ValueEnum;
+
+/// Text encoding of the input stream
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum Encoding {
+    Hex,
+    Base64,
+}
+
+/// Streaming decoder; symbols of an incomplete byte (or base64 group) are kept until more input
+/// arrives.
+pub(crate) struct Decoder {
+    encoding: Encoding,
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub(crate) fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            pending: Vec::with_capacity(4),
+        }
+    }
+
+    /// Decodes `input`, appending the resulting bytes to `out`. Whitespace is ignored.
+    pub(crate) fn decode(&mut self, input: &[u8], out: &mut Vec<u8>) -> anyhow::Result<()> {
+        let group_len = match self.encoding {
+            Encoding::Hex => 2,
+            Encoding::Base64 => 4,
+        };
+
+        for &c in input.iter().filter(|c| !c.is_ascii_whitespace()) {
+            self.pending.push(c);
+            if self.pending.len() < group_len {
+                continue;
+            }
+
+            match self.encoding {
+                Encoding::Hex => out.push(hex(self.pending[0])? << 4 | hex(self.pending[1])?),
+                Encoding::Base64 => {
+                    let padding = self
+                        .pending
+                        .iter()
+                        .rev()
+                        .take_while(|&&c| c == b'=')
+                        .count();
+                    if padding > 2 {
+                        bail!("invalid base64 padding");
+                    }
+                    let mut group = 0u32;
+                    for &c in &self.pending[..4 - padding] {
+                        group = group << 6 | u32::from(base64(c)?);
+                    }
+                    group <<= 6 * padding;
+                    out.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+                }
+            }
+            self.pending.clear();
+        }
+        Ok(())
+    }
+}
+
+fn hex(c: u8) -> anyhow::Result<u8> {
+    (c as char)
+        .to_digit(16)
+        .map(|digit| digit as u8)
+        .ok_or_else(|| anyhow!("invalid hex character {:?}", c as char))
+}
+
+/// Accepts both the standard and the URL-safe alphabet.
+fn base64(c: u8) -> anyhow::Result<u8> {
+    Ok(match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' | b'-' => 62,
+        b'/' | b'_' => 63,
+        _ => bail!("invalid base64 character {:?}", c as char),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(encoding: Encoding, chunks: &[&str]) -> anyhow::Result<Vec<u8>> {
+        let mut decoder = Decoder::new(encoding);
+        let mut out = vec![];
+        for chunk in chunks {
+            decoder.decode(chunk.as_bytes(), &mut out)?;
+        }
+        Ok(out)
+    }
+
+    #[test]
+    fn hex() {
+        let out = decode(Encoding::Hex, &["01 aB\n", "f", "F"]).unwrap();
+        assert_eq!(out, [0x01, 0xab, 0xff]);
+
+        assert!(decode(Encoding::Hex, &["0g"]).is_err());
+    }
+
+    #[test]
+    fn base64() {
+        let out = decode(Encoding::Base64, &["aGVs", "bG8=\r\n", "/-8"]).unwrap();
+        assert_eq!(out, b"hello");
+        let out = decode(Encoding::Base64, &["/-8="]).unwrap();
+        assert_eq!(out, [0xff, 0xef]);
+        let out = decode(Encoding::Base64, &["YQ", "=="]).unwrap();
+        assert_eq!(out, b"a");
+
+        assert!(decode(Encoding::Base64, &["a==="]).is_err());
+        assert!(decode(Encoding::Base64, &["a*bc"]).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Decoding of the CSV files exported by the UART analyzers of logic analyzer software, e.g.
+//! Saleae Logic or sigrok's PulseView
+//!
+//! The first row is a header naming the columns. Each byte is read from the column named `data`
+//! or `value`; if there's a `type` column, rows whose type isn't `data` (e.g. framing errors) are
+//! skipped. Captures of several channels are told apart by the column named `name` or `channel`.
+
+use std::{mem, str};
+
+use anyhow::{anyhow, bail, Context as _};
+
+/// Streaming decoder; a row is decoded once its line ending arrives, or at the end of the input.
+pub(crate) struct Decoder {
+    /// Only rows of this channel are decoded, if set
+    channel: Option<String>,
+    line: Vec<u8>,
+    columns: Option<Columns>,
+    row: usize,
+}
+
+struct Columns {
+    data: usize,
+    kind: Option<usize>,
+    name: Option<usize>,
+}
+
+impl Decoder {
+    /// Creates a decoder of the rows of `channel`, or of all rows.
+    pub(crate) fn new(channel: Option<String>) -> Self {
+        Self {
+            channel,
+            line: Vec::new(),
+            columns: None,
+            row: 0,
+        }
+    }
+
+    /// Decodes `input`, appending the bytes of its complete rows to `out`.
+    pub(crate) fn decode(&mut self, input: &[u8], out: &mut Vec<u8>) -> anyhow::Result<()> {
+        for &c in input {
+            if c != b'\n' {
+                self.line.push(c);
+                continue;
+            }
+
+            self.next_row(out)?;
+        }
+        Ok(())
+    }
+
+    /// Decodes the last row, if the input didn't end with a line ending.
+    pub(crate) fn finish(&mut self, out: &mut Vec<u8>) -> anyhow::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+        self.next_row(out)
+    }
+
+    fn next_row(&mut self, out: &mut Vec<u8>) -> anyhow::Result<()> {
+        let line = mem::take(&mut self.line);
+        self.row += 1;
+        self.decode_row(&line, out)
+            .with_context(|| format!("CSV row {}", self.row))
+    }
+
+    fn decode_row(&mut self, line: &[u8], out: &mut Vec<u8>) -> anyhow::Result<()> {
+        let line = str::from_utf8(line)?.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let fields = split(line);
+
+        let Some(columns) = &self.columns else {
+            let columns = Columns::from_header(&fields)?;
+            if self.channel.is_some() && columns.name.is_none() {
+                bail!("no `name` or `channel` column in the CSV header to select the channel by");
+            }
+            self.columns = Some(columns);
+            return Ok(());
+        };
+        if let (Some(channel), Some(name)) = (&self.channel, columns.name) {
+            if fields.get(name) != Some(channel) {
+                return Ok(());
+            }
+        }
+        if let Some(kind) = columns.kind.and_then(|kind| fields.get(kind)) {
+            if !kind.eq_ignore_ascii_case("data") {
+                return Ok(());
+            }
+        }
+        let value = fields
+            .get(columns.data)
+            .ok_or_else(|| anyhow!("missing data column"))?;
+        out.push(parse_byte(value)?);
+        Ok(())
+    }
+}
+
+impl Columns {
+    fn from_header(fields: &[String]) -> anyhow::Result<Self> {
+        let find = |names: &[&str]| {
+            fields
+                .iter()
+                .position(|field| names.iter().any(|name| field.eq_ignore_ascii_case(name)))
+        };
+        Ok(Self {
+            data: find(&["data", "value"])
+                .ok_or_else(|| anyhow!("no `data` or `value` column in the CSV header"))?,
+            kind: find(&["type"]),
+            name: find(&["name", "channel"]),
+        })
+    }
+}
+
+/// Splits a CSV row into its fields, removing quotes and surrounding whitespace.
+fn split(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(mem::take(&mut field).trim().to_owned()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_owned());
+    fields
+}
+
+/// Parses a byte as shown by the analyzer: `0x`-prefixed hexadecimal, `0b`-prefixed binary,
+/// decimal, or a (possibly quoted and escaped) ASCII character.
+fn parse_byte(value: &str) -> anyhow::Result<u8> {
+    let parsed = if let Some(hex) = value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        u8::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = value.strip_prefix("0b").or(value.strip_prefix("0B")) {
+        u8::from_str_radix(bin, 2).ok()
+    } else if value.bytes().all(|c| c.is_ascii_digit()) {
+        value.parse().ok()
+    } else {
+        let unquoted = value
+            .strip_prefix('\'')
+            .and_then(|value| value.strip_suffix('\''))
+            .unwrap_or(value);
+        match unquoted.as_bytes() {
+            [c] => Some(*c),
+            [b'\\', b'0'] => Some(b'\0'),
+            [b'\\', b't'] => Some(b'\t'),
+            [b'\\', b'n'] => Some(b'\n'),
+            [b'\\', b'r'] => Some(b'\r'),
+            [b'\\', c @ (b'\\' | b'\'')] => Some(*c),
+            _ => None,
+        }
+    };
+    match parsed {
+        Some(byte) => Ok(byte),
+        None => bail!("invalid byte value {value:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(chunks: &[&str]) -> anyhow::Result<Vec<u8>> {
+        decode_channel(None, chunks)
+    }
+
+    fn decode_channel(channel: Option<&str>, chunks: &[&str]) -> anyhow::Result<Vec<u8>> {
+        let mut decoder = Decoder::new(channel.map(str::to_owned));
+        let mut out = vec![];
+        for chunk in chunks {
+            decoder.decode(chunk.as_bytes(), &mut out)?;
+        }
+        decoder.finish(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn saleae() {
+        let out = decode(&[
+            "name,type,start_time,duration,\"data\",error\r\n",
+            "\"Async Serial\",\"data\",0.001,8.6e-05,0x01,\n\"Async Serial\",\"fra",
+            "ming error\",0.002,8.6e-05,0x00,framing\r\n",
+            "\"Async Serial\",\"data\",0.003,8.6e-05,0xFF,\r\n",
+        ])
+        .unwrap();
+        assert_eq!(out, [0x01, 0xff]);
+    }
+
+    #[test]
+    fn last_row_without_line_ending() {
+        let out = decode(&["data\n0x01\n", "0x02"]).unwrap();
+        assert_eq!(out, [0x01, 0x02]);
+    }
+
+    #[test]
+    fn channel() {
+        let chunks = [
+            "name,type,start_time,duration,data\n",
+            "TX,data,0.001,8.6e-05,0x01\n",
+            "RX,data,0.001,8.6e-05,0x02\n",
+            "\"TX\",data,0.002,8.6e-05,0x03\n",
+        ];
+        assert_eq!(decode_channel(Some("TX"), &chunks).unwrap(), [0x01, 0x03]);
+        assert_eq!(decode_channel(Some("RX"), &chunks).unwrap(), [0x02]);
+        assert_eq!(decode(&chunks).unwrap(), [0x01, 0x02, 0x03]);
+
+        assert!(decode_channel(Some("TX"), &["data\n"]).is_err());
+    }
+
+    #[test]
+    fn values() {
+        let out = decode(&[
+            "Time [s],Value,Parity Error,Framing Error\n",
+            "0.1,'h',,\n0.2,\"','\",,\n0.3,'\\n',,\n0.4,0b101,,\n0.5,200,,\n\n",
+        ])
+        .unwrap();
+        assert_eq!(out, [b'h', b',', b'\n', 0b101, 200]);
+
+        assert!(decode(&["data\n", "256\n"]).is_err());
+        assert!(decode(&["data\n", "'ab'\n"]).is_err());
+        assert!(decode(&["time,bytes\n"]).is_err());
+    }
+}
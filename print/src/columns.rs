@@ -0,0 +1,202 @@
+//! Printing several sources side by side, for `defmt-print columns`
+
+use std::{
+    fs::File,
+    io::Read,
+    path::PathBuf,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Instant,
+};
+
+use anyhow::bail;
+use clap::Args;
+use defmt_decoder::Frame;
+
+use crate::{hooks::Hooks, Firmware, Stream, StreamOpts, READ_BUFFER_SIZE};
+
+#[derive(Args)]
+pub(crate) struct ColumnsOpts {
+    /// Files, FIFOs or serial ports to read, one per column; the column is headed LABEL, or else
+    /// the file name
+    #[arg(value_name = "[LABEL=]PATH", required = true)]
+    sources: Vec<String>,
+
+    /// Width of each column, in characters; longer frames are wrapped
+    #[arg(long, value_name = "N", default_value_t = 50)]
+    width: usize,
+}
+
+/// Input of the printing loop
+enum Event {
+    /// Data read from the source at `source`, and when it was read
+    Data {
+        source: usize,
+        data: Vec<u8>,
+        time: Instant,
+    },
+    /// The source at `source` was closed
+    Eof { source: usize },
+}
+
+/// Decodes the data of all sources, printing each frame in the column of its source on a row of
+/// its own, prefixed with the host time it was received at.
+pub(crate) fn run(
+    opts: ColumnsOpts,
+    firmwares: &[Firmware],
+    select: bool,
+    stream_opts: &StreamOpts,
+    hooks: &Hooks,
+) -> anyhow::Result<()> {
+    if opts.sources.len() < 2 {
+        bail!("`columns` needs at least two sources");
+    }
+    if opts.width == 0 {
+        bail!("the column width must not be zero");
+    }
+
+    let start = Instant::now();
+    let (events, received) = mpsc::channel();
+    let mut labels = vec![];
+    for (source, arg) in opts.sources.iter().enumerate() {
+        let (label, path) = match arg.split_once('=') {
+            Some((label, path)) => (label.to_owned(), PathBuf::from(path)),
+            None => {
+                let path = PathBuf::from(arg);
+                let label = path
+                    .file_name()
+                    .map_or_else(|| arg.clone(), |name| name.to_string_lossy().into_owned());
+                (label, path)
+            }
+        };
+        spawn_reader(source, File::open(&path)?, events.clone());
+        labels.push(label);
+    }
+    drop(events);
+
+    let columns = Columns {
+        width: opts.width,
+        count: labels.len(),
+    };
+    columns.print_row("host time", |i| labels[i].clone());
+
+    let mut streams = (0..labels.len())
+        .map(|_| Stream::new(firmwares, select, stream_opts))
+        .collect::<Vec<_>>();
+    let mut open = streams.len();
+    while open > 0 {
+        let (source, data, time) = match received.recv() {
+            Ok(Event::Data { source, data, time }) => (source, Some(data), time),
+            Ok(Event::Eof { source }) => {
+                open -= 1;
+                (source, None, Instant::now())
+            }
+            Err(_) => break,
+        };
+        let time = format!("{:.3}s", time.duration_since(start).as_secs_f64());
+        let print = |frame: &Frame, _: &Firmware| {
+            let text = frame.display(false).to_string();
+            columns.print_row(&time, |i| match i == source {
+                true => text.clone(),
+                false => String::new(),
+            });
+            hooks.frame(frame);
+        };
+        match data {
+            Some(data) => streams[source].received(&data, print)?,
+            // e.g. the last row of a CSV file without a line ending
+            None => streams[source].finish(print)?,
+        }
+    }
+    Ok(())
+}
+
+/// Reads `file` on a separate thread, like stdin is read when printing a single stream.
+fn spawn_reader(source: usize, mut file: File, events: Sender<Event>) {
+    thread::spawn(move || {
+        let mut buf = [0; READ_BUFFER_SIZE];
+        loop {
+            let event = match file.read(&mut buf) {
+                Ok(0) | Err(_) => Event::Eof { source },
+                Ok(n) => Event::Data {
+                    source,
+                    data: buf[..n].to_vec(),
+                    time: Instant::now(),
+                },
+            };
+            let eof = matches!(event, Event::Eof { .. });
+            if events.send(event).is_err() || eof {
+                break;
+            }
+        }
+    });
+}
+
+/// Layout of the columns
+struct Columns {
+    width: usize,
+    count: usize,
+}
+
+const TIME_WIDTH: usize = 10;
+
+impl Columns {
+    /// Prints a row with the text returned by `column` for each column.
+    fn print_row(&self, time: &str, column: impl Fn(usize) -> String) {
+        for line in self.row(time, column) {
+            println!("{line}");
+        }
+    }
+
+    /// Lays out a row, wrapping text that is wider than a column onto further lines.
+    fn row(&self, time: &str, column: impl Fn(usize) -> String) -> Vec<String> {
+        let cells = (0..self.count)
+            .map(|i| wrap(&column(i), self.width))
+            .collect::<Vec<_>>();
+        let lines = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        (0..lines)
+            .map(|line| {
+                let time = if line == 0 { time } else { "" };
+                let mut row = format!("{time:>TIME_WIDTH$}");
+                for cell in &cells {
+                    let text = cell.get(line).map_or("", String::as_str);
+                    row += &format!(" | {text:<0$}", self.width);
+                }
+                row.trim_end().to_owned()
+            })
+            .collect()
+    }
+}
+
+/// Splits `text` into lines of at most `width` characters.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    text.lines()
+        .flat_map(|line| {
+            let chars = line.chars().collect::<Vec<_>>();
+            chars
+                .chunks(width)
+                .map(|chunk| chunk.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row() {
+        let columns = Columns { width: 6, count: 3 };
+        let texts = ["", "INFO connected", "x"];
+
+        assert_eq!(
+            columns.row("0.125s", |i| texts[i].to_owned()),
+            [
+                "    0.125s |        | INFO c | x",
+                "           |        | onnect |",
+                "           |        | ed     |",
+            ]
+        );
+    }
+}
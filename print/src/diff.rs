@@ -0,0 +1,68 @@
+//! Comparison of the log statements of two ELF files, for `defmt-print diff`
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::anyhow;
+use clap::Args;
+use defmt_decoder::{CatalogDiff, Statement, Table};
+
+#[derive(Args)]
+pub(crate) struct DiffOpts {
+    /// ELF file of the previous firmware version, compared with the one given with `-e`
+    old: PathBuf,
+}
+
+/// Prints the log statements that differ between `opts.old` and `elf`, and exits with status 1 if
+/// there are any.
+pub(crate) fn run(opts: &DiffOpts, elf: &Path) -> anyhow::Result<()> {
+    let old = statements(&opts.old)?;
+    let new = statements(elf)?;
+    let diff = CatalogDiff::new(&old, &new);
+    if diff.is_empty() {
+        println!("no log statements changed");
+        return Ok(());
+    }
+
+    let current_dir = env::current_dir()?;
+    let describe = |statement: &Statement| describe(statement, &current_dir);
+    for statement in &diff.added {
+        println!("+ {}", describe(statement));
+    }
+    for statement in &diff.removed {
+        println!("- {}", describe(statement));
+    }
+    for (old, new) in &diff.changed {
+        println!("~ {}", describe(old));
+        println!("  {}", describe(new));
+    }
+    for (old, new) in &diff.moved {
+        println!("> {:#06x} -> {}", old.index, describe(new));
+    }
+    process::exit(1)
+}
+
+fn statements(elf: &Path) -> anyhow::Result<Vec<Statement>> {
+    let bytes = fs::read(elf)?;
+    let table = Table::parse(&bytes)?
+        .ok_or_else(|| anyhow!(".defmt data not found in {}", elf.display()))?;
+    let locs = table.get_locations(&bytes)?;
+    Ok(table.statements(Some(&locs)))
+}
+
+/// Formats a statement as its index, level, format string and location.
+fn describe(statement: &Statement, current_dir: &Path) -> String {
+    let level = statement.level.map_or("println", |level| level.as_str());
+    let mut line = format!(
+        "{:#06x} {:<7} {:?} ({})",
+        statement.index, level, statement.format, statement.crate_name
+    );
+    if let Some(loc) = &statement.location {
+        let path = loc.file.strip_prefix(current_dir).unwrap_or(&loc.file);
+        line += &format!(" {}:{}", path.display(), loc.line);
+    }
+    line
+}
@@ -0,0 +1,173 @@
+//! Commands run when a frame matches a regex, for `defmt-print --on-match`
+
+use std::{
+    cell::Cell,
+    io::Write as _,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context as _};
+use defmt_decoder::Frame;
+use regex::Regex;
+
+/// Rules of `--on-match`
+#[derive(Default)]
+pub(crate) struct Hooks {
+    hooks: Vec<Hook>,
+    /// Minimum time between two runs of the same rule
+    cooldown: Duration,
+}
+
+struct Hook {
+    regex: Regex,
+    command: String,
+    /// Whether the command of the last run is still running
+    running: Arc<AtomicBool>,
+    last_run: Cell<Option<Instant>>,
+}
+
+impl Hooks {
+    /// Parses rules of the form `REGEX:COMMAND`. The rule is split at the first colon that isn't
+    /// escaped as `\:`.
+    pub(crate) fn parse(rules: &[String], cooldown: Duration) -> anyhow::Result<Self> {
+        let hooks = rules
+            .iter()
+            .map(|rule| Hook::parse(rule).with_context(|| format!("invalid rule `{rule}`")))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { hooks, cooldown })
+    }
+
+    /// Runs the command of every rule whose regex matches the text of `frame`, unless it is still
+    /// running or ran less than the cooldown ago; such matches are dropped, not queued.
+    pub(crate) fn frame(&self, frame: &Frame) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let line = frame.display(false).to_string();
+        let level = frame.level().map_or("", |level| level.as_str());
+        for hook in self.hooks.iter().filter(|hook| hook.regex.is_match(&line)) {
+            if hook.ready(self.cooldown) {
+                hook.run(&line, level);
+            }
+        }
+    }
+}
+
+impl Hook {
+    fn parse(rule: &str) -> anyhow::Result<Self> {
+        let mut escaped = false;
+        let colon = rule
+            .char_indices()
+            .find(|&(_, c)| {
+                let found = c == ':' && !escaped;
+                escaped = c == '\\' && !escaped;
+                found
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(|| anyhow!("expected `REGEX:COMMAND`"))?;
+
+        let (regex, command) = (&rule[..colon], &rule[colon + 1..]);
+        if command.trim().is_empty() {
+            return Err(anyhow!("the command is empty"));
+        }
+        Ok(Self {
+            regex: Regex::new(&regex.replace("\\:", ":"))?,
+            command: command.to_owned(),
+            running: Arc::default(),
+            last_run: Cell::default(),
+        })
+    }
+
+    /// Whether the command may run again: a burst of matching frames would otherwise start as
+    /// many processes.
+    fn ready(&self, cooldown: Duration) -> bool {
+        !self.running.load(Ordering::Acquire)
+            && self
+                .last_run
+                .get()
+                .is_none_or(|last_run| last_run.elapsed() >= cooldown)
+    }
+
+    /// Runs the command through the shell, without waiting for it, with the line of the frame in
+    /// `DEFMT_LINE` and on stdin, and its log level in `DEFMT_LEVEL`.
+    fn run(&self, line: &str, level: &str) {
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        let child = command
+            .arg(&self.command)
+            .env("DEFMT_LINE", line)
+            .env("DEFMT_LEVEL", level)
+            .stdin(Stdio::piped())
+            .spawn();
+
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("(HOST) failed to run `{}`: {e}", self.command);
+                return;
+            }
+        };
+        self.running.store(true, Ordering::Release);
+        self.last_run.set(Some(Instant::now()));
+
+        let line = format!("{line}\n");
+        let running = self.running.clone();
+        thread::spawn(move || {
+            if let Some(mut stdin) = child.stdin.take() {
+                // the command may not read its stdin
+                stdin.write_all(line.as_bytes()).ok();
+            }
+            child.wait().ok();
+            running.store(false, Ordering::Release);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let hook =
+            Hook::parse("ERROR.*(timeout|overrun):notify-send \"defmt: $DEFMT_LINE\"").unwrap();
+        assert!(hook.regex.is_match("ERROR uart overrun"));
+        assert_eq!(hook.command, "notify-send \"defmt: $DEFMT_LINE\"");
+
+        let hook = Hook::parse(r"temp\: \d+:echo hot").unwrap();
+        assert!(hook.regex.is_match("INFO temp: 95"));
+        assert_eq!(hook.command, "echo hot");
+
+        assert!(Hook::parse("no command").is_err());
+        assert!(Hook::parse("regex:").is_err());
+        assert!(Hook::parse("(:echo").is_err());
+    }
+
+    #[test]
+    fn ready() {
+        let hook = Hook::parse("ERROR:echo").unwrap();
+        assert!(hook.ready(Duration::from_secs(60)));
+
+        hook.running.store(true, Ordering::Release);
+        assert!(!hook.ready(Duration::ZERO));
+        hook.running.store(false, Ordering::Release);
+
+        hook.last_run.set(Some(Instant::now()));
+        assert!(!hook.ready(Duration::from_secs(60)));
+        assert!(hook.ready(Duration::ZERO));
+    }
+}
@@ -1,31 +1,157 @@
 use std::{
+    collections::hash_map::RandomState,
     env, fs,
+    hash::{BuildHasher, Hasher},
     io::{self, Read},
+    mem,
+    num::NonZeroU64,
     path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
 };
 
 use anyhow::anyhow;
-use clap::Parser;
-use defmt_decoder::{DecodeError, Frame, Locations, Table};
+use clap::{Parser, Subcommand};
+use defmt_decoder::{
+    redact::{Action, Redaction, Rule},
+    DecodeError, Frame, Locations, Reassembler, StreamDecoder, Svd, Table, TableSelector,
+};
+use hooks::Hooks;
+
+mod analyze;
+mod armor;
+mod capture;
+mod columns;
+mod diff;
+mod hooks;
+mod watch;
 
 /// Prints defmt-encoded logs to stdout
 #[derive(Parser)]
 #[command(name = "defmt-print")]
 struct Opts {
-    #[arg(short, required = true, conflicts_with("version"))]
+    /// ELF file, or a directory of ELF files to choose from using the handshake frame sent by
+    /// `defmt::handshake!`
+    #[arg(short, required = true, conflicts_with_all(["version", "json_schema"]))]
     elf: Option<PathBuf>,
 
+    /// Text encoding of the input, for streams that are not sent as raw bytes
+    #[arg(long, value_enum, value_name = "ENCODING")]
+    decode: Option<armor::Encoding>,
+
+    /// Reads the input from the CSV export of the UART analyzer of a logic analyzer, e.g. Saleae
+    /// Logic or PulseView
+    #[arg(long, conflicts_with = "decode")]
+    csv: bool,
+
+    /// Decodes only the rows of the CSV input whose `name` or `channel` column is NAME
+    #[arg(long, value_name = "NAME", requires = "csv")]
+    csv_channel: Option<String>,
+
+    /// Reassembles frames sent in fragments by `defmt::Fragmenter`, e.g. over BLE or CAN
+    #[arg(long)]
+    fragmented: bool,
+
     #[arg(long)]
     json: bool,
 
+    /// Print the JSON Schema of the `--json` output and exit
+    #[arg(long)]
+    json_schema: bool,
+
     #[arg(long)]
     show_skipped_frames: bool,
 
+    /// CMSIS-SVD file used to print `reg:PERIPHERAL.REGISTER` values as their bit-fields
+    #[arg(long)]
+    svd: Option<PathBuf>,
+
+    /// Rate, in hertz, of the counter logged with the `tick` display hint
+    #[arg(long, value_name = "HZ")]
+    tick_rate: Option<NonZeroU64>,
+
+    /// Unix time, in seconds, at which the tick counter was zero; prints ticks as date time
+    #[arg(long, value_name = "UNIX_SECONDS", requires = "tick_rate")]
+    boot_epoch: Option<i64>,
+
+    /// Hides the arguments selected by RULE, a comma separated list of `statement=TEXT` (the log
+    /// statement contains TEXT) and `field=NAME` (the argument is written as `NAME: {}` or
+    /// `NAME={}`)
+    #[arg(long, value_name = "RULE")]
+    redact: Vec<String>,
+
+    /// Replaces the arguments selected by RULE with a salted hash of their value; see `--redact`
+    #[arg(long, value_name = "RULE")]
+    hash: Vec<String>,
+
+    /// Shows the values of `#[defmt(sensitive)]` fields instead of redacting them
+    #[arg(long)]
+    show_sensitive: bool,
+
+    /// Exits with an error on unknown indices, frames longer or shorter than their arguments and
+    /// malformed frames, which hint at a mismatch between the firmware and the ELF file, instead
+    /// of skipping them
+    #[arg(long)]
+    strict: bool,
+
+    /// Salt mixed into the values hashed with `--hash`; without it, a random salt is used, so the
+    /// hashes can't be correlated across sessions
+    #[arg(long, env = "DEFMT_REDACT_SALT", value_name = "SALT")]
+    redact_salt: Option<String>,
+
+    /// Runs COMMAND through the shell whenever the text of a frame matches REGEX, with the text in
+    /// `DEFMT_LINE` and on stdin, and the log level in `DEFMT_LEVEL`; escape colons in REGEX as `\:`
+    #[arg(long, value_name = "REGEX:COMMAND")]
+    on_match: Vec<String>,
+
+    /// Minimum time between two runs of the command of an `--on-match` rule; a rule whose command
+    /// is still running is never run again before it exits
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.0)]
+    on_match_cooldown: f64,
+
     #[arg(short, long)]
     verbose: bool,
 
     #[arg(short = 'V', long)]
     version: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rebuild the firmware when its sources change, and reload the ELF file whenever it changes
+    Watch(watch::WatchOpts),
+    /// List the log statements added, removed or changed since an older version of the ELF file,
+    /// and those whose index shifted; exits with status 1 if there are any
+    Diff(diff::DiffOpts),
+    /// Decode several sources, e.g. the serial ports of two boards, and print their frames side
+    /// by side, one column per source, in the order they were received
+    Columns(columns::ColumnsOpts),
+    /// Write the hash of the `.defmt` table into the ELF file, for `defmt::handshake!` to send; run
+    /// it after linking and before flashing
+    Stamp,
+    /// Decode stdin until it is closed, then print the time between consecutive frames of each
+    /// log statement, from their timestamps: minimum, average, maximum, jitter and a histogram
+    Analyze(analyze::AnalyzeOpts),
+}
+
+/// Input of the decoding loop
+enum Event {
+    /// Data received on stdin
+    Data(Vec<u8>),
+    /// The ELF file changed
+    Reload,
+    /// Stdin was closed
+    Eof,
+}
+
+/// Why the decoding loop stopped
+enum Stop {
+    Reload,
+    Eof,
 }
 
 const READ_BUFFER_SIZE: usize = 1024;
@@ -33,67 +159,451 @@ const READ_BUFFER_SIZE: usize = 1024;
 fn main() -> anyhow::Result<()> {
     let Opts {
         elf,
+        decode,
+        csv,
+        csv_channel,
+        fragmented,
         json,
+        json_schema,
         show_skipped_frames,
+        svd,
+        tick_rate,
+        boot_epoch,
+        redact,
+        hash,
+        show_sensitive,
+        strict,
+        redact_salt,
+        on_match,
+        on_match_cooldown,
         verbose,
         version,
+        command,
     } = Opts::parse();
 
     if version {
         return print_version();
     }
+    if json_schema {
+        println!("{}", defmt_decoder::log::json_schema());
+        return Ok(());
+    }
 
     defmt_decoder::log::init_logger(verbose, json, move |metadata| match verbose {
         false => defmt_decoder::log::is_defmt_frame(metadata), // We display *all* defmt frames, but nothing else.
         true => true,                                          // We display *all* frames.
     });
 
-    let bytes = fs::read(elf.unwrap())?;
+    let elf = elf.unwrap();
+    if let Some(Command::Diff(opts)) = &command {
+        return diff::run(opts, &elf);
+    }
+    if let Some(Command::Stamp) = &command {
+        return stamp(&elf);
+    }
+    let stream_opts = StreamOpts {
+        decode,
+        csv: csv.then_some(csv_channel),
+        fragmented,
+        show_skipped_frames: show_skipped_frames || verbose,
+        strict,
+    };
+    let current_dir = env::current_dir()?;
+    let cooldown = Duration::try_from_secs_f64(on_match_cooldown)
+        .map_err(|e| anyhow!("invalid `--on-match-cooldown`: {e}"))?;
+    let hooks = Hooks::parse(&on_match, cooldown)?;
+
+    let redact_salt = match redact_salt {
+        Some(salt) => salt,
+        // unsalted hashes of small values, e.g. PINs, are easily reversed by hashing all of them
+        None if !hash.is_empty() => {
+            eprintln!("(HOST) no `--redact-salt` given; hashing with a random salt");
+            random_salt()
+        }
+        None => String::new(),
+    };
+    let mut redaction = Redaction::new().salt(redact_salt);
+    for rule in &redact {
+        redaction = redaction.rule(Rule::parse(rule, Action::Redact)?);
+    }
+    for rule in &hash {
+        redaction = redaction.rule(Rule::parse(rule, Action::Hash)?);
+    }
 
-    let table = Table::parse(&bytes)?.ok_or_else(|| anyhow!(".defmt data not found"))?;
-    let locs = table.get_locations(&bytes)?;
+    let load = |path: &Path| -> anyhow::Result<Option<Firmware>> {
+        let bytes = fs::read(path)?;
 
-    let locs = if table.indices().all(|idx| locs.contains_key(&(idx as u64))) {
-        Some(locs)
-    } else {
-        log::warn!("(BUG) location info is incomplete; it will be omitted from the output");
-        None
+        let Some(mut table) = Table::parse(&bytes)? else {
+            return Ok(None);
+        };
+        if let Some(svd) = &svd {
+            table.set_svd(Svd::parse(&fs::read_to_string(svd)?)?);
+        }
+        if let Some(hz) = tick_rate {
+            table.set_tick_rate(hz);
+        }
+        if let Some(epoch) = boot_epoch {
+            table.set_boot_epoch(epoch);
+        }
+        table.set_redaction(redaction.clone());
+        table.set_show_sensitive(show_sensitive);
+        table.set_strict(strict);
+        let locs = table.get_locations(&bytes)?;
+
+        let locs = if table.indices().all(|idx| locs.contains_key(&(idx as u64))) {
+            Some(locs)
+        } else {
+            log::warn!("(BUG) location info is incomplete; it will be omitted from the output");
+            None
+        };
+
+        Ok(Some(Firmware {
+            path: path.to_owned(),
+            table,
+            locs,
+        }))
     };
 
-    let mut buf = [0; READ_BUFFER_SIZE];
-    let mut stream_decoder = table.new_stream_decoder();
+    if let Some(Command::Columns(opts)) = command {
+        let firmwares = load_firmwares(&elf, load)?;
+        return columns::run(opts, &firmwares, elf.is_dir(), &stream_opts, &hooks);
+    }
+    if let Some(Command::Analyze(opts)) = command {
+        let firmwares = load_firmwares(&elf, load)?;
+        return analyze::run(opts, &firmwares, elf.is_dir(), &stream_opts);
+    }
 
-    let current_dir = env::current_dir()?;
-    let mut stdin = io::stdin().lock();
+    let (events, received) = mpsc::channel();
+    if let Some(Command::Watch(opts)) = command {
+        watch::spawn(opts, elf.clone(), events.clone())?;
+    }
+    spawn_stdin_reader(events);
+
+    let mut firmwares = load_firmwares(&elf, load)?;
+    loop {
+        let stream = Stream::new(&firmwares, elf.is_dir(), &stream_opts);
+        match decode_stream(stream, &received, &hooks, &current_dir)? {
+            Stop::Eof => return Ok(()),
+            // e.g. a half-written ELF file fails to load; the next change triggers another reload
+            Stop::Reload => match load_firmwares(&elf, load) {
+                Ok(reloaded) => {
+                    eprintln!("(HOST) ELF file changed; reloaded");
+                    firmwares = reloaded;
+                }
+                Err(e) => log::warn!(
+                    "(HOST) could not reload the ELF file, keeping the previous one: {e}"
+                ),
+            },
+        }
+    }
+}
+
+/// An ELF file and its decoding table
+pub(crate) struct Firmware {
+    path: PathBuf,
+    table: Table,
+    locs: Option<Locations>,
+}
+
+/// Loads the ELF file `elf`, or the ELF files in it if it is a directory.
+fn load_firmwares(
+    elf: &Path,
+    load: impl Fn(&Path) -> anyhow::Result<Option<Firmware>>,
+) -> anyhow::Result<Vec<Firmware>> {
+    if elf.is_dir() {
+        load_dir(elf, load)
+    } else {
+        let firmware = load(elf)?.ok_or_else(|| anyhow!(".defmt data not found"))?;
+        Ok(vec![firmware])
+    }
+}
+
+/// Loads the ELF files in `dir` that contain defmt data; other files are skipped.
+fn load_dir(
+    dir: &Path,
+    load: impl Fn(&Path) -> anyhow::Result<Option<Firmware>>,
+) -> anyhow::Result<Vec<Firmware>> {
+    let mut firmwares = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Ok(Some(firmware)) = load(&path) else {
+            continue;
+        };
+        if firmware.table.handshake_frames().next().is_none() {
+            eprintln!(
+                "(HOST) {} does not send a handshake frame; it will never be selected",
+                path.display()
+            );
+        } else if firmware.table.stamped_hash() != Some(firmware.table.hash()) {
+            eprintln!(
+                "(HOST) the table hash of {0} is missing or outdated; run `defmt-print stamp -e {0}` \
+                 before flashing it",
+                path.display()
+            );
+        }
+        firmwares.push(firmware);
+    }
+
+    if firmwares.is_empty() {
+        return Err(anyhow!(
+            "no ELF file with .defmt data found in {}",
+            dir.display()
+        ));
+    }
+    Ok(firmwares)
+}
+
+/// Returns a salt that differs between sessions.
+fn random_salt() -> String {
+    // `RandomState` is seeded from the OS's random number generator
+    let hash = RandomState::new().build_hasher().finish();
+    format!("{hash:016x}")
+}
 
+/// Writes the hash of the table into the ELF file `elf`.
+fn stamp(elf: &Path) -> anyhow::Result<()> {
+    let mut bytes = fs::read(elf)?;
+    let hash = Table::stamp(&mut bytes)?;
+    fs::write(elf, bytes)?;
+    eprintln!(
+        "(HOST) table hash {hash:#018x} written into {}",
+        elf.display()
+    );
+    Ok(())
+}
+
+/// Reads stdin on a separate thread, so that the ELF file can be reloaded while waiting for data.
+fn spawn_stdin_reader(events: Sender<Event>) {
+    thread::spawn(move || {
+        let mut buf = [0; READ_BUFFER_SIZE];
+        let mut stdin = io::stdin().lock();
+        loop {
+            // if 0 bytes where read, we reached EOF, so quit
+            let event = match stdin.read(&mut buf) {
+                Ok(0) | Err(_) => Event::Eof,
+                Ok(n) => Event::Data(buf[..n].to_vec()),
+            };
+            let eof = matches!(event, Event::Eof);
+            if events.send(event).is_err() || eof {
+                break;
+            }
+        }
+    });
+}
+
+/// Decodes the data read from stdin with `stream`, logging the decoded frames and running the
+/// `hooks` they match.
+fn decode_stream(
+    mut stream: Stream,
+    received: &Receiver<Event>,
+    hooks: &Hooks,
+    current_dir: &Path,
+) -> anyhow::Result<Stop> {
+    let mut on_frame = |frame: &Frame, firmware: &Firmware| {
+        forward_to_logger(frame, location_info(&firmware.locs, frame, current_dir));
+        hooks.frame(frame);
+    };
     loop {
-        // read from stdin and push it to the decoder
-        let n = stdin.read(&mut buf)?;
-        // if 0 bytes where read, we reached EOF, so quit
-        if n == 0 {
-            break Ok(());
+        let data = match received.recv() {
+            Ok(Event::Data(data)) => data,
+            Ok(Event::Reload) => return Ok(Stop::Reload),
+            Ok(Event::Eof) | Err(_) => {
+                stream.finish(&mut on_frame)?;
+                return Ok(Stop::Eof);
+            }
+        };
+        stream.received(&data, &mut on_frame)?;
+    }
+}
+
+/// How the data of a stream is decoded
+pub(crate) struct StreamOpts {
+    decode: Option<armor::Encoding>,
+    /// Whether the input is a logic analyzer CSV export, and the channel to decode
+    csv: Option<Option<String>>,
+    fragmented: bool,
+    show_skipped_frames: bool,
+    strict: bool,
+}
+
+/// Decoding state of a stream of data, decoded with the table of one of `firmwares`
+pub(crate) struct Stream<'f> {
+    firmwares: &'f [Firmware],
+    capture: Option<capture::Decoder>,
+    armor: Option<armor::Decoder>,
+    reassembler: Option<Reassembler>,
+    /// Chooses the table whose handshake frame was received last; data before the first
+    /// handshake frame is dropped
+    selector: Option<TableSelector>,
+    current: Option<usize>,
+    decoder: Option<Box<dyn StreamDecoder + 'f>>,
+    show_skipped_frames: bool,
+    strict: bool,
+    decoded: Vec<u8>,
+    reassembled: Vec<u8>,
+}
+
+impl<'f> Stream<'f> {
+    /// Creates a stream decoded with the table of one of `firmwares`, chosen by handshake frame
+    /// if `select` is set, or else with the first one.
+    pub(crate) fn new(firmwares: &'f [Firmware], select: bool, opts: &StreamOpts) -> Self {
+        let (selector, current) = match select {
+            true => (
+                Some(TableSelector::new(firmwares.iter().map(|f| &f.table))),
+                None,
+            ),
+            false => (None, Some(0)),
+        };
+        Self {
+            firmwares,
+            capture: opts.csv.clone().map(capture::Decoder::new),
+            armor: opts.decode.map(armor::Decoder::new),
+            reassembler: opts.fragmented.then(Reassembler::new),
+            selector,
+            current,
+            decoder: current.map(|i| firmwares[i].table.new_stream_decoder()),
+            show_skipped_frames: opts.show_skipped_frames,
+            strict: opts.strict,
+            decoded: Vec::new(),
+            reassembled: Vec::new(),
+        }
+    }
+
+    /// Pushes received data, calling `on_frame` with every frame decoded and the firmware it was
+    /// decoded with.
+    pub(crate) fn received(
+        &mut self,
+        data: &[u8],
+        on_frame: impl FnMut(&Frame, &'f Firmware),
+    ) -> anyhow::Result<()> {
+        if self.capture.is_none() && self.armor.is_none() {
+            return self.decode(data, on_frame);
+        }
+
+        let mut decoded = mem::take(&mut self.decoded);
+        decoded.clear();
+        if let Some(capture) = &mut self.capture {
+            capture.decode(data, &mut decoded)?;
+        } else if let Some(armor) = &mut self.armor {
+            armor.decode(data, &mut decoded)?;
+        }
+        let result = self.decode(&decoded, on_frame);
+        self.decoded = decoded;
+        result
+    }
+
+    /// Decodes what is left of the input once it ended, e.g. the last row of a CSV file without a
+    /// line ending, and the frames the stream decoder still holds back.
+    pub(crate) fn finish(
+        &mut self,
+        mut on_frame: impl FnMut(&Frame, &'f Firmware),
+    ) -> anyhow::Result<()> {
+        if let Some(capture) = &mut self.capture {
+            let mut decoded = vec![];
+            capture.finish(&mut decoded)?;
+            self.decode(&decoded, &mut on_frame)?;
         }
-        stream_decoder.received(&buf[..n]);
+        if let Some(decoder) = &mut self.decoder {
+            decoder.finish();
+        }
+        self.decode_frames(&mut on_frame)
+    }
+
+    fn decode(
+        &mut self,
+        data: &[u8],
+        mut on_frame: impl FnMut(&Frame, &'f Firmware),
+    ) -> anyhow::Result<()> {
+        let Some(reassembler) = &mut self.reassembler else {
+            return self.select(data, &mut on_frame);
+        };
+
+        let dropped = reassembler.dropped_frames();
+        let mut reassembled = mem::take(&mut self.reassembled);
+        reassembled.clear();
+        reassembler.received(data, &mut reassembled);
+        if self.show_skipped_frames && reassembler.dropped_frames() > dropped {
+            println!("(HOST) frame with lost fragments skipped");
+        }
+        let result = self.select(&reassembled, &mut on_frame);
+        self.reassembled = reassembled;
+        result
+    }
+
+    /// Passes `data` on to the stream decoder of the selected table, switching tables on
+    /// handshake frames.
+    fn select(
+        &mut self,
+        data: &[u8],
+        on_frame: &mut impl FnMut(&Frame, &'f Firmware),
+    ) -> anyhow::Result<()> {
+        match self
+            .selector
+            .as_mut()
+            .and_then(|selector| selector.received(data))
+        {
+            Some(handshake) => {
+                // the data preceding the handshake frame was sent with the table selected before
+                if let Some(decoder) = &mut self.decoder {
+                    decoder.received(&handshake.preceding);
+                }
+                self.decode_frames(on_frame)?;
+
+                let i = handshake.table;
+                if self.current != Some(i) {
+                    eprintln!(
+                        "(HOST) handshake received; decoding with {}",
+                        self.firmwares[i].path.display()
+                    );
+                    self.current = Some(i);
+                }
+                // start over, the device may have been reset or replaced
+                let mut decoder = self.firmwares[i].table.new_stream_decoder();
+                decoder.received(&handshake.data);
+                self.decoder = Some(decoder);
+            }
+            None => {
+                if let Some(decoder) = &mut self.decoder {
+                    decoder.received(data);
+                }
+            }
+        }
+        self.decode_frames(on_frame)
+    }
+
+    /// Decodes the frames received by the stream decoder of the selected table.
+    fn decode_frames(
+        &mut self,
+        on_frame: &mut impl FnMut(&Frame, &'f Firmware),
+    ) -> anyhow::Result<()> {
+        let (Some(i), Some(decoder)) = (self.current, &mut self.decoder) else {
+            return Ok(());
+        };
+        let firmware = &self.firmwares[i];
 
-        // decode the received data
         loop {
-            match stream_decoder.decode() {
-                Ok(frame) => forward_to_logger(&frame, location_info(&locs, &frame, &current_dir)),
-                Err(DecodeError::UnexpectedEof) => break,
-                Err(DecodeError::Malformed) => match table.encoding().can_recover() {
-                    // if recovery is impossible, abort
+            match decoder.decode() {
+                Ok(frame) => on_frame(&frame, firmware),
+                Err(DecodeError::UnexpectedEof) => return Ok(()),
+                Err(DecodeError::Malformed) => match firmware.table.encoding().can_recover() {
+                    // if recovery is impossible or not wanted, abort
                     false => return Err(DecodeError::Malformed.into()),
+                    true if self.strict => return Err(DecodeError::Malformed.into()),
                     // if recovery is possible, skip the current frame and continue with new data
                     true => {
                         // bug: https://github.com/rust-lang/rust-clippy/issues/9810
                         #[allow(clippy::print_literal)]
-                        if show_skipped_frames || verbose {
+                        if self.show_skipped_frames {
                             println!("(HOST) malformed frame skipped");
                             println!("└─ {} @ {}:{}", env!("CARGO_PKG_NAME"), file!(), line!());
                         }
                         continue;
                     }
                 },
+                Err(e) => return Err(e.into()),
             }
         }
     }
@@ -0,0 +1,89 @@
+//! Rebuilding the firmware when its sources change, for `defmt-print watch`
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc::Sender,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use clap::Args;
+
+use crate::Event;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Args)]
+pub(crate) struct WatchOpts {
+    /// Files or directories to watch for changes
+    #[arg(long = "path", value_name = "PATH", default_values = ["src", "Cargo.toml"])]
+    paths: Vec<PathBuf>,
+
+    /// Command rebuilding the firmware when a watched file changes
+    #[arg(long, value_name = "CMD", default_value = "cargo build")]
+    command: String,
+}
+
+/// Spawns a thread that runs the build command whenever a watched file changes, and sends
+/// [`Event::Reload`] whenever the ELF file changes.
+pub(crate) fn spawn(opts: WatchOpts, elf: PathBuf, events: Sender<Event>) -> anyhow::Result<()> {
+    let mut command = opts.command.split_whitespace();
+    let program = command
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("the build command is empty"))?
+        .to_owned();
+    let args = command.map(str::to_owned).collect::<Vec<_>>();
+
+    let mut sources = last_modified(&opts.paths);
+    let mut elf_modified = last_modified(&[&elf]);
+
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let modified = last_modified(&opts.paths);
+        if modified != sources {
+            sources = modified;
+            eprintln!("(HOST) sources changed; running `{}`", opts.command);
+            match Command::new(&program).args(&args).status() {
+                Ok(status) if !status.success() => eprintln!("(HOST) build failed: {status}"),
+                Ok(_) => {}
+                Err(e) => eprintln!("(HOST) could not run `{program}`: {e}"),
+            }
+        }
+
+        let modified = last_modified(&[&elf]);
+        if modified != elf_modified {
+            elf_modified = modified;
+            if events.send(Event::Reload).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Returns the latest modification time of the given files and of all files in the given
+/// directories.
+fn last_modified(paths: &[impl AsRef<Path>]) -> Option<SystemTime> {
+    paths
+        .iter()
+        .filter_map(|path| walk(path.as_ref()).ok())
+        .flatten()
+        .max()
+}
+
+fn walk(path: &Path) -> io::Result<Option<SystemTime>> {
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(Some(metadata.modified()?));
+    }
+
+    let mut latest = None;
+    for entry in fs::read_dir(path)? {
+        latest = latest.max(walk(&entry?.path())?);
+    }
+    Ok(latest)
+}
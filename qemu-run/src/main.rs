@@ -1,12 +1,20 @@
 //! An alternative to the [`probe-run`](https://github.com/knurling-rs/probe-run) printer,
 //! used by [`defmt`](https://github.com/knurling-rs/defmt).
-//! Parses data sent by QEMU over semihosting (ARM Cortex-M only).
+//! Parses data sent by QEMU over semihosting (ARM Cortex-M and RISC-V).
 //! *Printers* are *host* programs that receive log data, format it and display it.
+//!
+//! Set `QEMU_RUN_TIMEOUT` to a number of seconds to kill QEMU, and fail, if the firmware runs for
+//! longer than that. Set `QEMU_RUN_FAIL_PATTERN` to fail as soon as a decoded frame contains the
+//! given text, e.g. `panicked at`. Set `QEMU_RUN_ARGS` to whitespace separated arguments for the
+//! semihosting command line, e.g. `skip-tags=slow` to select `defmt-test` tests by tag.
 
 use std::{
     env, fs,
     io::Read as _,
     process::{self, Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, bail};
@@ -21,6 +29,11 @@ fn main() -> Result<(), anyhow::Error> {
     })
 }
 
+/// Exit code when `QEMU_RUN_FAIL_PATTERN` matched, the same as a panicking Rust program
+const FAIL_PATTERN_EXIT_CODE: i32 = 101;
+/// Exit code when `QEMU_RUN_TIMEOUT` elapsed, the same as the `timeout` command
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
 fn notmain() -> Result<Option<i32>, anyhow::Error> {
     let args = env::args().skip(1 /* program name */).collect::<Vec<_>>();
 
@@ -38,24 +51,31 @@ fn notmain() -> Result<Option<i32>, anyhow::Error> {
     };
     let table = table?.ok_or_else(|| anyhow!("`.defmt` section not found"))?;
 
+    let timeout = match env::var("QEMU_RUN_TIMEOUT") {
+        Ok(secs) => Some(Duration::from_secs(secs.parse().map_err(|_| {
+            anyhow!("`QEMU_RUN_TIMEOUT` must be a number of seconds, found `{secs}`")
+        })?)),
+        Err(_) => None,
+    };
+    let fail_pattern = env::var("QEMU_RUN_FAIL_PATTERN").ok();
+    let semihosting_args = env::var("QEMU_RUN_ARGS").ok().map(|args| {
+        // the first argument is the program name, by convention
+        let args = [path.as_str()].into_iter().chain(args.split_whitespace());
+        args.map(str::to_owned).collect::<Vec<_>>()
+    });
+
+    let arch = Arch::detect(&bytes)?;
     let mut child = KillOnDrop(
-        Command::new("qemu-system-arm")
-            .args([
-                "-cpu",
-                "cortex-m3",
-                "-machine",
-                "lm3s6965evb",
-                "-nographic",
-                "-monitor",
-                "none",
-                "-semihosting-config",
-                "enable=on,target=native",
-                "-kernel",
-            ])
+        arch.command(semihosting_args.as_deref().unwrap_or_default())
             .arg(path)
             .stdout(Stdio::piped())
             .spawn()
-            .expect("Error running qemu-system-arm; perhaps you haven't installed it yet?"),
+            .unwrap_or_else(|_| {
+                panic!(
+                    "Error running {}; perhaps you haven't installed it yet?",
+                    arch.qemu()
+                )
+            }),
     );
 
     let mut stdout = child
@@ -64,40 +84,141 @@ fn notmain() -> Result<Option<i32>, anyhow::Error> {
         .take()
         .ok_or_else(|| anyhow!("failed to acquire child's stdout handle"))?;
 
+    // read on a separate thread, so that the timeout also fires while no data arrives
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut readbuf = [0; 256];
+        while let Ok(n @ 1..) = stdout.read(&mut readbuf) {
+            if sender.send(readbuf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
     let mut decoder = table.new_stream_decoder();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
 
-    let mut readbuf = [0; 256];
-    let exit_code;
     loop {
-        let n = stdout.read(&mut readbuf)?;
-        decoder.received(&readbuf[..n]);
-        decode(&mut *decoder)?;
+        let received = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(data) => {
+                decoder.received(&data);
+                if decode(&mut *decoder, fail_pattern.as_deref())? {
+                    eprintln!("frame matched `QEMU_RUN_FAIL_PATTERN`");
+                    return Ok(Some(FAIL_PATTERN_EXIT_CODE));
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                eprintln!("timed out after {}s", timeout.unwrap_or_default().as_secs());
+                return Ok(Some(TIMEOUT_EXIT_CODE));
+            }
+            // QEMU closed its stdout, i.e. it exited
+            Err(RecvTimeoutError::Disconnected) => {
+                decoder.finish();
+                if decode(&mut *decoder, fail_pattern.as_deref())? {
+                    eprintln!("frame matched `QEMU_RUN_FAIL_PATTERN`");
+                    return Ok(Some(FAIL_PATTERN_EXIT_CODE));
+                }
+                break;
+            }
+        }
+    }
+
+    let exit_code = child.0.wait()?.code();
+    Ok(exit_code)
+}
+
+/// Architecture of the firmware, read from its ELF header
+#[derive(Clone, Copy)]
+enum Arch {
+    Arm,
+    Riscv32,
+    Riscv64,
+}
 
-        if let Some(status) = child.0.try_wait()? {
-            exit_code = status.code();
+impl Arch {
+    fn detect(elf: &[u8]) -> Result<Self, anyhow::Error> {
+        const EM_ARM: u16 = 40;
+        const EM_RISCV: u16 = 243;
 
-            let mut data = Vec::new();
-            stdout.read_to_end(&mut data)?;
-            decoder.received(&data);
-            decode(&mut *decoder)?;
+        if elf.len() < 20 || elf[..4] != *b"\x7fELF" {
+            bail!("not an ELF file");
+        }
 
-            break;
+        let machine = [elf[18], elf[19]];
+        let machine = match elf[5] {
+            2 => u16::from_be_bytes(machine),
+            _ => u16::from_le_bytes(machine),
+        };
+        let is_64_bit = elf[4] == 2;
+
+        match machine {
+            EM_ARM => Ok(Arch::Arm),
+            EM_RISCV if is_64_bit => Ok(Arch::Riscv64),
+            EM_RISCV => Ok(Arch::Riscv32),
+            _ => bail!("unsupported architecture (ELF machine {machine}); expected ARM or RISC-V"),
         }
     }
 
-    Ok(exit_code)
+    fn qemu(self) -> &'static str {
+        match self {
+            Arch::Arm => "qemu-system-arm",
+            Arch::Riscv32 => "qemu-system-riscv32",
+            Arch::Riscv64 => "qemu-system-riscv64",
+        }
+    }
+
+    fn command(self, semihosting_args: &[String]) -> Command {
+        let mut command = Command::new(self.qemu());
+        match self {
+            Arch::Arm => {
+                command.args(["-cpu", "cortex-m3", "-machine", "lm3s6965evb", "-nographic"])
+            }
+            // the `virt` UART would otherwise be mixed into the defmt stream on stdout
+            Arch::Riscv32 | Arch::Riscv64 => command.args([
+                "-machine", "virt", "-bios", "none", "-display", "none", "-serial", "none",
+            ]),
+        };
+        let mut semihosting_config = "enable=on,target=native".to_owned();
+        for arg in semihosting_args {
+            // commas separate the options; a literal one is escaped by doubling it
+            semihosting_config.push_str(&format!(",arg={}", arg.replace(',', ",,")));
+        }
+        command.args([
+            "-monitor",
+            "none",
+            "-semihosting-config",
+            &semihosting_config,
+            "-kernel",
+        ]);
+        command
+    }
 }
 
-fn decode(decoder: &mut dyn StreamDecoder) -> Result<(), DecodeError> {
+/// Decodes and prints all complete frames; returns `true` if one of them contains `fail_pattern`.
+fn decode(
+    decoder: &mut dyn StreamDecoder,
+    fail_pattern: Option<&str>,
+) -> Result<bool, DecodeError> {
     loop {
         match decoder.decode() {
             Ok(frame) => {
-                println!("{}", frame.display(true))
+                let frame = frame.display(true).to_string();
+                println!("{frame}");
+                if fail_pattern.is_some_and(|pattern| frame.contains(pattern)) {
+                    return Ok(true);
+                }
             }
-            Err(DecodeError::UnexpectedEof) => return Ok(()),
-            Err(DecodeError::Malformed) => {
-                eprintln!("failed to decode defmt data");
-                return Err(DecodeError::Malformed);
+            Err(DecodeError::UnexpectedEof) => return Ok(false),
+            Err(e) => {
+                eprintln!("failed to decode defmt data: {e}");
+                return Err(e);
             }
         }
     }
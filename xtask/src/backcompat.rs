@@ -1,6 +1,10 @@
 /*
 Backward compatibility test broke? Here's what needs to happen.
 
+Every revision in `REVISIONS_UNDER_TEST` (or passed with `--revision`) is checked in both directions:
+the current firmware is decoded by that revision's `qemu-run`, and that revision's firmware is
+decoded by the current `qemu-run`.
+
 # To land a pull-request (PR)
 
 Temporarily disable the test.
@@ -16,7 +20,8 @@ Temporarily disable the test.
 
 - create a PR that
   - sets `DISABLED` constant back to `false`
-  - updates `REVISION_UNDER_TEST` constant to point to the hash of the merge commit of PR <number>
+  - replaces the revisions in the `REVISIONS_UNDER_TEST` constant with the hash of the merge commit
+    of PR <number>; add the tag of every later release that keeps the wire format
 
 ## Second issue (if it doesn't already exist): "multiple decoder support"
 
@@ -35,18 +40,28 @@ use anyhow::anyhow;
 use colored::Colorize as _;
 use tempfile::TempDir;
 
-use crate::{ALL_ERRORS, ALL_SNAPSHOT_TESTS, SNAPSHOT_TESTS_DIRECTORY};
+use crate::{snapshot, ALL_ERRORS, ALL_SNAPSHOT_TESTS, SNAPSHOT_TESTS_DIRECTORY, SNAPSHOT_TIMEOUT};
 
 const DISABLED: bool = false;
 
-// use this format: PR <number> - <what feature / change broke compatibility>
-// PR #747 - Bump wire format
-const REVISION_UNDER_TEST: &str = "0e92d3a88aa472377b964979f522829d961d8986";
+// use this format: PR <number> - <what feature / change broke compatibility>, or the release
+// the revision (a commit hash or a release tag) stands for
+const REVISIONS_UNDER_TEST: &[&str] = &[
+    // PR #747 - Bump wire format
+    "0e92d3a88aa472377b964979f522829d961d8986",
+    // PR #748 - Release `defmt-v0.3.4`, `defmt-decoder-v0.3.6` and `defmt-print-v0.3.4`
+    "defmt-v0.3.4",
+];
+
+/// Snapshot tests using features that the decoders of `REVISIONS_UNDER_TEST` don't support, e.g.
+/// the symbols of `intern-dedup`
+const NEW_DECODER_SNAPSHOT_TESTS: &[&str] = &["dedup"];
 
 // the target name is in `firmware/qemu/.cargo/config.toml` but it'd be hard to extract it from that file
 const RUNNER_ENV_VAR: &str = "CARGO_TARGET_THUMBV7M_NONE_EABI_RUNNER";
 
-pub fn test() {
+/// Tests against `revisions`, or against `REVISIONS_UNDER_TEST` if it is empty.
+pub fn test(revisions: &[String]) {
     if DISABLED {
         println!("⚠️  backcompat (DISABLED)");
         return;
@@ -54,59 +69,132 @@ pub fn test() {
 
     println!("🧪 backcompat");
 
-    println!("building old qemu-run.. (git revision: {REVISION_UNDER_TEST})");
-    let qemu_run = match QemuRun::build() {
+    let revisions = match revisions.is_empty() {
+        true => REVISIONS_UNDER_TEST.iter().map(|rev| rev.to_string()).collect(),
+        false => revisions.to_vec(),
+    };
+
+    println!("building current qemu-run..");
+    let current = match QemuRun::build(repo_path()) {
         Ok(qemu_run) => qemu_run,
+        Err(e) => {
+            eprintln!("error building current qemu-run: {e}");
+            ALL_ERRORS
+                .lock()
+                .unwrap()
+                .push("backcompat (building current qemu-run)".to_string());
+            return;
+        }
+    };
+
+    for revision in &revisions {
+        test_revision(revision, &current);
+    }
+}
+
+fn test_revision(revision: &str, current: &QemuRun) {
+    println!("building old qemu-run.. (git revision: {revision})");
+    let build = || -> anyhow::Result<_> {
+        let checkout = Checkout::new(revision)?;
+        let qemu_run = QemuRun::build(checkout.path())?;
+        Ok((checkout, qemu_run))
+    };
+    let (checkout, old) = match build() {
+        Ok(it) => it,
         Err(e) => {
             // only print build errors so the user can fix those manually if needed
             eprintln!("error building old qemu-run: {e}");
             ALL_ERRORS
                 .lock()
                 .unwrap()
-                .push("backcompat (building qemu-run)".to_string());
+                .push(format!("backcompat (building qemu-run at {revision})"));
             return;
         }
     };
 
+    let firmware = repo_path().join(SNAPSHOT_TESTS_DIRECTORY);
     for snapshot_test in ALL_SNAPSHOT_TESTS {
+        if NEW_DECODER_SNAPSHOT_TESTS.contains(&snapshot_test) {
+            continue;
+        }
         super::do_test(
-            || qemu_run.run_snapshot(snapshot_test),
-            "backcompat (see xtask/src/backcompat.rs for FIXME instructions)",
+            || old.run_snapshot(&firmware, snapshot_test),
+            &format!(
+                "backcompat ({revision} decoder; see xtask/src/backcompat.rs for FIXME instructions)"
+            ),
+        );
+    }
+
+    let old_firmware = checkout.path().join(SNAPSHOT_TESTS_DIRECTORY);
+    for snapshot_test in ALL_SNAPSHOT_TESTS {
+        // snapshot tests added after `revision` don't exist in its checkout
+        if !checkout.has_snapshot(snapshot_test) {
+            continue;
+        }
+        super::do_test(
+            || current.run_snapshot(&old_firmware, snapshot_test),
+            &format!(
+                "backcompat ({revision} firmware; see xtask/src/backcompat.rs for FIXME instructions)"
+            ),
         );
     }
 }
 
-struct QemuRun {
-    executable_path: PathBuf,
-    _tempdir: TempDir,
+fn repo_path() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap()
 }
 
-impl QemuRun {
-    fn build() -> anyhow::Result<Self> {
+/// A clone of this repository, checked out at an older revision
+struct Checkout {
+    tempdir: TempDir,
+}
+
+impl Checkout {
+    fn new(revision: &str) -> anyhow::Result<Self> {
         let tempdir = tempfile::tempdir()?;
+        clone_repo(tempdir.path(), revision)?;
+        Ok(Self { tempdir })
+    }
+
+    fn path(&self) -> &Path {
+        self.tempdir.path()
+    }
 
-        let tempdir_path = tempdir.path();
-        clone_repo(tempdir_path)?;
-        let executable_path = build_qemu_run(tempdir_path)?;
+    fn has_snapshot(&self, name: &str) -> bool {
+        let firmware = self.path().join(SNAPSHOT_TESTS_DIRECTORY);
+        firmware.join("src/bin").join(format!("{name}.rs")).exists()
+            || firmware.join("tests").join(format!("{name}.rs")).exists()
+    }
+}
+
+struct QemuRun {
+    executable_path: PathBuf,
+}
 
-        Ok(Self {
-            executable_path,
-            _tempdir: tempdir,
-        })
+impl QemuRun {
+    fn build(workspace: &Path) -> anyhow::Result<Self> {
+        let executable_path = build_qemu_run(workspace)?;
+        Ok(Self { executable_path })
     }
 
-    fn run_snapshot(&self, name: &str) -> anyhow::Result<()> {
+    fn run_snapshot(&self, firmware: &Path, name: &str) -> anyhow::Result<()> {
         println!("{}", name.bold());
 
         let is_test = name.contains("test");
         let command = if is_test { "tt" } else { "rb" };
+        let mut args = vec!["-q", command, name];
+        let features = snapshot::features(name);
+        if !features.is_empty() {
+            args.extend_from_slice(&["--features", features]);
+        }
 
         run_silently(
             Command::new("cargo")
-                .args(["-q", command, name])
-                .current_dir(SNAPSHOT_TESTS_DIRECTORY)
-                .env(RUNNER_ENV_VAR, self.path()),
-            || anyhow!("{}", name.to_string()),
+                .args(&args)
+                .current_dir(firmware)
+                .env(RUNNER_ENV_VAR, self.path())
+                .env("QEMU_RUN_TIMEOUT", SNAPSHOT_TIMEOUT),
+            || anyhow!("{}", name),
         )?;
 
         Ok(())
@@ -117,12 +205,11 @@ impl QemuRun {
     }
 }
 
-fn clone_repo(tempdir: &Path) -> anyhow::Result<()> {
-    let repo_path = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+fn clone_repo(tempdir: &Path, revision: &str) -> anyhow::Result<()> {
     run_silently(
         Command::new("git")
             .arg("clone")
-            .arg(repo_path)
+            .arg(repo_path())
             .arg(".")
             .current_dir(tempdir),
         || anyhow!("`git clone` failed"),
@@ -130,7 +217,7 @@ fn clone_repo(tempdir: &Path) -> anyhow::Result<()> {
 
     run_silently(
         Command::new("git")
-            .args(["reset", "--hard", REVISION_UNDER_TEST])
+            .args(["reset", "--hard", revision])
             .current_dir(tempdir),
         || anyhow!("`git reset` failed"),
     )?;
@@ -138,15 +225,15 @@ fn clone_repo(tempdir: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn build_qemu_run(tempdir: &Path) -> anyhow::Result<PathBuf> {
+fn build_qemu_run(workspace: &Path) -> anyhow::Result<PathBuf> {
     run_silently(
         Command::new("cargo")
             .args(["build", "-p", "qemu-run"])
-            .current_dir(tempdir),
+            .current_dir(workspace),
         || anyhow!("`cargo build` failed"),
     )?;
 
-    let mut executable_path = tempdir.to_owned();
+    let mut executable_path = workspace.to_owned();
     executable_path.push("target");
     executable_path.push("debug");
     executable_path.push("qemu-run");
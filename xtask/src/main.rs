@@ -9,7 +9,9 @@ use anyhow::anyhow;
 use clap::{Parser, Subcommand};
 
 use crate::{
-    snapshot::{test_snapshot, Snapshot, ALL_SNAPSHOT_TESTS, SNAPSHOT_TESTS_DIRECTORY},
+    snapshot::{
+        test_snapshot, Snapshot, ALL_SNAPSHOT_TESTS, SNAPSHOT_TESTS_DIRECTORY, SNAPSHOT_TIMEOUT,
+    },
     utils::{run_capturing_stdout, run_command, rustc_is_nightly},
 };
 
@@ -33,7 +35,13 @@ struct Options {
 #[allow(clippy::enum_variant_names)]
 enum TestCommand {
     TestAll,
-    TestBackcompat,
+    /// Test wire-format compatibility with previously released revisions, in both directions
+    TestBackcompat {
+        /// Git revision to test against; can be given several times [default: the revisions in
+        /// `xtask/src/backcompat.rs`]
+        #[arg(long = "revision", value_name = "REV")]
+        revisions: Vec<String>,
+    },
     TestBook,
     TestCross,
     TestHost,
@@ -55,7 +63,7 @@ fn main() -> anyhow::Result<()> {
 
     match opt.cmd {
         TestCommand::TestBook => test_book(),
-        TestCommand::TestBackcompat => backcompat::test(),
+        TestCommand::TestBackcompat { revisions } => backcompat::test(&revisions),
         TestCommand::TestHost => test_host(opt.deny_warnings),
         TestCommand::TestLint => test_lint(),
         TestCommand::TestUi => test_ui(),
@@ -72,7 +80,7 @@ fn main() -> anyhow::Result<()> {
                     test_host(opt.deny_warnings);
                     test_cross(opt.deny_warnings);
                     test_snapshot(false, None);
-                    backcompat::test();
+                    backcompat::test(&[]);
                     test_book();
                     test_lint();
                 }
@@ -108,19 +116,52 @@ fn test_host(deny_warnings: bool) {
         false => vec![],
     };
 
-    for feat in ["", "unstable-test", "alloc"] {
+    for feat in [
+        "",
+        "unstable-test",
+        "alloc",
+        "embedded-time",
+        "serde",
+        "snapshot",
+        "task-context",
+    ] {
         do_test(
             || run_command("cargo", &["check", "--features", feat], None, &env),
             "host",
         );
     }
 
-    for feat in ["unstable-test", "unstable-test,alloc"] {
+    for feat in [
+        "unstable-test",
+        "unstable-test,alloc",
+        "unstable-test,embedded-time",
+        "unstable-test,serde",
+        "unstable-test,caller-location",
+        "unstable-test,snapshot",
+        "unstable-test,task-context",
+    ] {
         do_test(
             || run_command("cargo", &["test", "--features", feat], None, &env),
             "host",
         );
     }
+
+    do_test(
+        || {
+            run_command(
+                "cargo",
+                &["test", "--features", "stack-usage"],
+                Some("firmware/defmt-test"),
+                &env,
+            )
+        },
+        "host",
+    );
+
+    do_test(
+        || run_command("cargo", &["test"], Some("firmware/defmt-rtt"), &env),
+        "host",
+    );
 }
 
 fn test_cross(deny_warnings: bool) {
@@ -209,6 +250,27 @@ fn test_cross(deny_warnings: bool) {
         "cross",
     );
 
+    do_test(
+        || {
+            run_command(
+                "cargo",
+                &[
+                    "check",
+                    "--target",
+                    "riscv32imac-unknown-none-elf",
+                    "-p",
+                    "defmt-semihosting",
+                    "-p",
+                    "firmware",
+                    "--bins",
+                ],
+                Some("firmware"),
+                &env,
+            )
+        },
+        "cross",
+    );
+
     do_test(
         || {
             run_command(
@@ -338,7 +400,8 @@ fn test_lint() {
 }
 
 fn test_ui() {
-    println!("🧪 lint");
+    println!("🧪 ui");
+    // the UI tests of `defmt` run as part of `test_host`
     do_test(
         || run_command("cargo", &["test"], Some("firmware/defmt-test/macros"), &[]),
         "ui",
@@ -1,7 +1,8 @@
-use std::{process::Command, str::FromStr};
+use std::{fs, process::Command, str::FromStr};
 
 use anyhow::{anyhow, Context};
 use colored::Colorize;
+use defmt_decoder::Table;
 use similar::{ChangeTag, TextDiff};
 
 use crate::{
@@ -10,7 +11,9 @@ use crate::{
 };
 
 pub const SNAPSHOT_TESTS_DIRECTORY: &str = "firmware/qemu";
-pub const ALL_SNAPSHOT_TESTS: [&str; 12] = [
+/// Seconds after which `qemu-run` kills a snapshot test that hangs
+pub const SNAPSHOT_TIMEOUT: &str = "60";
+pub const ALL_SNAPSHOT_TESTS: [&str; 13] = [
     "log",
     "bitflags",
     "timestamp",
@@ -23,8 +26,12 @@ pub const ALL_SNAPSHOT_TESTS: [&str; 12] = [
     "hints",
     "hints_inner",
     "dbg",
+    "dedup",
 ];
 
+/// Snapshot tests that only run on ARM, because they use `defmt-test`
+const ARM_ONLY_SNAPSHOT_TESTS: [&str; 1] = ["defmt-test"];
+
 #[derive(Clone, Debug)]
 pub struct Snapshot(String);
 
@@ -56,7 +63,7 @@ pub fn test_snapshot(overwrite: bool, snapshot: Option<Snapshot>) {
         None => test_all_snapshots(overwrite),
         Some(snapshot) => {
             do_test(
-                || test_single_snapshot(snapshot.name(), "", overwrite),
+                || test_single_snapshot(snapshot.name(), features(snapshot.name()), overwrite, false),
                 "qemu/snapshot",
             );
         }
@@ -71,27 +78,82 @@ fn test_all_snapshots(overwrite: bool) {
     }
 
     for test in tests {
-        let features = match test {
-            "alloc" => "alloc",
-            "net" => "ip_in_core",
-            _ => "",
-        };
-
         do_test(
-            || test_single_snapshot(test, features, overwrite),
+            || test_single_snapshot(test, features(test), overwrite, false),
             "qemu/snapshot",
         );
     }
+
+    // the output on RISC-V must match the one on ARM, so it is never overwritten from RISC-V
+    if overwrite {
+        return;
+    }
+
+    do_test(|| test_dedup_across_crates(false), "qemu/snapshot");
+
+    println!("🧪 qemu/snapshot (RISC-V)");
+    for test in ALL_SNAPSHOT_TESTS {
+        if ARM_ONLY_SNAPSHOT_TESTS.contains(&test) {
+            continue;
+        }
+
+        do_test(
+            || test_single_snapshot(test, features(test), false, true),
+            "qemu/snapshot (RISC-V)",
+        );
+    }
+    do_test(|| test_dedup_across_crates(true), "qemu/snapshot (RISC-V)");
+}
+
+/// Returns the Cargo features the snapshot test `name` needs
+pub fn features(name: &str) -> &'static str {
+    match name {
+        "alloc" => "alloc",
+        "dedup" => "intern-dedup",
+        "net" => "ip_in_core",
+        _ => "",
+    }
+}
+
+/// Checks that interning the strings of the `dedup` snapshot test from a second crate changes
+/// neither its output nor the number of entries in its table; compares against the build of the
+/// preceding `dedup` run.
+fn test_dedup_across_crates(riscv: bool) -> anyhow::Result<()> {
+    let one_crate = table_len("dedup", riscv)?;
+    test_single_snapshot("dedup", "intern-dedup,dedup-helper", false, riscv)?;
+    let two_crates = table_len("dedup", riscv)?;
+
+    if one_crate == two_crates {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "dedup: the table grew from {one_crate} to {two_crates} entries when interning the same strings from a second crate"
+        ))
+    }
+}
+
+/// Returns the number of entries in the table of the snapshot test `name`, as last built
+fn table_len(name: &str, riscv: bool) -> anyhow::Result<usize> {
+    let target = match riscv {
+        false => "thumbv7m-none-eabi",
+        true => "riscv32imac-unknown-none-elf",
+    };
+    let path = format!("{SNAPSHOT_TESTS_DIRECTORY}/../target/{target}/debug/{name}");
+    let elf = fs::read(&path).with_context(|| path.clone())?;
+    let table = Table::parse(&elf)?.ok_or_else(|| anyhow!("{path}: no defmt table"))?;
+    Ok(table.raw_symbols().count())
 }
 
-fn test_single_snapshot(name: &str, features: &str, overwrite: bool) -> anyhow::Result<()> {
+/// Runs the snapshot test `name`; with `riscv`, it is built for RISC-V instead of ARM.
+fn test_single_snapshot(name: &str, features: &str, overwrite: bool, riscv: bool) -> anyhow::Result<()> {
     println!("{}", name.bold());
 
     let is_test = name.contains("test");
 
-    let mut args = match is_test {
-        true => vec!["-q", "tt", name],
-        false => vec!["-q", "rb", name],
+    let mut args = match (is_test, riscv) {
+        (true, _) => vec!["-q", "tt", name],
+        (false, false) => vec!["-q", "rb", name],
+        (false, true) => vec!["-q", "rb-riscv", name],
     };
 
     if !features.is_empty() {
@@ -102,6 +164,7 @@ fn test_single_snapshot(name: &str, features: &str, overwrite: bool) -> anyhow::
         Command::new("cargo")
             .args(&args)
             .env("DEFMT_LOG", "trace")
+            .env("QEMU_RUN_TIMEOUT", SNAPSHOT_TIMEOUT)
             .current_dir(SNAPSHOT_TESTS_DIRECTORY),
     )
     .with_context(|| name.to_string())?;
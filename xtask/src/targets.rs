@@ -12,6 +12,7 @@ pub fn install() -> anyhow::Result<Vec<String>> {
         "thumbv7em-none-eabi",
         "thumbv8m.base-none-eabi",
         "riscv32i-unknown-none-elf",
+        "riscv32imac-unknown-none-elf",
     ]
     .iter()
     .map(|item| item.to_string())